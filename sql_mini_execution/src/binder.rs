@@ -0,0 +1,612 @@
+use std::rc::Rc;
+
+use bigdecimal::ToPrimitive;
+use sql_mini_parser::{
+    commands::{
+        CompoundSelect, Distinct, OrderByItem, SelectField, SelectStatement, TableReference,
+    },
+    expr::{ColumnRef, Expression, FunctionCall},
+    value::Value,
+    window::WindowFunction,
+};
+
+use crate::{
+    compound, cte,
+    error::QueryExecutionError,
+    eval::{aggregate_column, Env},
+    functions::FunctionRegistry,
+    plan::LogicalPlan,
+    window::{collect_windows, window_column},
+};
+
+/// Collect every distinct aggregate call in `expr` into `aggregates`
+fn collect_aggregates(
+    expr: &Expression,
+    functions: &FunctionRegistry,
+    aggregates: &mut Vec<FunctionCall>,
+) {
+    match expr {
+        Expression::Function(call) if functions.is_aggregate(&call.name) => {
+            if !aggregates.contains(call) {
+                aggregates.push(call.clone());
+            }
+        }
+        expr => expr
+            .children()
+            .into_iter()
+            .for_each(|child| collect_aggregates(child, functions, aggregates)),
+    }
+}
+
+/// The name of an output column without an alias, a column keeps its own
+/// name and anything else is named by its sql
+pub(crate) fn column_name(expr: &Expression) -> String {
+    match expr {
+        Expression::Column(column) => column.name.clone(),
+        expr => expr.to_string(),
+    }
+}
+
+/// The index a number in ORDER BY or DISTINCT ON refers to, `None` for any
+/// other expression
+fn position(
+    expr: &Expression,
+    len: usize,
+    clause: &str,
+) -> Result<Option<usize>, QueryExecutionError> {
+    let Expression::Literal(Value::Number(n)) = expr else {
+        return Ok(None);
+    };
+    n.to_usize()
+        .and_then(|n| n.checked_sub(1))
+        .filter(|&i| i < len)
+        .map(Some)
+        .ok_or_else(|| QueryExecutionError::InvalidOperand(clause.into(), Value::Number(n.clone())))
+}
+
+/// Wrap `plan` in the LIMIT and OFFSET of `query`
+fn limit(plan: LogicalPlan, query: &CompoundSelect) -> LogicalPlan {
+    if query.limit.is_none() && query.offset.is_none() {
+        return plan;
+    }
+    LogicalPlan::Limit {
+        input: Box::new(plan),
+        limit: query.limit.clone(),
+        offset: query.offset.clone(),
+    }
+}
+
+/// Turns parsed queries into [`LogicalPlan`]s, resolving every table and
+/// column name
+pub(crate) struct Binder<'a> {
+    env: Env<'a>,
+}
+
+impl<'a> Binder<'a> {
+    pub(crate) fn new(env: Env<'a>) -> Self {
+        Self { env }
+    }
+
+    /// Bind a whole query
+    ///
+    /// CTEs of the query are materialized and left in scope, the caller
+    /// unbinds them once the plan has run
+    pub(crate) fn bind_query(
+        &self,
+        query: &CompoundSelect,
+    ) -> Result<LogicalPlan, QueryExecutionError> {
+        if let Some(with) = &query.with {
+            cte::materialize(with, self.env)?;
+        }
+
+        // a lone select can also order by columns it does not output
+        if query.compounds.is_empty() {
+            let plan = self.bind_select(&query.first, &query.order_by)?;
+            return Ok(limit(plan, query));
+        }
+
+        compound::check_columns(query, self.env.ctx)?;
+        let mut plan = self.bind_select(&query.first, &[])?;
+        for term in &query.compounds {
+            let right = self.bind_select(&term.select, &[])?;
+            let (expected, got) = (plan.columns().len(), right.columns().len());
+            if expected != got {
+                return Err(QueryExecutionError::ColumnCountMismatch(expected, got));
+            }
+            plan = LogicalPlan::SetOperation {
+                op: term.op,
+                left: Box::new(plan),
+                right: Box::new(right),
+            };
+        }
+        self.bind_order_limit(plan, query)
+    }
+
+    /// Sort and limit `plan`, the rows produced by the selects of `query`,
+    /// by the ORDER BY, LIMIT and OFFSET of `query`
+    ///
+    /// The ORDER BY can only use the output columns
+    pub(crate) fn bind_order_limit(
+        &self,
+        plan: LogicalPlan,
+        query: &CompoundSelect,
+    ) -> Result<LogicalPlan, QueryExecutionError> {
+        if query.order_by.is_empty() {
+            return Ok(limit(plan, query));
+        }
+
+        let columns = plan.columns();
+        let order_by = query
+            .order_by
+            .iter()
+            .map(|item| {
+                let expr = match position(&item.expr, columns.len(), "ORDER BY")? {
+                    Some(i) => Expression::Column(columns[i].clone()),
+                    None => item.expr.clone(),
+                };
+                self.check_columns(&expr, &columns)?;
+                Ok(OrderByItem {
+                    expr,
+                    descending: item.descending,
+                })
+            })
+            .collect::<Result<_, QueryExecutionError>>()?;
+        let plan = LogicalPlan::Sort {
+            input: Box::new(plan),
+            order_by,
+        };
+        Ok(limit(plan, query))
+    }
+
+    /// Bind a single select, sorted by `order_by`
+    ///
+    /// The rows are sorted before the select list is computed, so `order_by`
+    /// can use both the output names and the columns of the FROM clause
+    pub(crate) fn bind_select(
+        &self,
+        select: &SelectStatement,
+        order_by: &[OrderByItem],
+    ) -> Result<LogicalPlan, QueryExecutionError> {
+        let from = self.bind_from(&select.from)?;
+        let source = from.columns();
+
+        // expand `*` and name every output column
+        let (names, exprs): (Vec<_>, Vec<_>) = select
+            .fields
+            .iter()
+            .flat_map(|field| match field {
+                SelectField::Wildcard => source
+                    .iter()
+                    .map(|col| (col.name.clone(), Expression::Column(col.clone())))
+                    .collect(),
+                SelectField::Expression { expr, alias } => {
+                    vec![(
+                        alias.clone().unwrap_or_else(|| column_name(expr)),
+                        expr.clone(),
+                    )]
+                }
+            })
+            .unzip();
+        for expr in &exprs {
+            self.check_columns(expr, &source)?;
+        }
+
+        let mut plan = from;
+        if let Some(predicate) = &select.where_clause {
+            self.check_placement(predicate)?;
+            self.check_columns(predicate, &source)?;
+            plan = LogicalPlan::Filter {
+                input: Box::new(plan),
+                predicate: predicate.clone(),
+            };
+        }
+        for expr in &select.group_by {
+            self.check_placement(expr)?;
+            self.check_columns(expr, &source)?;
+        }
+
+        let order_by = order_by
+            .iter()
+            .map(|item| {
+                Ok(OrderByItem {
+                    expr: self.output_expr(&item.expr, &names, &exprs, &source, "ORDER BY")?,
+                    descending: item.descending,
+                })
+            })
+            .collect::<Result<Vec<_>, QueryExecutionError>>()?;
+        let distinct_on = match &select.distinct {
+            Some(Distinct::On(on)) => Some(
+                on.iter()
+                    .map(|expr| self.output_expr(expr, &names, &exprs, &source, "DISTINCT ON"))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            _ => None,
+        };
+
+        let mut aggregates = Vec::new();
+        let mut windows: Vec<WindowFunction> = Vec::new();
+        let keys = order_by
+            .iter()
+            .map(|item| &item.expr)
+            .chain(distinct_on.iter().flatten());
+        for expr in exprs.iter().chain(keys) {
+            collect_aggregates(expr, self.env.ctx.functions, &mut aggregates);
+            collect_windows(expr, &mut windows);
+        }
+
+        if !aggregates.is_empty() || !select.group_by.is_empty() {
+            let columns = source
+                .iter()
+                .cloned()
+                .chain(
+                    aggregates
+                        .iter()
+                        .map(|call| ColumnRef::new(aggregate_column(call))),
+                )
+                .collect();
+            plan = LogicalPlan::Aggregate {
+                input: Box::new(plan),
+                group_by: select.group_by.clone(),
+                aggregates,
+                columns,
+            };
+        }
+
+        // window functions see the rows after grouping, and before DISTINCT
+        if !windows.is_empty() {
+            let columns = plan
+                .columns()
+                .iter()
+                .cloned()
+                .chain(windows.iter().map(|w| ColumnRef::new(window_column(w))))
+                .collect();
+            plan = LogicalPlan::Window {
+                input: Box::new(plan),
+                windows,
+                columns,
+            };
+        }
+
+        if !order_by.is_empty() {
+            plan = LogicalPlan::Sort {
+                input: Box::new(plan),
+                order_by,
+            };
+        }
+        // DISTINCT ON keeps the first row of each set in ORDER BY order
+        if let Some(on) = distinct_on {
+            plan = LogicalPlan::Distinct {
+                input: Box::new(plan),
+                on: Some(on),
+            };
+        }
+
+        plan = LogicalPlan::Project {
+            input: Box::new(plan),
+            exprs,
+            columns: names.into_iter().map(ColumnRef::new).collect(),
+        };
+        if select.distinct == Some(Distinct::Rows) {
+            plan = LogicalPlan::Distinct {
+                input: Box::new(plan),
+                on: None,
+            };
+        }
+        Ok(plan)
+    }
+
+    /// Bind a FROM clause, columns are qualified with the name or alias of
+    /// the table they come from
+    fn bind_from(&self, from: &TableReference) -> Result<LogicalPlan, QueryExecutionError> {
+        match from {
+            TableReference::Table { name, alias } => {
+                let alias = alias.as_ref().unwrap_or(name);
+                if let Some(cte) = self.env.ctx.read_cte(name) {
+                    let columns = cte
+                        .columns
+                        .iter()
+                        .map(|col| ColumnRef::qualified(alias.clone(), col.clone()))
+                        .collect();
+                    return Ok(LogicalPlan::Values {
+                        name: name.clone(),
+                        result: cte,
+                        columns,
+                    });
+                }
+                let table = self
+                    .env
+                    .ctx
+                    .tables
+                    .get(name)
+                    .ok_or_else(|| QueryExecutionError::TableNotFound(name.clone()))?;
+                Ok(LogicalPlan::Scan {
+                    table: name.clone(),
+                    alias: alias.clone(),
+                    columns: table.columns().refs(Some(alias)),
+                })
+            }
+            TableReference::Subquery { query, alias } => {
+                let input = self.bind_query(query)?;
+                let columns = input
+                    .columns()
+                    .iter()
+                    .map(|col| ColumnRef::qualified(alias.clone(), col.name.clone()))
+                    .collect();
+                Ok(LogicalPlan::Alias {
+                    input: Box::new(input),
+                    alias: alias.clone(),
+                    columns,
+                })
+            }
+            TableReference::Join {
+                left,
+                kind,
+                right,
+                on,
+            } => {
+                let left = self.bind_from(left)?;
+                let right = self.bind_from(right)?;
+                let columns: Rc<[ColumnRef]> = left
+                    .columns()
+                    .iter()
+                    .chain(right.columns().iter())
+                    .cloned()
+                    .collect();
+                if let Some(on) = on {
+                    self.check_placement(on)?;
+                    self.check_columns(on, &columns)?;
+                }
+                Ok(LogicalPlan::Join {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    kind: *kind,
+                    on: on.clone(),
+                    columns,
+                })
+            }
+        }
+    }
+
+    /// Rewrite an ORDER BY or DISTINCT ON expression of a select to run
+    /// before its select list: a number picks the select expression at that
+    /// position, and output names refer to the expressions they name
+    fn output_expr(
+        &self,
+        expr: &Expression,
+        names: &[String],
+        exprs: &[Expression],
+        source: &[ColumnRef],
+        clause: &str,
+    ) -> Result<Expression, QueryExecutionError> {
+        if let Some(i) = position(expr, exprs.len(), clause)? {
+            return Ok(exprs[i].clone());
+        }
+
+        fn substitute(
+            expr: &mut Expression,
+            names: &[String],
+            exprs: &[Expression],
+            functions: &FunctionRegistry,
+        ) {
+            match expr {
+                Expression::Column(ColumnRef { table: None, name }) => {
+                    if let Some(i) = names.iter().position(|output| output == name) {
+                        *expr = exprs[i].clone();
+                    }
+                }
+                // arguments are evaluated against the rows being grouped
+                Expression::Function(call) if functions.is_aggregate(&call.name) => {}
+                Expression::Window(_) => {}
+                expr => expr
+                    .children_mut()
+                    .into_iter()
+                    .for_each(|child| substitute(child, names, exprs, functions)),
+            }
+        }
+
+        let mut expr = expr.clone();
+        substitute(&mut expr, names, exprs, self.env.ctx.functions);
+        self.check_columns(&expr, source)?;
+        Ok(expr)
+    }
+
+    /// Check that every column `expr` references is one of `columns` or a
+    /// column of an enclosing query
+    fn check_columns(
+        &self,
+        expr: &Expression,
+        columns: &[ColumnRef],
+    ) -> Result<(), QueryExecutionError> {
+        let Expression::Column(column) = expr else {
+            return expr
+                .children()
+                .into_iter()
+                .try_for_each(|child| self.check_columns(child, columns));
+        };
+
+        let mut matches = columns.iter().filter(|col| {
+            col.name == column.name && (column.table.is_none() || col.table == column.table)
+        });
+        if let Some(first) = matches.next() {
+            return match matches.find(|col| col.table != first.table) {
+                Some(_) => Err(QueryExecutionError::AmbiguousColumn(column.to_string())),
+                None => Ok(()),
+            };
+        }
+
+        let mut scope = self.env.outer;
+        while let Some(current) = scope {
+            if current.row.resolve(column).is_some() {
+                return Ok(());
+            }
+            scope = current.outer;
+        }
+        Err(QueryExecutionError::ColumnDoesNotExist(column.to_string()))
+    }
+
+    /// Check that a clause evaluated for each input row, like WHERE, has no
+    /// aggregate or window functions
+    fn check_placement(&self, expr: &Expression) -> Result<(), QueryExecutionError> {
+        let mut aggregates = Vec::new();
+        collect_aggregates(expr, self.env.ctx.functions, &mut aggregates);
+        if let Some(call) = aggregates.first() {
+            return Err(QueryExecutionError::MisplacedAggregate(call.name.clone()));
+        }
+        let mut windows = Vec::new();
+        collect_windows(expr, &mut windows);
+        if let Some(window) = windows.first() {
+            return Err(QueryExecutionError::MisplacedWindowFunction(
+                window.call.name.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{ast::SqlQuery, commands::CompoundSelect, parse::Parse};
+
+    use crate::{
+        error::QueryExecutionError,
+        eval::{Context, Env},
+        plan::LogicalPlan,
+        tests::{execution, run_all},
+        Execution,
+    };
+
+    use super::Binder;
+
+    const TABLES: &[&str] = &[
+        "create table emp (name string, dept string, salary int);",
+        "create table depts (name string);",
+    ];
+
+    fn bind(exec: &Execution, sql: &str) -> Result<LogicalPlan, QueryExecutionError> {
+        let (_, query) = CompoundSelect::parse_from_raw(sql).unwrap();
+        let ctx = Context::new(&exec.functions, &exec.tables);
+        Binder::new(Env::new(&ctx)).bind_query(&query)
+    }
+
+    /// The name of every node from the root down the first input
+    fn shape(plan: &LogicalPlan) -> Vec<&'static str> {
+        let name = match plan {
+            LogicalPlan::Scan { .. } => "Scan",
+            LogicalPlan::IndexScan { .. } => "IndexScan",
+            LogicalPlan::Values { .. } => "Values",
+            LogicalPlan::Alias { .. } => "Alias",
+            LogicalPlan::Filter { .. } => "Filter",
+            LogicalPlan::Join { .. } => "Join",
+            LogicalPlan::Aggregate { .. } => "Aggregate",
+            LogicalPlan::Window { .. } => "Window",
+            LogicalPlan::Project { .. } => "Project",
+            LogicalPlan::Sort { .. } => "Sort",
+            LogicalPlan::Distinct { .. } => "Distinct",
+            LogicalPlan::Limit { .. } => "Limit",
+            LogicalPlan::SetOperation { .. } => "SetOperation",
+        };
+        let mut names = vec![name];
+        if let Some(input) = plan.inputs().first() {
+            names.extend(shape(input));
+        }
+        names
+    }
+
+    #[test]
+    fn test_plan_shape() {
+        let exec = execution(TABLES);
+        let plan = bind(
+            &exec,
+            "select dept, sum(salary) as total from emp e join depts d on e.dept = d.name \
+             where salary > 10 group by dept order by total desc limit 2",
+        )
+        .unwrap();
+        assert_eq!(
+            shape(&plan),
+            vec![
+                "Limit",
+                "Project",
+                "Sort",
+                "Aggregate",
+                "Filter",
+                "Join",
+                "Scan"
+            ]
+        );
+        assert_eq!(&*plan.names(), ["dept", "total"]);
+
+        // ORDER BY of a lone select runs before the select list, so the alias
+        // is replaced by the expression it names
+        let LogicalPlan::Limit { input, .. } = &plan else {
+            unreachable!()
+        };
+        let LogicalPlan::Project { input, .. } = input.as_ref() else {
+            unreachable!()
+        };
+        let LogicalPlan::Sort { order_by, .. } = input.as_ref() else {
+            unreachable!()
+        };
+        assert_eq!(order_by[0].expr.to_string(), "sum(salary)");
+
+        let plan = bind(
+            &exec,
+            "select name from emp union select name from depts order by 1",
+        )
+        .unwrap();
+        assert_eq!(
+            shape(&plan),
+            vec!["Sort", "SetOperation", "Project", "Scan"]
+        );
+    }
+
+    #[test]
+    fn test_output_names() {
+        let mut exec = execution(TABLES);
+        run_all(
+            &mut exec,
+            &["create table k (index int, header string, name string);"],
+        );
+        let plan = bind(
+            &exec,
+            "select index, header, k.name, index + 1, name as n from k",
+        )
+        .unwrap();
+        assert_eq!(
+            &*plan.names(),
+            ["index", "header", "name", "\"index\" + 1", "n"]
+        );
+    }
+
+    #[test]
+    fn test_names_resolved_before_execution() {
+        let mut exec = execution(TABLES);
+        // the tables are empty, so only binding can find these
+        assert!(matches!(
+            bind(&exec, "select nope from emp"),
+            Err(QueryExecutionError::ColumnDoesNotExist(name)) if name == "nope"
+        ));
+        assert!(matches!(
+            bind(&exec, "select e.name from emp"),
+            Err(QueryExecutionError::ColumnDoesNotExist(_))
+        ));
+        assert!(matches!(
+            bind(&exec, "select name from emp join depts on dept = depts.name"),
+            Err(QueryExecutionError::AmbiguousColumn(name)) if name == "name"
+        ));
+        assert!(matches!(
+            bind(&exec, "select name from missing"),
+            Err(QueryExecutionError::TableNotFound(_))
+        ));
+        assert!(matches!(
+            bind(&exec, "select name from emp where count(*) > 1"),
+            Err(QueryExecutionError::MisplacedAggregate(_))
+        ));
+
+        // columns of an enclosing query are resolved when the subquery runs
+        let (_, query) = SqlQuery::parse_from_raw(
+            "select name from depts d where exists (select 1 from emp where dept = d.name);",
+        )
+        .unwrap();
+        assert!(exec.run(query).is_ok());
+    }
+}
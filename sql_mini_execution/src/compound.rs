@@ -0,0 +1,275 @@
+use std::{collections::HashSet, fmt};
+
+use sql_mini_parser::{
+    commands::{
+        CompoundSelect, SelectField, SelectStatement, SetOperator, SqlTypeInfo, TableReference,
+    },
+    expr::{BinaryOperator, Expression, UnaryOperator},
+    value::Value,
+};
+
+use crate::{binder::column_name, error::QueryExecutionError, eval::Context};
+
+/// The broad kind of value a column holds, as far as can be told without
+/// running the query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Number,
+    String,
+    Bool,
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number => write!(f, "number"),
+            Self::String => write!(f, "string"),
+            Self::Bool => write!(f, "boolean"),
+        }
+    }
+}
+
+impl From<SqlTypeInfo> for ColumnType {
+    fn from(type_info: SqlTypeInfo) -> Self {
+        match type_info {
+            SqlTypeInfo::String => Self::String,
+            SqlTypeInfo::Int => Self::Number,
+        }
+    }
+}
+
+/// A column's name and its type, `None` when it could hold anything
+type TypedColumn = (String, Option<ColumnType>);
+
+/// The columns a FROM clause provides, `None` when only running the query
+/// can tell
+fn source_columns(from: &TableReference, ctx: &Context) -> Option<Vec<TypedColumn>> {
+    match from {
+        TableReference::Table { name, .. } => match ctx.cte(name) {
+            Some(cte) => Some(cte.columns.iter().map(|col| (col.clone(), None)).collect()),
+            None => Some(
+                ctx.tables
+                    .get(name)?
+                    .columns()
+                    .iter()
+                    .map(|col| (col.name.clone(), Some(col.type_info.into())))
+                    .collect(),
+            ),
+        },
+        // its own CTEs are not bound yet
+        TableReference::Subquery { query, .. } if query.with.is_none() => {
+            output_columns(&query.first, ctx)
+        }
+        TableReference::Subquery { .. } => None,
+        TableReference::Join { left, right, .. } => {
+            let mut columns = source_columns(left, ctx)?;
+            columns.extend(source_columns(right, ctx)?);
+            Some(columns)
+        }
+    }
+}
+
+/// The columns a select produces
+fn output_columns(select: &SelectStatement, ctx: &Context) -> Option<Vec<TypedColumn>> {
+    let source = source_columns(&select.from, ctx);
+    let mut columns = Vec::new();
+    for field in &select.fields {
+        match field {
+            SelectField::Wildcard => columns.extend(source.clone()?),
+            SelectField::Expression { expr, alias } => columns.push((
+                alias.clone().unwrap_or_else(|| column_name(expr)),
+                expression_type(expr, source.as_deref()),
+            )),
+        }
+    }
+    Some(columns)
+}
+
+fn expression_type(expr: &Expression, source: Option<&[TypedColumn]>) -> Option<ColumnType> {
+    match expr {
+        Expression::Literal(Value::Number(_)) => Some(ColumnType::Number),
+        Expression::Literal(Value::String(_)) => Some(ColumnType::String),
+        Expression::Literal(Value::Bool(_)) => Some(ColumnType::Bool),
+        Expression::Literal(Value::Null) | Expression::Parameter(_) => None,
+        Expression::Column(column) => source?
+            .iter()
+            .find(|(name, _)| *name == column.name)
+            .and_then(|(_, column_type)| *column_type),
+        Expression::Unary {
+            op: UnaryOperator::Not,
+            ..
+        } => Some(ColumnType::Bool),
+        Expression::Unary {
+            op: UnaryOperator::Minus,
+            ..
+        } => Some(ColumnType::Number),
+        Expression::Binary { op, .. } => match op {
+            BinaryOperator::Plus
+            | BinaryOperator::Minus
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo => Some(ColumnType::Number),
+            BinaryOperator::Concat => Some(ColumnType::String),
+            _ => Some(ColumnType::Bool),
+        },
+        Expression::IsNull { .. }
+        | Expression::Between { .. }
+        | Expression::InList { .. }
+        | Expression::Pattern { .. }
+        | Expression::InSubquery { .. }
+        | Expression::Exists(_) => Some(ColumnType::Bool),
+        Expression::Case {
+            when_clauses,
+            else_result,
+            ..
+        } => when_clauses
+            .iter()
+            .map(|clause| &clause.result)
+            .chain(else_result.as_deref())
+            .find_map(|result| expression_type(result, source)),
+        Expression::Function(_) | Expression::Subquery(_) | Expression::Window(_) => None,
+    }
+}
+
+/// Check that every select of a compound produces the same number of
+/// columns, and that no column mixes values of different types
+pub(crate) fn check_columns(
+    query: &CompoundSelect,
+    ctx: &Context,
+) -> Result<(), QueryExecutionError> {
+    let mut expected = output_columns(&query.first, ctx);
+    for term in &query.compounds {
+        let Some(columns) = output_columns(&term.select, ctx) else {
+            continue;
+        };
+        let Some(expected) = &mut expected else {
+            expected = Some(columns);
+            continue;
+        };
+
+        if expected.len() != columns.len() {
+            return Err(QueryExecutionError::ColumnCountMismatch(
+                expected.len(),
+                columns.len(),
+            ));
+        }
+        for ((name, left), (_, right)) in expected.iter_mut().zip(columns) {
+            match (*left, right) {
+                (Some(l), Some(r)) if l != r => {
+                    return Err(QueryExecutionError::IncompatibleColumnTypes {
+                        op: term.op.to_string(),
+                        column: name.clone(),
+                        left: l.to_string(),
+                        right: r.to_string(),
+                    })
+                }
+                (None, right) => *left = right,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Combine the rows produced so far with those of the next select
+pub(crate) fn combine(
+    op: SetOperator,
+    left: Vec<Vec<Value>>,
+    right: Vec<Vec<Value>>,
+) -> Vec<Vec<Value>> {
+    let mut seen = HashSet::new();
+    match op {
+        SetOperator::UnionAll => left.into_iter().chain(right).collect(),
+        SetOperator::Union => left
+            .into_iter()
+            .chain(right)
+            .filter(|row| seen.insert(row.clone()))
+            .collect(),
+        SetOperator::Intersect | SetOperator::Except => {
+            let right: HashSet<_> = right.into_iter().collect();
+            let keep_matches = op == SetOperator::Intersect;
+            left.into_iter()
+                .filter(|row| right.contains(row) == keep_matches && seen.insert(row.clone()))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{ast::SqlQuery, parse::Parse, value::Value};
+
+    use crate::{
+        error::QueryExecutionError,
+        tests::{execution, num, run_all},
+    };
+
+    const TABLES: &[&str] = &[
+        "create table a (n int, s string);",
+        "insert into a values 1, 'x';",
+        "insert into a values 2, 'y';",
+        "insert into a values 2, 'y';",
+        "create table b (n int);",
+        "insert into b values 2;",
+        "insert into b values 3;",
+    ];
+
+    #[test]
+    fn test_set_operations() {
+        let mut exec = execution(TABLES);
+        let column = |rows: Vec<Vec<Value>>| {
+            rows.into_iter()
+                .map(|mut row| row.remove(0))
+                .collect::<Vec<_>>()
+        };
+
+        let rows = run_all(
+            &mut exec,
+            &["select n from a union select n from b order by n;"],
+        );
+        assert_eq!(column(rows), vec![num(1), num(2), num(3)]);
+
+        let rows = run_all(
+            &mut exec,
+            &["select n from a union all select n from b order by 1 desc limit 3 offset 1;"],
+        );
+        assert_eq!(column(rows), vec![num(2), num(2), num(2)]);
+
+        let rows = run_all(&mut exec, &["select n from a intersect select n from b;"]);
+        assert_eq!(column(rows), vec![num(2)]);
+
+        let rows = run_all(&mut exec, &["select n from a except select n from b;"]);
+        assert_eq!(column(rows), vec![num(1)]);
+
+        // set operators apply left to right
+        let rows = run_all(
+            &mut exec,
+            &["select n from b except select n from a union select n from a order by n;"],
+        );
+        assert_eq!(column(rows), vec![num(1), num(2), num(3)]);
+    }
+
+    #[test]
+    fn test_order_by_source_columns() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(&mut exec, &["select s from a order by n desc, s limit 1;"]);
+        assert_eq!(rows, vec![vec![Value::String("y".into())]]);
+    }
+
+    #[test]
+    fn test_checked_before_execution() {
+        let mut exec = execution(TABLES);
+        let mut run = |sql: &str| exec.run(SqlQuery::parse_from_raw(sql).unwrap().1);
+
+        assert!(matches!(
+            run("select * from a union select n from b;"),
+            Err(QueryExecutionError::ColumnCountMismatch(2, 1))
+        ));
+        assert!(matches!(
+            run("select s from a union select n from b;"),
+            Err(QueryExecutionError::IncompatibleColumnTypes { .. })
+        ));
+        // NULL and untyped expressions fit any column
+        assert!(run("select null from a union select n from b;").is_ok());
+    }
+}
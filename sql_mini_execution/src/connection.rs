@@ -0,0 +1,394 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use serde::de::DeserializeOwned;
+use sql_mini_parser::{
+    ast::SqlQuery,
+    commands::{CopyDirection, CopyStatement},
+    error::ParseError,
+    expr::ColumnRef,
+    script::parse_script,
+    value::Value,
+};
+use thiserror::Error;
+
+use crate::{
+    error::QueryExecutionError, result::ResultSet, row::Row, table::Table, ExecResponse, Execution,
+};
+
+/// Anything that can go wrong running sql through a [`Connection`]
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[error(transparent)]
+    Execution(#[from] QueryExecutionError),
+
+    #[error("Could not access the database file: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Could not read or write the database file: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+/// A database to run sql against, the entry point for embedding
+///
+/// A database opened from a file is kept in memory and the file is rewritten
+/// after every statement that changes it, except for statements run with
+/// [`Connection::execute_query`] which are written by [`Connection::flush`]
+#[derive(Debug, Default)]
+pub struct Connection {
+    exec: Execution,
+    path: Option<PathBuf>,
+    /// Whether statements changed the database since it was last written
+    unsaved: bool,
+}
+
+impl Connection {
+    /// Open the database stored at `path`, it is created by the first
+    /// statement that changes it if it does not exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+        let mut exec = Execution::new();
+        match fs::read(&path) {
+            Ok(bytes) => {
+                exec.tables = serde_json::from_slice(&bytes)?;
+                exec.tables.values_mut().for_each(Table::reindex);
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(Self {
+            exec,
+            path: Some(path),
+            unsaved: false,
+        })
+    }
+
+    /// A database that only lives as long as the connection
+    pub fn open_in_memory() -> Self {
+        Self::default()
+    }
+
+    /// The engine behind the connection, to register functions or keep
+    /// prepared statements
+    pub fn execution(&mut self) -> &mut Execution {
+        &mut self.exec
+    }
+
+    /// Run a single statement with `params` bound to its parameters in order,
+    /// returns the number of rows it inserted, or copied in from a file
+    pub fn execute(&mut self, sql: &str, params: &[Value]) -> Result<usize, Error> {
+        let query = SqlQuery::parse_statement(sql)?;
+        let copy_from = matches!(
+            query,
+            SqlQuery::Copy(CopyStatement {
+                direction: CopyDirection::From,
+                ..
+            })
+        );
+        match self.run_query(query, params)? {
+            ExecResponse::Insert => Ok(1),
+            ExecResponse::Copy(report) if copy_from => Ok(report.rows),
+            _ => Ok(0),
+        }
+    }
+
+    /// Run a single statement with `params` bound to its parameters in order
+    /// and return the rows it selects, statements that select nothing
+    /// return no rows
+    ///
+    /// `EXPLAIN` returns a row per line of the plan, in a `plan` column
+    pub fn query(&mut self, sql: &str, params: &[Value]) -> Result<Rows, Error> {
+        match self.run(sql, params)? {
+            ExecResponse::Select(result) => Ok(result.into()),
+            ExecResponse::Explain(plan) => {
+                let columns: Rc<[String]> = Rc::from(["plan".to_owned()]);
+                let refs: Rc<[ColumnRef]> = columns.iter().map(ColumnRef::new).collect();
+                let rows = plan
+                    .to_string()
+                    .lines()
+                    .map(|line| Row::new(refs.clone(), vec![line.into()]))
+                    .collect();
+                Ok(ResultSet::new(columns, rows).into())
+            }
+            _ => Ok(ResultSet::new(Rc::from([]), vec![]).into()),
+        }
+    }
+
+    /// Like [`Connection::query`] but decodes each row into `T`, see
+    /// [`Row::deserialize`]
+    pub fn query_as<T: DeserializeOwned>(
+        &mut self,
+        sql: &str,
+        params: &[Value],
+    ) -> Result<Vec<T>, Error> {
+        self.query(sql, params)?
+            .map(|row| row.deserialize().map_err(Error::from))
+            .collect()
+    }
+
+    /// Run every statement of `script`, like the contents of a `.sql` file,
+    /// stopping at the first that fails
+    ///
+    /// Nothing runs if any statement can not be parsed
+    pub fn execute_batch(&mut self, script: &str) -> Result<(), Error> {
+        let statements = parse_script(script)?;
+        let result = statements.into_iter().try_for_each(|statement| {
+            self.unsaved |= changes_data(&statement.query);
+            self.exec.run(statement.query).map(drop)
+        });
+        // keep the statements that ran even when a later one failed
+        self.flush()?;
+        Ok(result?)
+    }
+
+    /// Run a parsed statement, like one from [`parse_script`], and return
+    /// what it produced
+    ///
+    /// The database file is not written, so running many statements stays
+    /// fast, call [`Connection::flush`] when they are done
+    pub fn execute_query(&mut self, query: SqlQuery) -> Result<ExecResponse, Error> {
+        self.unsaved |= changes_data(&query);
+        Ok(self.exec.run(query)?)
+    }
+
+    /// Write the changes made since the database file was last written, if
+    /// it has one
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if self.unsaved {
+            self.save()?;
+            self.unsaved = false;
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, sql: &str, params: &[Value]) -> Result<ExecResponse, Error> {
+        self.run_query(SqlQuery::parse_statement(sql)?, params)
+    }
+
+    fn run_query(&mut self, query: SqlQuery, params: &[Value]) -> Result<ExecResponse, Error> {
+        let changes = changes_data(&query);
+        let mut statement = self.exec.prepare_query(query)?;
+        for (i, value) in params.iter().enumerate() {
+            statement.bind(i + 1, value.clone())?;
+        }
+        let response = self.exec.execute(&mut statement)?;
+        self.unsaved |= changes;
+        self.flush()?;
+        Ok(response)
+    }
+
+    /// Write the database to its file, if it has one
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // replace the file in one step, a crash never leaves half a database
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        fs::write(&temporary, serde_json::to_vec(&self.exec.tables)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    /// Write what was not flushed, errors are lost, call
+    /// [`Connection::flush`] first to see them
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+fn changes_data(query: &SqlQuery) -> bool {
+    !matches!(
+        query,
+        SqlQuery::Select(_)
+            | SqlQuery::Explain(_)
+            | SqlQuery::Copy(CopyStatement {
+                direction: CopyDirection::To,
+                ..
+            })
+    )
+}
+
+/// The rows returned by [`Connection::query`], read them with [`Row::get`]
+/// or [`Row::deserialize`]
+#[derive(Debug)]
+pub struct Rows {
+    columns: Rc<[String]>,
+    rows: std::vec::IntoIter<Row>,
+}
+
+impl Rows {
+    /// The names of the output columns
+    pub fn column_names(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+impl From<ResultSet> for Rows {
+    fn from(result: ResultSet) -> Self {
+        Self {
+            columns: result.columns.clone(),
+            rows: result.into_iter(),
+        }
+    }
+}
+
+impl Iterator for Rows {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use sql_mini_parser::{ast::SqlQuery, value::Value};
+
+    use crate::{error::QueryExecutionError, ExecResponse};
+
+    use super::{Connection, Error};
+
+    #[test]
+    fn test_in_memory() {
+        let mut conn = Connection::open_in_memory();
+        conn.execute_batch(
+            "create table people (name string, age int);
+             -- the first person
+             insert into people values 'ann', 30",
+        )
+        .unwrap();
+        assert_eq!(
+            conn.execute(
+                "insert into people values ?, ?;",
+                &["bob".into(), 20.into()]
+            )
+            .unwrap(),
+            1
+        );
+
+        let mut rows = conn
+            .query("select name from people where age < :max;", &[25.into()])
+            .unwrap();
+        assert_eq!(rows.column_names(), ["name"]);
+        assert_eq!(
+            rows.next().unwrap().get("name").unwrap(),
+            Value::from("bob")
+        );
+        assert!(rows.next().is_none());
+
+        let ages: Vec<(i64,)> = conn
+            .query_as("select age from people order by age;", &[])
+            .unwrap();
+        assert_eq!(ages, vec![(20,), (30,)]);
+
+        let plan: Vec<(String,)> = conn
+            .query_as("explain select name from people where age < 25;", &[])
+            .unwrap();
+        assert_eq!(
+            plan,
+            [
+                ("Project: name".to_owned(),),
+                ("  Filter: age < 25".to_owned(),),
+                ("    Scan: people (name, age)".to_owned(),)
+            ]
+        );
+
+        assert!(matches!(
+            conn.execute("select fart;", &[]),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            conn.execute("insert into people values ?, ?;", &[20.into(), 20.into()]),
+            Err(Error::Execution(
+                QueryExecutionError::ParameterTypeMismatch { .. }
+            ))
+        ));
+        assert_eq!(
+            conn.execute(
+                "insert into people values ?, ?;",
+                &["cid".into(), "1".into()]
+            )
+            .unwrap_err()
+            .to_string(),
+            "Parameter ?2 is compared to or inserted into an INT column, got '1'"
+        );
+        // a batch that does not parse runs nothing
+        assert!(conn
+            .execute_batch("insert into people values 'cid', 1; select fart;")
+            .is_err());
+        assert_eq!(
+            conn.query("select name from people;", &[]).unwrap().count(),
+            2
+        );
+
+        let file = env::temp_dir().join(format!("minisql-execute-{}.csv", process::id()));
+        let path = file.display();
+        assert_eq!(
+            conn.execute(&format!("copy people to '{path}';"), &[])
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            conn.execute(&format!("copy people from '{path}';"), &[])
+                .unwrap(),
+            2
+        );
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_open_file() {
+        let path = env::temp_dir().join(format!("minisql-connection-{}.db", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "create table people (name string, age int);
+             create index people_age on people (age);
+             insert into people values 'ann', 30;",
+        )
+        .unwrap();
+        drop(conn);
+
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute("insert into people values 'bob', 30;", &[])
+            .unwrap();
+        let names: Vec<(String,)> = conn
+            .query_as("select name from people where age = 30 order by name;", &[])
+            .unwrap();
+        assert_eq!(names, vec![("ann".to_owned(),), ("bob".to_owned(),)]);
+
+        let insert = SqlQuery::parse_statement("insert into people values 'cid', 20").unwrap();
+        assert!(matches!(
+            conn.execute_query(insert).unwrap(),
+            ExecResponse::Insert
+        ));
+        // not written until flushed
+        let saved = fs::read(&path).unwrap();
+        conn.flush().unwrap();
+        assert_ne!(fs::read(&path).unwrap(), saved);
+        drop(conn);
+        let mut conn = Connection::open(&path).unwrap();
+        assert_eq!(
+            conn.query("select name from people;", &[]).unwrap().count(),
+            3
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+//! `COPY`, reading the rows of a CSV file into a table and writing a table
+//! out as CSV
+//!
+//! Files are read a record at a time, so they never have to fit in memory
+//!
+//! A null is an empty field without quotes, an empty string is written as
+//! `""` so the two survive a copy out and back in, except in a table of one
+//! column where a null row is an empty line, which is skipped when read
+
+use std::{
+    cell::RefCell,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    rc::Rc,
+    str::FromStr,
+};
+
+use bigdecimal::BigDecimal;
+use sql_mini_parser::{
+    commands::{CopyOptions, SqlTypeInfo},
+    value::Value,
+};
+
+use crate::{error::QueryExecutionError, table::Table};
+
+/// What a `COPY` did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopyReport {
+    /// Rows read into or written from the table
+    pub rows: usize,
+    /// The lines that could not be read, they were skipped
+    pub errors: Vec<RowError>,
+}
+
+impl fmt::Display for CopyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Copied {} rows", self.rows)?;
+        if !self.errors.is_empty() {
+            write!(f, ", skipped {} bad lines", self.errors.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// A line of a CSV file that could not be copied into a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    /// Counting from 1, including any header
+    pub line: u64,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+fn file_error(path: &str, err: impl fmt::Display) -> QueryExecutionError {
+    QueryExecutionError::CopyFile {
+        path: path.to_owned(),
+        reason: err.to_string(),
+    }
+}
+
+/// The bytes read from a file that the csv reader has not moved past yet,
+/// the parsed records do not tell whether a field was quoted
+#[derive(Debug, Default)]
+struct RawText {
+    bytes: Vec<u8>,
+    /// The offset in the file of the first of `bytes`
+    start: u64,
+}
+
+impl RawText {
+    /// The text from `offset` on, forgetting everything before it
+    fn from(&mut self, offset: u64) -> &[u8] {
+        let skip = usize::try_from(offset.saturating_sub(self.start)).unwrap_or(usize::MAX);
+        self.bytes.drain(..skip.min(self.bytes.len()));
+        self.start = self.start.max(offset);
+        &self.bytes
+    }
+}
+
+/// A reader keeping what it reads in a [`RawText`]
+struct Recording<R> {
+    inner: R,
+    raw: Rc<RefCell<RawText>>,
+}
+
+impl<R: Read> Read for Recording<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.raw.borrow_mut().bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Append the rows of the CSV file at `path` to `table`, the fields of a
+/// line are the table's columns in order, lines that do not fit are
+/// reported and skipped
+pub(crate) fn copy_from(
+    table: &mut Table,
+    path: &str,
+    options: CopyOptions,
+) -> Result<CopyReport, QueryExecutionError> {
+    let file = File::open(path).map_err(|e| file_error(path, e))?;
+    let raw = Rc::new(RefCell::new(RawText::default()));
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter(options))
+        .has_headers(options.header)
+        .flexible(true)
+        .from_reader(Recording {
+            inner: file,
+            raw: raw.clone(),
+        });
+    let types: Vec<_> = table.columns().iter().map(|col| col.type_info).collect();
+
+    let mut report = CopyReport::default();
+    let mut record = csv::StringRecord::new();
+    loop {
+        let line = reader.position().line();
+        match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {}
+            Err(e) if e.is_io_error() => return Err(file_error(path, e)),
+            Err(e) => {
+                let line = e.position().map_or(line, csv::Position::line);
+                report.errors.push(RowError {
+                    line,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        }
+        let Some(position) = record.position() else {
+            continue;
+        };
+        let quoted = quoted_fields(raw.borrow_mut().from(position.byte()), delimiter(options));
+        match convert(&record, &quoted, &types)
+            .and_then(|values| table.insert(values).map_err(|e| e.to_string()))
+        {
+            Ok(()) => report.rows += 1,
+            Err(message) => report.errors.push(RowError {
+                line: position.line(),
+                message,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// Write every row of `table` to a CSV file at `path`
+pub(crate) fn copy_to(
+    table: &Table,
+    path: &str,
+    options: CopyOptions,
+) -> Result<CopyReport, QueryExecutionError> {
+    let file = File::create(path).map_err(|e| file_error(path, e))?;
+    let mut out = BufWriter::new(file);
+    let delimiter = delimiter(options);
+    if options.header {
+        let names = table.columns().iter().map(|col| Some(col.name.clone()));
+        write_record(&mut out, names, delimiter).map_err(|e| file_error(path, e))?;
+    }
+
+    let mut report = CopyReport::default();
+    for row in table {
+        let fields = row.values().iter().map(|value| match value {
+            Value::Null => None,
+            Value::Number(n) => Some(n.to_plain_string()),
+            value => Some(value.to_string()),
+        });
+        write_record(&mut out, fields, delimiter).map_err(|e| file_error(path, e))?;
+        report.rows += 1;
+    }
+    out.flush().map_err(|e| file_error(path, e))?;
+    Ok(report)
+}
+
+/// Write a line of fields, `None` for a null
+///
+/// The csv writer can not be used, it leaves empty fields unquoted
+fn write_record(
+    out: &mut impl Write,
+    fields: impl Iterator<Item = Option<String>>,
+    delimiter: u8,
+) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.write_all(&[delimiter])?;
+        }
+        let Some(field) = field else {
+            continue;
+        };
+        let quote = field.is_empty()
+            || field
+                .bytes()
+                .any(|b| matches!(b, b'"' | b'\r' | b'\n') || b == delimiter);
+        if quote {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_all(field.as_bytes())?;
+        }
+    }
+    out.write_all(b"\n")
+}
+
+fn delimiter(options: CopyOptions) -> u8 {
+    // the parser only accepts ascii delimiters
+    u8::try_from(options.delimiter).unwrap_or(b',')
+}
+
+/// Whether each field of the record at the start of `raw` starts with a quote
+fn quoted_fields(raw: &[u8], delimiter: u8) -> Vec<bool> {
+    let mut quoted = Vec::new();
+    let mut at_start = true;
+    let mut in_quotes = false;
+    let mut bytes = raw.iter().copied().peekable();
+    while let Some(b) = bytes.next() {
+        if at_start {
+            at_start = false;
+            quoted.push(b == b'"');
+            if b == b'"' {
+                in_quotes = true;
+                continue;
+            }
+        }
+        if in_quotes {
+            // a doubled quote stands for one quote in the field
+            if b == b'"' && bytes.next_if_eq(&b'"').is_none() {
+                in_quotes = false;
+            }
+        } else if b == delimiter {
+            at_start = true;
+        } else if b == b'\n' || b == b'\r' {
+            break;
+        }
+    }
+    if at_start {
+        // the file ends after a delimiter
+        quoted.push(false);
+    }
+    quoted
+}
+
+/// The values of a record for columns of `types`, an empty field that was
+/// not quoted is a null
+fn convert(
+    record: &csv::StringRecord,
+    quoted: &[bool],
+    types: &[SqlTypeInfo],
+) -> Result<Vec<Value>, String> {
+    if record.len() != types.len() {
+        return Err(format!(
+            "Expected {} fields, got {}",
+            types.len(),
+            record.len()
+        ));
+    }
+    record
+        .iter()
+        .zip(types)
+        .zip(quoted.iter().chain(std::iter::repeat(&false)))
+        .map(|((field, type_info), quoted)| match type_info {
+            _ if field.is_empty() && !quoted => Ok(Value::Null),
+            SqlTypeInfo::String => Ok(Value::String(field.to_owned())),
+            SqlTypeInfo::Int => BigDecimal::from_str(field.trim())
+                .ok()
+                .filter(BigDecimal::is_integer)
+                .map(Value::Number)
+                .ok_or_else(|| format!("{field:?} is not a valid {type_info}")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use sql_mini_parser::{ast::SqlQuery, parse::Parse, value::Value};
+
+    use crate::{tests::run_all, ExecResponse, Execution};
+
+    use super::{CopyReport, RowError};
+
+    fn copy(exec: &mut Execution, sql: &str) -> CopyReport {
+        let (_, query) = SqlQuery::parse_from_raw(sql).unwrap();
+        match exec.run(query).unwrap() {
+            ExecResponse::Copy(report) => report,
+            response => panic!("Expected a copy, got {response:?}"),
+        }
+    }
+
+    #[test]
+    fn test_copy() {
+        let dir = env::temp_dir();
+        let input = dir.join(format!("minisql-copy-in-{}.csv", process::id()));
+        let output = dir.join(format!("minisql-copy-out-{}.csv", process::id()));
+        fs::write(
+            &input,
+            "name;age\nann;30\nbob;thirty\n\"cid; jr\";\nonly one field\ndee;7\n",
+        )
+        .unwrap();
+
+        let mut exec = Execution::new();
+        run_all(&mut exec, &["create table people (name string, age int);"]);
+        let report = copy(
+            &mut exec,
+            &format!(
+                "copy people from '{}' with (header, delimiter ';');",
+                input.display()
+            ),
+        );
+        assert_eq!(report.rows, 3);
+        assert_eq!(
+            report.errors,
+            [
+                RowError {
+                    line: 3,
+                    message: "\"thirty\" is not a valid INT".into()
+                },
+                RowError {
+                    line: 5,
+                    message: "Expected 2 fields, got 1".into()
+                },
+            ]
+        );
+        assert_eq!(
+            run_all(&mut exec, &["select name, age from people order by name;"]),
+            [
+                vec![Value::from("ann"), 30.into()],
+                vec![Value::from("cid; jr"), Value::Null],
+                vec![Value::from("dee"), 7.into()],
+            ]
+        );
+
+        let report = copy(
+            &mut exec,
+            &format!("copy people to '{}';", output.display()),
+        );
+        assert_eq!(report.rows, 3);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "ann,30\ncid; jr,\ndee,7\n"
+        );
+
+        let (_, query) = SqlQuery::parse_from_raw("copy people from '/no/such/file.csv';").unwrap();
+        assert!(exec.run(query).is_err());
+
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_copy_round_trip() {
+        let path = env::temp_dir().join(format!("minisql-copy-round-trip-{}.csv", process::id()));
+        let mut exec = Execution::new();
+        run_all(
+            &mut exec,
+            &[
+                "create table t (s string, n int);",
+                "insert into t values null, 1;",
+                "insert into t values '', null;",
+                "insert into t values 'a \"b\", c', 2;",
+            ],
+        );
+        let export = format!("copy t to '{}' with (header);", path.display());
+        assert_eq!(copy(&mut exec, &export).rows, 3);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "s,n\n,1\n\"\",\n\"a \"\"b\"\", c\",2\n"
+        );
+
+        run_all(&mut exec, &["create table u (s string, n int);"]);
+        let import = format!("copy u from '{}' with (header);", path.display());
+        assert_eq!(
+            copy(&mut exec, &import),
+            CopyReport {
+                rows: 3,
+                errors: vec![]
+            }
+        );
+        assert_eq!(
+            run_all(&mut exec, &["select s, n from u;"]),
+            [
+                vec![Value::Null, 1.into()],
+                vec![Value::from(""), Value::Null],
+                vec![Value::from("a \"b\", c"), 2.into()],
+            ]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+use std::{collections::HashSet, rc::Rc};
+
+use sql_mini_parser::{
+    ast::{CommonTableExpression, WithClause},
+    commands::SetOperator,
+    expr::ColumnRef,
+    value::Value,
+};
+
+use crate::{
+    binder::Binder,
+    compound,
+    error::QueryExecutionError,
+    eval::Env,
+    operators::{self, row_count},
+    plan::LogicalPlan,
+    result::ResultSet,
+    row::Row,
+    select::{execute_core, execute_select},
+};
+
+/// Materialize every CTE of a WITH clause in order, binding each name in
+/// the context so later CTEs and the main select can read from it
+pub(crate) fn materialize(with: &WithClause, env: Env) -> Result<(), QueryExecutionError> {
+    for cte in &with.ctes {
+        let result = materialize_cte(cte, with.recursive, env)?;
+        env.ctx.bind_cte(&cte.name, Rc::new(result));
+    }
+    Ok(())
+}
+
+/// Give the result the CTE's column names, if it declared any
+fn rename(
+    cte: &CommonTableExpression,
+    result: ResultSet,
+) -> Result<ResultSet, QueryExecutionError> {
+    if cte.columns.is_empty() {
+        return Ok(result);
+    }
+    if cte.columns.len() != result.columns.len() {
+        return Err(QueryExecutionError::ColumnCountMismatch(
+            cte.columns.len(),
+            result.columns.len(),
+        ));
+    }
+    Ok(ResultSet::from_values(
+        cte.columns.clone().into(),
+        result.into_iter().map(Row::into_values),
+    ))
+}
+
+fn materialize_cte(
+    cte: &CommonTableExpression,
+    recursive: bool,
+    env: Env,
+) -> Result<ResultSet, QueryExecutionError> {
+    let query = &cte.query;
+    let unions = query
+        .compounds
+        .iter()
+        .all(|term| matches!(term.op, SetOperator::Union | SetOperator::UnionAll));
+    if !recursive || query.compounds.is_empty() || !unions {
+        return rename(cte, execute_select(query, env)?);
+    }
+
+    let depth = env.ctx.cte_depth();
+    let result = match &query.with {
+        Some(with) => materialize(with, env).and_then(|_| iterate(cte, env)),
+        None => iterate(cte, env),
+    };
+    env.ctx.unbind_ctes(depth);
+    result
+}
+
+/// Evaluate a recursive CTE: the first select seeds the rows, then the
+/// others run against the rows added by the previous round until they add
+/// no more
+fn iterate(cte: &CommonTableExpression, env: Env) -> Result<ResultSet, QueryExecutionError> {
+    let query = &cte.query;
+    let ctx = env.ctx;
+    compound::check_columns(query, ctx)?;
+
+    let anchor = rename(cte, execute_core(&query.first, env)?)?;
+    let columns = anchor.columns.clone();
+    // without an ORDER BY the LIMIT can stop the recursion early
+    let wanted = match (&query.limit, query.order_by.is_empty()) {
+        (Some(limit), true) => {
+            let offset = match &query.offset {
+                Some(offset) => row_count(offset, "OFFSET", env)?,
+                None => 0,
+            };
+            Some(offset.saturating_add(row_count(limit, "LIMIT", env)?))
+        }
+        _ => None,
+    };
+
+    let mut seen: HashSet<Vec<Value>> = HashSet::new();
+    let mut rows: Vec<Vec<Value>> = Vec::new();
+    let mut working: Vec<Vec<Value>> = anchor.into_iter().map(Row::into_values).collect();
+    // the anchor is combined as if by the first operator
+    if query.compounds[0].op == SetOperator::Union {
+        working.retain(|row| seen.insert(row.clone()));
+    } else {
+        seen.extend(working.iter().cloned());
+    }
+    rows.extend(working.iter().cloned());
+
+    while !working.is_empty() && wanted.is_none_or(|wanted| rows.len() < wanted) {
+        let index = ctx.bind_cte(
+            &cte.name,
+            Rc::new(ResultSet::from_values(columns.clone(), working)),
+        );
+
+        let mut added = Vec::new();
+        let mut recursed = false;
+        for term in &query.compounds {
+            let result = execute_core(&term.select, env);
+            recursed |= ctx.cte_read(index);
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    ctx.unbind_ctes(index);
+                    return Err(err);
+                }
+            };
+            if result.columns.len() != columns.len() {
+                ctx.unbind_ctes(index);
+                return Err(QueryExecutionError::ColumnCountMismatch(
+                    columns.len(),
+                    result.columns.len(),
+                ));
+            }
+            added.extend(
+                result
+                    .into_iter()
+                    .map(Row::into_values)
+                    .filter(|row| term.op == SetOperator::UnionAll || seen.insert(row.clone())),
+            );
+        }
+        ctx.unbind_ctes(index);
+
+        rows.extend(added.iter().cloned());
+        // a compound that never reads the CTE only needs to run once
+        working = if recursed { added } else { Vec::new() };
+    }
+
+    let plan = LogicalPlan::Values {
+        name: cte.name.clone(),
+        columns: columns.iter().map(ColumnRef::new).collect(),
+        result: Rc::new(ResultSet::from_values(columns, rows)),
+    };
+    let plan = Binder::new(env).bind_order_limit(plan, query)?;
+    operators::execute(&plan, env)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        tests::{num, run_all},
+        Execution,
+    };
+
+    #[test]
+    fn test_recursive_cte() {
+        let mut exec = Execution::new();
+        let rows = run_all(
+            &mut exec,
+            &[
+                "create table org (name string, boss string);",
+                "insert into org values 'ceo', NULL;",
+                "insert into org values 'cto', 'ceo';",
+                "insert into org values 'dev', 'cto';",
+                "insert into org values 'cfo', 'ceo';",
+                "insert into org values 'ops', 'cfo';",
+                "with recursive reports (name) as ( \
+                   select name from org where name = 'cto' \
+                   union \
+                   select o.name from org o where o.boss in (select name from reports) \
+                 ) select count(*) from reports;",
+            ],
+        );
+        assert_eq!(rows, vec![vec![num(2)]]);
+
+        // later CTEs can read earlier ones
+        let rows = run_all(
+            &mut exec,
+            &[
+                "with bosses as (select boss from org where boss is not null), \
+                    top (name) as (select boss from bosses where boss = 'ceo') \
+               select count(*) from top;",
+            ],
+        );
+        assert_eq!(rows, vec![vec![num(2)]]);
+    }
+
+    #[test]
+    fn test_union_semantics() {
+        let mut exec = Execution::new();
+        let rows = run_all(
+            &mut exec,
+            &[
+                "create table one (n int);",
+                "insert into one values 1;",
+                "with recursive cnt (x) as ( \
+                   select n from one union all select x + 1 from cnt where x < 5 \
+                 ) select sum(x), count(*) from cnt;",
+            ],
+        );
+        assert_eq!(rows, vec![vec![num(15), num(5)]]);
+
+        // UNION stops once no new rows are produced even though the query never runs dry
+        let rows = run_all(
+            &mut exec,
+            &["with recursive flip (x) as ( \
+                 select n from one union select 1 - x from flip \
+               ) select x from flip;"],
+        );
+        assert_eq!(rows, vec![vec![num(1)], vec![num(0)]]);
+
+        // a LIMIT ends a recursion that would never run dry
+        let rows = run_all(
+            &mut exec,
+            &["with recursive nat (x) as ( \
+                 select n from one union all select x + 1 from nat limit 3 \
+               ) select sum(x) from nat;"],
+        );
+        assert_eq!(rows, vec![vec![num(6)]]);
+    }
+}
@@ -0,0 +1,251 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+use serde::{
+    de::{
+        self, value::StrDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess,
+        Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+use sql_mini_parser::value::Value;
+
+use crate::{error::QueryExecutionError, row::Row};
+
+impl de::Error for QueryExecutionError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        QueryExecutionError::Deserialize(msg.to_string())
+    }
+}
+
+/// A row deserializes as a map from column names to values, so structs get
+/// the column with the same name as each field, or as a sequence of values
+/// in column order for tuples
+impl<'de> Deserializer<'de> for &'de Row {
+    type Error = QueryExecutionError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(RowAccess { row: self, next: 0 })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(RowAccess { row: self, next: 0 })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// Walks the columns of a row, as map entries or sequence elements
+struct RowAccess<'de> {
+    row: &'de Row,
+    next: usize,
+}
+
+impl<'de> MapAccess<'de> for RowAccess<'de> {
+    type Error = QueryExecutionError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some(column) = self.row.columns().get(self.next) else {
+            return Ok(None);
+        };
+        let name: StrDeserializer<'_, Self::Error> = column.name.as_str().into_deserializer();
+        seed.deserialize(name).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = &self.row.values()[self.next];
+        self.next += 1;
+        seed.deserialize(ValueDeserializer(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.values().len() - self.next)
+    }
+}
+
+impl<'de> SeqAccess<'de> for RowAccess<'de> {
+    type Error = QueryExecutionError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let Some(value) = self.row.values().get(self.next) else {
+            return Ok(None);
+        };
+        self.next += 1;
+        seed.deserialize(ValueDeserializer(value)).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.values().len() - self.next)
+    }
+}
+
+/// Numbers become integers when they have no fractional part and fit,
+/// floats otherwise, and NULL is `None` or `()`
+struct ValueDeserializer<'de>(&'de Value);
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = QueryExecutionError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Number(n) if n.is_integer() => match (n.to_i64(), n.to_u64()) {
+                (Some(n), _) => visitor.visit_i64(n),
+                (None, Some(n)) => visitor.visit_u64(n),
+                (None, None) => visitor.visit_f64(float(n)?),
+            },
+            Value::Number(n) => visitor.visit_f64(float(n)?),
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Number(n) => visitor.visit_f64(float(n)?),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Strings name unit variants
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::String(s) => {
+                let variant: StrDeserializer<'_, Self::Error> = s.as_str().into_deserializer();
+                visitor.visit_enum(variant)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+fn float(n: &BigDecimal) -> Result<f64, QueryExecutionError> {
+    n.to_f64()
+        .ok_or_else(|| QueryExecutionError::Deserialize(format!("{n} does not fit a float")))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{error::QueryExecutionError, tests::execution};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Team {
+        Red,
+        Blue,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Player {
+        name: String,
+        score: u32,
+        team: Option<Team>,
+        ratio: f64,
+    }
+
+    const TABLES: &[&str] = &[
+        "create table players (name string, score int, team string);",
+        "insert into players values 'ann', 30, 'red';",
+        "insert into players values 'bob', 20, null;",
+    ];
+
+    #[test]
+    fn test_query_as() {
+        let mut exec = execution(TABLES);
+        let players: Vec<Player> = exec
+            .query_as("select score / 40 as ratio, team, name, score from players order by name;")
+            .unwrap();
+        assert_eq!(
+            players,
+            vec![
+                Player {
+                    name: "ann".into(),
+                    score: 30,
+                    team: Some(Team::Red),
+                    ratio: 0.75,
+                },
+                Player {
+                    name: "bob".into(),
+                    score: 20,
+                    team: None,
+                    ratio: 0.5,
+                },
+            ]
+        );
+
+        let pairs: Vec<(String, i64)> = exec
+            .query_as("select name, score * 2 from players where score > 25;")
+            .unwrap();
+        assert_eq!(pairs, vec![("ann".to_owned(), 60)]);
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        let mut exec = execution(TABLES);
+        let missing = exec.query_as::<Player>("select name, score from players;");
+        assert!(matches!(missing, Err(QueryExecutionError::Deserialize(_))));
+
+        let negative = exec.query_as::<(u8,)>("select 0 - score from players;");
+        assert!(matches!(negative, Err(QueryExecutionError::Deserialize(_))));
+    }
+}
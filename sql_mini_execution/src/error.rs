@@ -1,6 +1,8 @@
 use sql_mini_parser::{commands::SqlTypeInfo, value::Value};
 use thiserror::Error;
 
+use crate::functions::Arity;
+
 /// Query exectuion errors
 
 #[derive(Error, Debug)]
@@ -12,9 +14,97 @@ pub enum QueryExecutionError {
     #[error("Table {0} already exists")]
     TAbleAlreadyExists(String),
 
+    #[error("Index {0} already exists")]
+    IndexAlreadyExists(String),
+
     #[error("Column {0} does not exist")]
     ColumnDoesNotExist(String),
 
+    #[error("Column {0} is ambiguous, qualify it with a table name")]
+    AmbiguousColumn(String),
+
     #[error("Value {1} can not be inserted into a {0} column")]
     InsertTypeMismatch(SqlTypeInfo, Value),
+
+    #[error("Could not parse {0}")]
+    Syntax(String),
+
+    #[error("Parameter {0} does not exist")]
+    UnknownParameter(String),
+
+    #[error("Parameter {0} has no value bound")]
+    UnboundParameter(String),
+
+    #[error(
+        "Parameter {parameter} is compared to or inserted into {} column, got {}",
+        with_article(.expected),
+        .value.to_sql()
+    )]
+    ParameterTypeMismatch {
+        parameter: String,
+        expected: SqlTypeInfo,
+        value: Value,
+    },
+
+    #[error("Function {0} does not exist")]
+    FunctionNotFound(String),
+
+    #[error("Function {name} takes {expected} arguments but {got} were given")]
+    WrongArgumentCount {
+        name: String,
+        expected: Arity,
+        got: usize,
+    },
+
+    #[error("Aggregate function {0} is not allowed here")]
+    MisplacedAggregate(String),
+
+    #[error("Window function {0} is only allowed in the select list and ORDER BY")]
+    MisplacedWindowFunction(String),
+
+    #[error("Invalid window frame {0}")]
+    InvalidWindowFrame(String),
+
+    #[error("Operator {0} can not be applied to {1}")]
+    InvalidOperand(String, Value),
+
+    #[error("Invalid pattern or escape {0}")]
+    InvalidPattern(String),
+
+    #[error("Subquery must return a single column, got {0}")]
+    SubqueryColumnCount(usize),
+
+    #[error("Subquery used as an expression returned more than one row")]
+    SubqueryRowCount,
+
+    #[error("Expected {0} columns, got {1}")]
+    ColumnCountMismatch(usize, usize),
+
+    #[error("{op} combines {left} and {right} values in column {column}")]
+    IncompatibleColumnTypes {
+        op: String,
+        column: String,
+        left: String,
+        right: String,
+    },
+
+    #[error("Division by zero")]
+    DivisionByZero,
+
+    #[error("Could not decode row: {0}")]
+    Deserialize(String),
+
+    #[error("Function error: {0}")]
+    FunctionError(String),
+
+    #[error("Could not copy {path}: {reason}")]
+    CopyFile { path: String, reason: String },
+}
+
+/// `an INT` or `a STRING`, for messages naming a type
+fn with_article(type_info: &SqlTypeInfo) -> String {
+    match type_info {
+        SqlTypeInfo::Int => format!("an {type_info}"),
+        SqlTypeInfo::String => format!("a {type_info}"),
+    }
 }
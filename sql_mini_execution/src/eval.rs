@@ -0,0 +1,654 @@
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use bigdecimal::Zero;
+use sql_mini_parser::{
+    commands::{CompoundSelect, SqlTypeInfo},
+    expr::{
+        BinaryOperator, ColumnRef, Expression, FunctionArgs, FunctionCall, Parameter, UnaryOperator,
+    },
+    value::Value,
+};
+
+use crate::{
+    error::QueryExecutionError, explain::Profiler, functions::FunctionRegistry, pattern::Pattern,
+    result::ResultSet, row::Row, select::execute_select, table::Table, window::window_column,
+};
+
+/// The result of a subquery, along with a lookup set built the first time it
+/// is used by `IN`
+pub(crate) struct SubqueryResult {
+    pub(crate) result: ResultSet,
+    values: OnceCell<HashSet<Value>>,
+}
+
+impl SubqueryResult {
+    fn single_column(&self) -> Result<(), QueryExecutionError> {
+        match self.result.columns.len() {
+            1 => Ok(()),
+            n => Err(QueryExecutionError::SubqueryColumnCount(n)),
+        }
+    }
+
+    /// `value IN (<subquery>)`
+    fn contains(&self, value: &Value) -> Result<Value, QueryExecutionError> {
+        self.single_column()?;
+        let values = self.values.get_or_init(|| {
+            self.result
+                .rows()
+                .iter()
+                .map(|row| row.values()[0].clone())
+                .collect()
+        });
+
+        if value.is_null() {
+            Ok(Value::Null)
+        } else if values.contains(value) {
+            Ok(Value::Bool(true))
+        } else if values.contains(&Value::Null) {
+            Ok(Value::Null)
+        } else {
+            Ok(Value::Bool(false))
+        }
+    }
+}
+
+/// Everything needed to evaluate expressions, shared by a query and all of
+/// its subqueries
+pub(crate) struct Context<'a> {
+    pub(crate) functions: &'a FunctionRegistry,
+    pub(crate) tables: &'a HashMap<String, Table>,
+    /// Results of subqueries that do not reference an enclosing query, so
+    /// they are only run once, keyed by their statement as plans are bound
+    /// again for every run. Cleared when the CTEs in scope change, the same
+    /// statement can read other rows then
+    subqueries: RefCell<HashMap<CompoundSelect, Rc<SubqueryResult>>>,
+    /// Materialized common table expressions in scope, innermost last
+    ctes: RefCell<Vec<CteBinding>>,
+    /// Set to measure the operators of every subquery, for EXPLAIN ANALYZE
+    pub(crate) profiler: Option<Profiler>,
+    /// Values bound to the numbered parameters of a prepared statement
+    parameters: &'a [Value],
+    /// The types of the columns the parameters are compared to, known while
+    /// planning when the values are not
+    parameter_types: &'a [Option<SqlTypeInfo>],
+}
+
+/// A CTE name and the rows it refers to
+struct CteBinding {
+    name: String,
+    result: Rc<ResultSet>,
+    /// Set once a query reads the rows, used to find recursive references
+    read: Cell<bool>,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(functions: &'a FunctionRegistry, tables: &'a HashMap<String, Table>) -> Self {
+        Self {
+            functions,
+            tables,
+            subqueries: RefCell::new(HashMap::new()),
+            ctes: RefCell::new(Vec::new()),
+            profiler: None,
+            parameters: &[],
+            parameter_types: &[],
+        }
+    }
+
+    /// Evaluate `?<n>` to the n-th of `parameters`
+    pub(crate) fn with_parameters(mut self, parameters: &'a [Value]) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Plan knowing parameter `n` only takes values of `types[n - 1]`
+    pub(crate) fn with_parameter_types(mut self, types: &'a [Option<SqlTypeInfo>]) -> Self {
+        self.parameter_types = types;
+        self
+    }
+
+    /// The type of values parameter `n` takes, if it is known
+    pub(crate) fn parameter_type(&self, n: usize) -> Option<SqlTypeInfo> {
+        self.parameter_types
+            .get(n.checked_sub(1)?)
+            .copied()
+            .flatten()
+    }
+
+    /// Measure the operators of subqueries as they run
+    pub(crate) fn profiled(mut self) -> Self {
+        self.profiler = Some(Profiler::default());
+        self
+    }
+
+    /// The stored table called `name`
+    pub(crate) fn table(&self, name: &str) -> Result<&'a Table, QueryExecutionError> {
+        self.tables
+            .get(name)
+            .ok_or_else(|| QueryExecutionError::TableNotFound(name.to_owned()))
+    }
+
+    /// The innermost CTE called `name`, if any
+    pub(crate) fn cte(&self, name: &str) -> Option<Rc<ResultSet>> {
+        self.ctes
+            .borrow()
+            .iter()
+            .rev()
+            .find(|cte| cte.name == name)
+            .map(|cte| cte.result.clone())
+    }
+
+    /// Like [`Context::cte`] but records that the rows were read
+    pub(crate) fn read_cte(&self, name: &str) -> Option<Rc<ResultSet>> {
+        let ctes = self.ctes.borrow();
+        let cte = ctes.iter().rev().find(|cte| cte.name == name)?;
+        cte.read.set(true);
+        Some(cte.result.clone())
+    }
+
+    /// Bring a CTE into scope, returning its index for [`Context::cte_read`]
+    pub(crate) fn bind_cte(&self, name: &str, result: Rc<ResultSet>) -> usize {
+        self.subqueries.borrow_mut().clear();
+        let mut ctes = self.ctes.borrow_mut();
+        ctes.push(CteBinding {
+            name: name.to_owned(),
+            result,
+            read: Cell::new(false),
+        });
+        ctes.len() - 1
+    }
+
+    /// Whether the CTE bound at `index` has been read since it was bound
+    pub(crate) fn cte_read(&self, index: usize) -> bool {
+        self.ctes
+            .borrow()
+            .get(index)
+            .is_some_and(|cte| cte.read.get())
+    }
+
+    /// The number of CTEs in scope, to restore with [`Context::unbind_ctes`]
+    pub(crate) fn cte_depth(&self) -> usize {
+        self.ctes.borrow().len()
+    }
+
+    pub(crate) fn unbind_ctes(&self, depth: usize) {
+        let mut ctes = self.ctes.borrow_mut();
+        if ctes.len() > depth {
+            ctes.truncate(depth);
+            self.subqueries.borrow_mut().clear();
+        }
+    }
+
+    /// Run `select` as a subquery of the query currently evaluating `scope`
+    fn subquery(
+        &self,
+        select: &CompoundSelect,
+        scope: &Scope,
+    ) -> Result<Rc<SubqueryResult>, QueryExecutionError> {
+        if let Some(cached) = self.subqueries.borrow().get(select) {
+            return Ok(cached.clone());
+        }
+
+        let correlated = Cell::new(false);
+        let env = Env {
+            ctx: self,
+            outer: Some(scope),
+            correlated: Some(&correlated),
+        };
+        let result = Rc::new(SubqueryResult {
+            result: match &self.profiler {
+                Some(profiler) => profiler.run_subquery(select, env)?,
+                None => execute_select(select, env)?,
+            },
+            values: OnceCell::new(),
+        });
+        if !correlated.get() {
+            self.subqueries
+                .borrow_mut()
+                .insert(select.clone(), result.clone());
+        }
+        Ok(result)
+    }
+}
+
+/// The row an expression is evaluated against, along with the rows currently
+/// being evaluated by enclosing queries
+#[derive(Clone, Copy)]
+pub(crate) struct Scope<'a> {
+    pub(crate) row: &'a Row,
+    pub(crate) outer: Option<&'a Scope<'a>>,
+    /// Set when an expression in this scope references an enclosing query
+    pub(crate) correlated: Option<&'a Cell<bool>>,
+}
+
+impl<'a> Scope<'a> {
+    pub(crate) fn new(
+        row: &'a Row,
+        outer: Option<&'a Scope<'a>>,
+        correlated: Option<&'a Cell<bool>>,
+    ) -> Self {
+        Self {
+            row,
+            outer,
+            correlated,
+        }
+    }
+
+    /// Find `column` in the innermost scope that has it
+    fn lookup(&self, column: &ColumnRef) -> Result<Value, QueryExecutionError> {
+        let mut crossed = Vec::new();
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            if let Some(value) = current.row.resolve(column) {
+                crossed
+                    .into_iter()
+                    .for_each(|flag: &Cell<bool>| flag.set(true));
+                return Ok(value.clone());
+            }
+            crossed.extend(current.correlated);
+            scope = current.outer;
+        }
+        Err(QueryExecutionError::ColumnDoesNotExist(column.to_string()))
+    }
+}
+
+/// Everything a query needs to evaluate its expressions
+#[derive(Clone, Copy)]
+pub(crate) struct Env<'a> {
+    pub(crate) ctx: &'a Context<'a>,
+    /// The row of the enclosing query when running a subquery
+    pub(crate) outer: Option<&'a Scope<'a>>,
+    /// Set if the query references any column of an enclosing query
+    pub(crate) correlated: Option<&'a Cell<bool>>,
+}
+
+impl<'a> Env<'a> {
+    /// The environment of a top level query
+    pub(crate) fn new(ctx: &'a Context<'a>) -> Self {
+        Self {
+            ctx,
+            outer: None,
+            correlated: None,
+        }
+    }
+
+    /// Evaluate `expr` against a row of the query
+    pub(crate) fn evaluate(
+        &self,
+        expr: &Expression,
+        row: &Row,
+    ) -> Result<Value, QueryExecutionError> {
+        evaluate(
+            expr,
+            &Scope::new(row, self.outer, self.correlated),
+            self.ctx,
+        )
+    }
+
+    /// Evaluate every expression of `exprs` against a row of the query
+    pub(crate) fn evaluate_all<'e>(
+        &self,
+        exprs: impl IntoIterator<Item = &'e Expression>,
+        row: &Row,
+    ) -> Result<Vec<Value>, QueryExecutionError> {
+        let scope = Scope::new(row, self.outer, self.correlated);
+        exprs
+            .into_iter()
+            .map(|expr| evaluate(expr, &scope, self.ctx))
+            .collect()
+    }
+}
+
+/// Name of the column holding the already computed result of an aggregate call
+///
+/// `#` can not appear in an identifier so this never clashes with a real column
+pub(crate) fn aggregate_column(call: &FunctionCall) -> String {
+    format!("#{call}")
+}
+
+/// Total order over values, used for sorting and min/max
+///
+/// Values of different types are ordered NULL < booleans < numbers < strings
+pub(crate) fn compare_values(left: &Value, right: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+        }
+    }
+
+    match (left, right) {
+        (Value::Bool(l), Value::Bool(r)) => l.cmp(r),
+        (Value::Number(l), Value::Number(r)) => l.cmp(r),
+        (Value::String(l), Value::String(r)) => l.cmp(r),
+        (l, r) => rank(l).cmp(&rank(r)),
+    }
+}
+
+/// Whether a value passes a filter, NULL counts as false
+pub(crate) fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::Number(n) => !n.is_zero(),
+        Value::String(_) | Value::Null => false,
+    }
+}
+
+/// Evaluate an expression against a single row
+pub(crate) fn evaluate(
+    expr: &Expression,
+    scope: &Scope,
+    ctx: &Context,
+) -> Result<Value, QueryExecutionError> {
+    match expr {
+        Expression::Literal(value) => Ok(value.clone()),
+        Expression::Parameter(Parameter::Numbered(n)) => ctx
+            .parameters
+            .get(n - 1)
+            .cloned()
+            .ok_or_else(|| QueryExecutionError::UnboundParameter(expr.to_string())),
+        Expression::Parameter(_) => Err(QueryExecutionError::UnboundParameter(expr.to_string())),
+        Expression::Column(column) => scope.lookup(column),
+        Expression::Function(call) if ctx.functions.is_aggregate(&call.name) => scope
+            .row
+            .get(&aggregate_column(call))
+            .map_err(|_| QueryExecutionError::MisplacedAggregate(call.name.clone())),
+        Expression::Window(window) => scope
+            .row
+            .get(&window_column(window))
+            .map_err(|_| QueryExecutionError::MisplacedWindowFunction(window.call.name.clone())),
+        Expression::Function(call) => {
+            let args = match &call.args {
+                FunctionArgs::Wildcard => vec![],
+                FunctionArgs::List(args) => args
+                    .iter()
+                    .map(|arg| evaluate(arg, scope, ctx))
+                    .collect::<Result<Vec<_>, _>>()?,
+            };
+            ctx.functions.call_scalar(&call.name, &args)
+        }
+        Expression::Unary { op, expr } => {
+            let value = evaluate(expr, scope, ctx)?;
+            match (op, value) {
+                (_, Value::Null) => Ok(Value::Null),
+                (UnaryOperator::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+                (UnaryOperator::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+                (op, v) => Err(QueryExecutionError::InvalidOperand(op.to_string(), v)),
+            }
+        }
+        Expression::Binary { left, op, right } => {
+            let left = evaluate(left, scope, ctx)?;
+            // AND/OR only look at the right side when it can change the outcome
+            match (op, &left) {
+                (BinaryOperator::And, Value::Bool(false)) => return Ok(Value::Bool(false)),
+                (BinaryOperator::Or, Value::Bool(true)) => return Ok(Value::Bool(true)),
+                _ => {}
+            }
+            let right = evaluate(right, scope, ctx)?;
+            binary_op(*op, left, right)
+        }
+        Expression::IsNull { expr, negated } => {
+            let value = evaluate(expr, scope, ctx)?;
+            Ok(Value::Bool(value.is_null() != *negated))
+        }
+        Expression::Case {
+            operand,
+            when_clauses,
+            else_result,
+        } => {
+            let operand = match operand {
+                Some(operand) => Some(evaluate(operand, scope, ctx)?),
+                None => None,
+            };
+            for when in when_clauses {
+                let condition = evaluate(&when.condition, scope, ctx)?;
+                let matched = match &operand {
+                    Some(operand) => {
+                        binary_op(BinaryOperator::Eq, operand.clone(), condition)?
+                            == Value::Bool(true)
+                    }
+                    None => is_truthy(&condition),
+                };
+                if matched {
+                    return evaluate(&when.result, scope, ctx);
+                }
+            }
+            match else_result {
+                Some(else_result) => evaluate(else_result, scope, ctx),
+                None => Ok(Value::Null),
+            }
+        }
+        Expression::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => {
+            let value = evaluate(expr, scope, ctx)?;
+            let low = evaluate(low, scope, ctx)?;
+            let high = evaluate(high, scope, ctx)?;
+            let result = binary_op(
+                BinaryOperator::And,
+                binary_op(BinaryOperator::GtEq, value.clone(), low)?,
+                binary_op(BinaryOperator::LtEq, value, high)?,
+            )?;
+            Ok(negate_if(result, *negated))
+        }
+        Expression::InList {
+            expr,
+            negated,
+            list,
+        } => {
+            let value = evaluate(expr, scope, ctx)?;
+            let candidates = list
+                .iter()
+                .map(|item| evaluate(item, scope, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(negate_if(in_list(&value, candidates.iter()), *negated))
+        }
+        Expression::Pattern {
+            expr,
+            negated,
+            op,
+            pattern,
+            escape,
+        } => {
+            let value = evaluate(expr, scope, ctx)?;
+            let pattern = evaluate(pattern, scope, ctx)?;
+            let escape = match escape {
+                Some(escape) => Some(evaluate(escape, scope, ctx)?),
+                None => None,
+            };
+
+            let result = match (value, pattern, escape) {
+                (Value::Null, _, _) | (_, Value::Null, _) | (_, _, Some(Value::Null)) => {
+                    Value::Null
+                }
+                (Value::String(text), Value::String(pattern), escape) => {
+                    let escape = match escape {
+                        Some(Value::String(escape)) => Some(escape),
+                        Some(v) => {
+                            return Err(QueryExecutionError::InvalidOperand(op.to_string(), v))
+                        }
+                        None => None,
+                    };
+                    let pattern = Pattern::new(*op, &pattern, escape.as_deref())?;
+                    Value::Bool(pattern.matches(&text))
+                }
+                (Value::String(_), v, _) | (v, _, _) => {
+                    return Err(QueryExecutionError::InvalidOperand(op.to_string(), v))
+                }
+            };
+            Ok(negate_if(result, *negated))
+        }
+        Expression::Subquery(select) => {
+            let subquery = ctx.subquery(select, scope)?;
+            subquery.single_column()?;
+            match subquery.result.rows() {
+                [] => Ok(Value::Null),
+                [row] => Ok(row.values()[0].clone()),
+                _ => Err(QueryExecutionError::SubqueryRowCount),
+            }
+        }
+        Expression::InSubquery {
+            expr,
+            negated,
+            subquery,
+        } => {
+            let value = evaluate(expr, scope, ctx)?;
+            let subquery = ctx.subquery(subquery, scope)?;
+            Ok(negate_if(subquery.contains(&value)?, *negated))
+        }
+        Expression::Exists(select) => {
+            let subquery = ctx.subquery(select, scope)?;
+            Ok(Value::Bool(!subquery.result.is_empty()))
+        }
+    }
+}
+
+/// Apply NOT to a boolean result when `negated`, NULL stays NULL
+fn negate_if(value: Value, negated: bool) -> Value {
+    match value {
+        Value::Bool(b) if negated => Value::Bool(!b),
+        value => value,
+    }
+}
+
+/// `value IN (candidates)`, NULL when there is no match but a NULL was involved
+pub(crate) fn in_list<'a>(value: &Value, candidates: impl Iterator<Item = &'a Value>) -> Value {
+    if value.is_null() {
+        return Value::Null;
+    }
+    let mut saw_null = false;
+    for candidate in candidates {
+        if candidate.is_null() {
+            saw_null = true;
+        } else if compare_values(value, candidate).is_eq() {
+            return Value::Bool(true);
+        }
+    }
+    if saw_null {
+        Value::Null
+    } else {
+        Value::Bool(false)
+    }
+}
+
+/// Apply a binary operator following sql's three valued logic
+pub(crate) fn binary_op(
+    op: BinaryOperator,
+    left: Value,
+    right: Value,
+) -> Result<Value, QueryExecutionError> {
+    use BinaryOperator as Op;
+
+    match (op, left, right) {
+        (Op::And, Value::Bool(false), _) | (Op::And, _, Value::Bool(false)) => {
+            Ok(Value::Bool(false))
+        }
+        (Op::Or, Value::Bool(true), _) | (Op::Or, _, Value::Bool(true)) => Ok(Value::Bool(true)),
+        (Op::And, Value::Bool(l), Value::Bool(r)) => Ok(Value::Bool(l && r)),
+        (Op::Or, Value::Bool(l), Value::Bool(r)) => Ok(Value::Bool(l || r)),
+        (Op::And | Op::Or, Value::Null | Value::Bool(_), Value::Null | Value::Bool(_)) => {
+            // at least one side is NULL, otherwise an arm above matched
+            Ok(Value::Null)
+        }
+        (_, Value::Null, _) | (_, _, Value::Null) => Ok(Value::Null),
+        (Op::Eq | Op::NotEq | Op::Lt | Op::LtEq | Op::Gt | Op::GtEq, left, right) => {
+            let ordering = compare_values(&left, &right);
+            let result = match op {
+                Op::Eq => ordering.is_eq(),
+                Op::NotEq => ordering.is_ne(),
+                Op::Lt => ordering.is_lt(),
+                Op::LtEq => ordering.is_le(),
+                Op::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            };
+            Ok(Value::Bool(result))
+        }
+        (Op::Concat, left, right) => Ok(Value::String(format!("{left}{right}"))),
+        (Op::Plus, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+        (Op::Minus, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
+        (Op::Multiply, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
+        (Op::Divide | Op::Modulo, Value::Number(_), Value::Number(r)) if r.is_zero() => {
+            Err(QueryExecutionError::DivisionByZero)
+        }
+        (Op::Divide, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l / r)),
+        (Op::Modulo, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l % r)),
+        (op, Value::Number(_), v) | (op, v, _) => {
+            Err(QueryExecutionError::InvalidOperand(op.to_string(), v))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{expr::BinaryOperator, value::Value};
+
+    use crate::tests::run_all;
+    use crate::Execution;
+
+    use super::binary_op;
+
+    #[test]
+    fn test_three_valued_logic() {
+        let and = |l, r| binary_op(BinaryOperator::And, l, r).unwrap();
+        let or = |l, r| binary_op(BinaryOperator::Or, l, r).unwrap();
+
+        assert_eq!(and(Value::Null, Value::Bool(false)), Value::Bool(false));
+        assert_eq!(and(Value::Null, Value::Bool(true)), Value::Null);
+        assert_eq!(or(Value::Null, Value::Bool(true)), Value::Bool(true));
+        assert_eq!(or(Value::Bool(false), Value::Null), Value::Null);
+        assert_eq!(
+            binary_op(BinaryOperator::Eq, Value::Null, Value::Null).unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_predicates() {
+        let mut exec = Execution::new();
+        let rows = run_all(
+            &mut exec,
+            &[
+                "create table t (a int, b string);",
+                "insert into t values 1, 'apple';",
+                "insert into t values 5, 'Banana';",
+                "insert into t values NULL, 'cherry';",
+                "select a between 2 and 6, a not in (1, NULL), b ilike 'b%', \
+                 case when a > 2 then 'big' when a is null then 'none' else 'small' end \
+                 from t;",
+            ],
+        );
+
+        assert_eq!(
+            rows,
+            vec![
+                vec![
+                    Value::Bool(false),
+                    Value::Bool(false),
+                    Value::Bool(false),
+                    Value::String("small".into())
+                ],
+                vec![
+                    Value::Bool(true),
+                    Value::Null,
+                    Value::Bool(true),
+                    Value::String("big".into())
+                ],
+                vec![
+                    Value::Null,
+                    Value::Null,
+                    Value::Bool(false),
+                    Value::String("none".into())
+                ],
+            ]
+        );
+    }
+}
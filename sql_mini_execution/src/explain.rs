@@ -0,0 +1,309 @@
+//! `EXPLAIN`, describing the plan chosen for a query, and `EXPLAIN ANALYZE`,
+//! which also runs the query and reports what every operator did
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+    time::Duration,
+};
+
+use sql_mini_parser::{commands::CompoundSelect, expr::Expression};
+
+use crate::{
+    error::QueryExecutionError, eval::Env, operators, plan::LogicalPlan, result::ResultSet,
+    select::with_plan,
+};
+
+/// What an operator did while its query ran
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperatorStats {
+    /// Rows produced, over every loop
+    pub rows: u64,
+    /// How many times the operator ran, once per row of the enclosing query
+    /// for a correlated subquery
+    pub loops: u64,
+    /// Time spent producing rows, including the operators it reads from
+    pub elapsed: Duration,
+}
+
+/// A node of an explained plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainNode {
+    /// What the node does, like `Filter: salary > 10`
+    pub description: String,
+    /// Only measured with `EXPLAIN ANALYZE`
+    pub stats: Option<OperatorStats>,
+    /// The nodes this one reads rows from, followed by the subqueries it runs
+    pub inputs: Vec<ExplainNode>,
+}
+
+impl ExplainNode {
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.description, indent = depth * 2)?;
+        match self.stats {
+            Some(stats) if stats.loops == 0 => write!(f, " (never executed)")?,
+            Some(stats) => write!(
+                f,
+                " (rows={} loops={} time={:.3}ms)",
+                stats.rows,
+                stats.loops,
+                stats.elapsed.as_secs_f64() * 1000.0
+            )?,
+            None => {}
+        }
+        for input in &self.inputs {
+            writeln!(f)?;
+            input.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// One node per line, inputs indented below the node reading them
+impl fmt::Display for ExplainNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
+
+/// Counted by an operator as it runs
+#[derive(Default)]
+pub(crate) struct Counters {
+    pub(crate) rows: Cell<u64>,
+    pub(crate) loops: Cell<u64>,
+    pub(crate) elapsed: Cell<Duration>,
+}
+
+impl Counters {
+    fn stats(&self) -> OperatorStats {
+        OperatorStats {
+            rows: self.rows.get(),
+            loops: self.loops.get(),
+            elapsed: self.elapsed.get(),
+        }
+    }
+}
+
+/// The counters of every operator of a plan, shaped like the plan
+pub(crate) struct ProfileNode {
+    pub(crate) counters: Rc<Counters>,
+    pub(crate) inputs: Vec<ProfileNode>,
+}
+
+impl ProfileNode {
+    pub(crate) fn new(plan: &LogicalPlan) -> Self {
+        Self {
+            counters: Rc::default(),
+            inputs: plan.inputs().into_iter().map(ProfileNode::new).collect(),
+        }
+    }
+}
+
+/// The plan of a subquery and what its operators did over every run
+struct Subplan {
+    plan: LogicalPlan,
+    profile: ProfileNode,
+}
+
+/// Collects the profiles of the subqueries run while analyzing a query
+///
+/// Subqueries are told apart by their text, the same subquery written twice
+/// is reported as one
+#[derive(Default)]
+pub(crate) struct Profiler {
+    subplans: RefCell<HashMap<String, Rc<Subplan>>>,
+}
+
+impl Profiler {
+    /// Run a subquery, adding to the profile of its earlier runs
+    pub(crate) fn run_subquery(
+        &self,
+        query: &CompoundSelect,
+        env: Env,
+    ) -> Result<ResultSet, QueryExecutionError> {
+        with_plan(query, env, |plan| {
+            let subplan = self
+                .subplans
+                .borrow_mut()
+                .entry(query.to_string())
+                .or_insert_with(|| {
+                    Rc::new(Subplan {
+                        profile: ProfileNode::new(&plan),
+                        plan: plan.clone(),
+                    })
+                })
+                .clone();
+            operators::execute_profiled(&plan, env, &subplan.profile)
+        })
+    }
+
+    fn subplan(&self, query: &CompoundSelect) -> Option<Rc<Subplan>> {
+        self.subplans.borrow().get(&query.to_string()).cloned()
+    }
+}
+
+/// Describe the plan of `query`
+///
+/// If the context is [profiled](crate::eval::Context::profiled) the query
+/// runs first, to measure every operator
+pub(crate) fn explain(
+    query: &CompoundSelect,
+    env: Env,
+) -> Result<ExplainNode, QueryExecutionError> {
+    with_plan(query, env, |plan| match &env.ctx.profiler {
+        Some(profiler) => {
+            let profile = ProfileNode::new(&plan);
+            operators::execute_profiled(&plan, env, &profile)?;
+            Ok(describe(&plan, Some(&profile), Some(profiler)))
+        }
+        None => Ok(describe(&plan, None, None)),
+    })
+}
+
+fn describe(
+    plan: &LogicalPlan,
+    profile: Option<&ProfileNode>,
+    profiler: Option<&Profiler>,
+) -> ExplainNode {
+    let mut inputs: Vec<_> = plan
+        .inputs()
+        .into_iter()
+        .enumerate()
+        .map(|(i, input)| describe(input, profile.and_then(|p| p.inputs.get(i)), profiler))
+        .collect();
+
+    let mut subqueries = Vec::new();
+    for expr in plan.expressions() {
+        collect_subqueries(expr, &mut subqueries);
+    }
+    inputs.extend(
+        subqueries
+            .into_iter()
+            .map(|query| describe_subquery(query, profiler)),
+    );
+
+    ExplainNode {
+        description: plan.describe(),
+        stats: profile.map(|profile| profile.counters.stats()),
+        inputs,
+    }
+}
+
+fn describe_subquery(query: &CompoundSelect, profiler: Option<&Profiler>) -> ExplainNode {
+    let description = format!("SubPlan: ({query})");
+    let Some(profiler) = profiler else {
+        return ExplainNode {
+            description,
+            stats: None,
+            inputs: vec![],
+        };
+    };
+    let subplan = profiler.subplan(query);
+    match subplan {
+        Some(subplan) => ExplainNode {
+            description,
+            stats: None,
+            inputs: vec![describe(
+                &subplan.plan,
+                Some(&subplan.profile),
+                Some(profiler),
+            )],
+        },
+        // evaluation never reached it
+        None => ExplainNode {
+            description,
+            stats: Some(OperatorStats::default()),
+            inputs: vec![],
+        },
+    }
+}
+
+/// Collect the subqueries of `expr`, but not those nested in them
+fn collect_subqueries<'e>(expr: &'e Expression, subqueries: &mut Vec<&'e CompoundSelect>) {
+    match expr {
+        Expression::Subquery(query) | Expression::Exists(query) => subqueries.push(query),
+        Expression::InSubquery { expr, subquery, .. } => {
+            collect_subqueries(expr, subqueries);
+            subqueries.push(subquery);
+        }
+        expr => expr
+            .children()
+            .into_iter()
+            .for_each(|child| collect_subqueries(child, subqueries)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{ast::SqlQuery, parse::Parse};
+
+    use crate::{tests::execution, ExecResponse, Execution};
+
+    use super::ExplainNode;
+
+    fn explain(exec: &mut Execution, sql: &str) -> ExplainNode {
+        let (_, query) = SqlQuery::parse_from_raw(sql).unwrap();
+        match exec.run(query).unwrap() {
+            ExecResponse::Explain(node) => node,
+            response => panic!("expected a plan, got {response:?}"),
+        }
+    }
+
+    const TABLES: &[&str] = &[
+        "create table emp (name string, dept string, salary int);",
+        "insert into emp values 'ann', 'eng', 100;",
+        "insert into emp values 'bob', 'eng', 80;",
+        "insert into emp values 'cid', 'ops', 50;",
+    ];
+
+    #[test]
+    fn test_explain() {
+        let mut exec = execution(TABLES);
+        let plan = explain(
+            &mut exec,
+            "explain select name from emp where salary > 60 order by name;",
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Project: name\n\
+             \x20 Sort: name\n\
+             \x20   Filter: salary > 60\n\
+             \x20     Scan: emp (name, salary)"
+        );
+        assert!(plan.stats.is_none());
+    }
+
+    #[test]
+    fn test_explain_analyze() {
+        let mut exec = execution(TABLES);
+        let plan = explain(
+            &mut exec,
+            "explain analyze select e.name from emp e where e.salary > \
+             (select avg(i.salary) from emp i where i.dept = e.dept);",
+        );
+        let stats = |node: &ExplainNode| {
+            let stats = node.stats.unwrap();
+            (stats.rows, stats.loops)
+        };
+
+        let filter = &plan.inputs[0];
+        assert_eq!(stats(&plan), (1, 1));
+        assert_eq!(stats(filter), (1, 1));
+        assert_eq!(stats(&filter.inputs[0]), (3, 1));
+
+        // the correlated subquery runs once for every employee
+        let subplan = &filter.inputs[1];
+        assert!(subplan
+            .description
+            .starts_with("SubPlan: (SELECT avg(i.salary)"));
+        let mut scan = &subplan.inputs[0];
+        while let Some(input) = scan.inputs.first() {
+            scan = input;
+        }
+        assert_eq!(scan.description, "Scan: emp AS i (dept, salary)");
+        assert_eq!(stats(&subplan.inputs[0]), (3, 3));
+        assert_eq!(stats(scan), (9, 3));
+    }
+}
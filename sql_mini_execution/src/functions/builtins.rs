@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+
+use bigdecimal::{BigDecimal, Zero};
+use sql_mini_parser::value::Value;
+
+use crate::{error::QueryExecutionError, eval::compare_values};
+
+use super::{AggregateFunction, Arity, FunctionRegistry};
+
+/// Register all the built-in functions
+pub(super) fn register(registry: &mut FunctionRegistry) {
+    registry.register_scalar("abs", 1, |args| match &args[0] {
+        Value::Number(n) => Ok(Value::Number(n.abs())),
+        Value::Null => Ok(Value::Null),
+        v => Err(QueryExecutionError::InvalidOperand("abs".into(), v.clone())),
+    });
+    registry.register_scalar("coalesce", Arity::Variadic, |args| {
+        Ok(args
+            .iter()
+            .find(|v| !v.is_null())
+            .cloned()
+            .unwrap_or(Value::Null))
+    });
+    registry.register_scalar("length", 1, |args| match &args[0] {
+        Value::String(s) => Ok(Value::Number((s.chars().count() as u64).into())),
+        Value::Null => Ok(Value::Null),
+        v => Err(QueryExecutionError::InvalidOperand(
+            "length".into(),
+            v.clone(),
+        )),
+    });
+    registry.register_scalar("lower", 1, |args| {
+        map_string("lower", &args[0], str::to_lowercase)
+    });
+    registry.register_scalar("upper", 1, |args| {
+        map_string("upper", &args[0], str::to_uppercase)
+    });
+
+    registry.register_aggregate("count", Arity::Range(0, 1), Count::default);
+    registry.register_aggregate("sum", 1, Sum::default);
+    registry.register_aggregate("avg", 1, Avg::default);
+    registry.register_aggregate("min", 1, || Extreme::new(Ordering::Less));
+    registry.register_aggregate("max", 1, || Extreme::new(Ordering::Greater));
+}
+
+fn map_string(
+    name: &str,
+    value: &Value,
+    f: fn(&str) -> String,
+) -> Result<Value, QueryExecutionError> {
+    match value {
+        Value::String(s) => Ok(Value::String(f(s))),
+        Value::Null => Ok(Value::Null),
+        v => Err(QueryExecutionError::InvalidOperand(name.into(), v.clone())),
+    }
+}
+
+/// The number in `value`, or `None` for NULL
+fn as_number(name: &str, value: &Value) -> Result<Option<BigDecimal>, QueryExecutionError> {
+    match value {
+        Value::Number(n) => Ok(Some(n.clone())),
+        Value::Null => Ok(None),
+        v => Err(QueryExecutionError::InvalidOperand(name.into(), v.clone())),
+    }
+}
+
+/// `count(*)` counts rows, `count(expr)` counts non NULL values
+#[derive(Default)]
+struct Count(usize);
+
+impl AggregateFunction for Count {
+    fn step(&mut self, args: &[Value]) -> Result<(), QueryExecutionError> {
+        if args.first().is_none_or(|v| !v.is_null()) {
+            self.0 += 1;
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<Value, QueryExecutionError> {
+        Ok(Value::Number((self.0 as u64).into()))
+    }
+}
+
+#[derive(Default)]
+struct Sum(Option<BigDecimal>);
+
+impl AggregateFunction for Sum {
+    fn step(&mut self, args: &[Value]) -> Result<(), QueryExecutionError> {
+        if let Some(n) = as_number("sum", &args[0])? {
+            self.0 = Some(self.0.take().unwrap_or_else(BigDecimal::zero) + n);
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<Value, QueryExecutionError> {
+        Ok(self.0.take().map_or(Value::Null, Value::Number))
+    }
+}
+
+#[derive(Default)]
+struct Avg {
+    sum: BigDecimal,
+    count: usize,
+}
+
+impl AggregateFunction for Avg {
+    fn step(&mut self, args: &[Value]) -> Result<(), QueryExecutionError> {
+        if let Some(n) = as_number("avg", &args[0])? {
+            self.sum += n;
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<Value, QueryExecutionError> {
+        if self.count == 0 {
+            return Ok(Value::Null);
+        }
+        Ok(Value::Number(
+            &self.sum / BigDecimal::from(self.count as u64),
+        ))
+    }
+}
+
+/// `min` or `max`, keeps the value that orders as `keep` against the others
+struct Extreme {
+    keep: Ordering,
+    current: Option<Value>,
+}
+
+impl Extreme {
+    fn new(keep: Ordering) -> Self {
+        Self {
+            keep,
+            current: None,
+        }
+    }
+}
+
+impl AggregateFunction for Extreme {
+    fn step(&mut self, args: &[Value]) -> Result<(), QueryExecutionError> {
+        let value = &args[0];
+        if value.is_null() {
+            return Ok(());
+        }
+        match &self.current {
+            Some(current) if compare_values(value, current) != self.keep => {}
+            _ => self.current = Some(value.clone()),
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<Value, QueryExecutionError> {
+        Ok(self.current.take().unwrap_or(Value::Null))
+    }
+}
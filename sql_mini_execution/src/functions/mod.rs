@@ -0,0 +1,174 @@
+use std::{collections::HashMap, fmt};
+
+use derive_more::Display;
+use sql_mini_parser::value::Value;
+
+use crate::error::QueryExecutionError;
+
+mod builtins;
+
+/// The signature of a scalar function callable from sql
+pub type ScalarFunction = dyn Fn(&[Value]) -> Result<Value, QueryExecutionError>;
+
+/// An aggregate callable from sql, a fresh instance is created for every group
+///
+/// `step` is called once per row with the evaluated arguments and `finalize`
+/// once all rows of the group have been seen
+pub trait AggregateFunction {
+    fn step(&mut self, args: &[Value]) -> Result<(), QueryExecutionError>;
+
+    fn finalize(&mut self) -> Result<Value, QueryExecutionError>;
+}
+
+/// How many arguments a function accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Arity {
+    #[display("{_0}")]
+    Exact(usize),
+    #[display("{_0} to {_1}")]
+    Range(usize, usize),
+    #[display("any number of")]
+    Variadic,
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Self::Exact(n) => *n == count,
+            Self::Range(min, max) => (*min..=*max).contains(&count),
+            Self::Variadic => true,
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(value: usize) -> Self {
+        Self::Exact(value)
+    }
+}
+
+struct Scalar {
+    arity: Arity,
+    func: Box<ScalarFunction>,
+}
+
+struct Aggregate {
+    arity: Arity,
+    factory: Box<dyn Fn() -> Box<dyn AggregateFunction>>,
+}
+
+/// Every function callable from sql, built-in or registered by the user
+///
+/// Names are case insensitive
+pub struct FunctionRegistry {
+    scalars: HashMap<String, Scalar>,
+    aggregates: HashMap<String, Aggregate>,
+}
+
+impl FunctionRegistry {
+    /// A registry without even the built-in functions
+    pub fn empty() -> Self {
+        Self {
+            scalars: HashMap::new(),
+            aggregates: HashMap::new(),
+        }
+    }
+
+    /// Register a scalar function, replacing any function with the same name
+    pub fn register_scalar<F>(&mut self, name: &str, arity: impl Into<Arity>, func: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, QueryExecutionError> + 'static,
+    {
+        let name = name.to_lowercase();
+        self.aggregates.remove(&name);
+        self.scalars.insert(
+            name,
+            Scalar {
+                arity: arity.into(),
+                func: Box::new(func),
+            },
+        );
+    }
+
+    /// Register an aggregate function, replacing any function with the same name
+    pub fn register_aggregate<A, F>(&mut self, name: &str, arity: impl Into<Arity>, factory: F)
+    where
+        A: AggregateFunction + 'static,
+        F: Fn() -> A + 'static,
+    {
+        let name = name.to_lowercase();
+        self.scalars.remove(&name);
+        self.aggregates.insert(
+            name,
+            Aggregate {
+                arity: arity.into(),
+                factory: Box::new(move || Box::new(factory())),
+            },
+        );
+    }
+
+    pub fn is_aggregate(&self, name: &str) -> bool {
+        self.aggregates.contains_key(&name.to_lowercase())
+    }
+
+    /// Call the scalar function `name` with already evaluated arguments
+    pub fn call_scalar(&self, name: &str, args: &[Value]) -> Result<Value, QueryExecutionError> {
+        let lower = name.to_lowercase();
+        if self.aggregates.contains_key(&lower) {
+            return Err(QueryExecutionError::MisplacedAggregate(name.to_owned()));
+        }
+
+        let scalar = self
+            .scalars
+            .get(&lower)
+            .ok_or_else(|| QueryExecutionError::FunctionNotFound(name.to_owned()))?;
+        check_arity(name, scalar.arity, args.len())?;
+
+        (scalar.func)(args)
+    }
+
+    /// Create a new accumulator for the aggregate `name` called with `arg_count` arguments
+    pub fn new_aggregate(
+        &self,
+        name: &str,
+        arg_count: usize,
+    ) -> Result<Box<dyn AggregateFunction>, QueryExecutionError> {
+        let aggregate = self
+            .aggregates
+            .get(&name.to_lowercase())
+            .ok_or_else(|| QueryExecutionError::FunctionNotFound(name.to_owned()))?;
+        check_arity(name, aggregate.arity, arg_count)?;
+
+        Ok((aggregate.factory)())
+    }
+}
+
+pub(crate) fn check_arity(name: &str, arity: Arity, got: usize) -> Result<(), QueryExecutionError> {
+    if arity.accepts(got) {
+        Ok(())
+    } else {
+        Err(QueryExecutionError::WrongArgumentCount {
+            name: name.to_owned(),
+            expected: arity,
+            got,
+        })
+    }
+}
+
+impl Default for FunctionRegistry {
+    /// A registry with all the built-in functions
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        builtins::register(&mut registry);
+        registry
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionRegistry")
+            .field("scalars", &self.scalars.keys())
+            .field("aggregates", &self.aggregates.keys())
+            .finish()
+    }
+}
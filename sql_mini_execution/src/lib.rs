@@ -1,59 +1,430 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
+use copy::CopyReport;
 use derive_more::Display;
 use error::QueryExecutionError;
-use sql_mini_parser::ast::SqlQuery;
-use table::{Table, TableIter};
+use eval::{Context, Env, Scope};
+use explain::ExplainNode;
+use functions::{AggregateFunction, Arity, FunctionRegistry};
+use prepared::PreparedStatement;
+use result::ResultSet;
+use row::Row;
+use serde::de::DeserializeOwned;
+use sql_mini_parser::{
+    ast::SqlQuery,
+    commands::{CopyDirection, CreateStatement, SqlTypeInfo},
+    value::Value,
+};
+use table::Table;
 
+mod binder;
+mod compound;
+pub mod connection;
+pub mod copy;
+mod cte;
+mod de;
 pub mod error;
+mod eval;
+pub mod explain;
+pub mod functions;
+mod operators;
+mod optimizer;
+mod pattern;
+pub mod plan;
+pub mod prepared;
+pub mod result;
 pub mod row;
+mod select;
+pub mod stats;
 pub mod table;
+mod window;
 
 #[derive(Debug, Display)]
-pub enum ExecResponse<'a> {
+pub enum ExecResponse {
     #[display("{_0:?}")]
-    Select(TableIter<'a>),
+    Select(ResultSet),
     Insert,
     Create,
+    CreateIndex,
+    #[display("{_0}")]
+    Explain(ExplainNode),
+    Analyze,
+    #[display("{_0}")]
+    Copy(CopyReport),
 }
 
 #[derive(Debug, Default)]
 pub struct Execution {
     tables: HashMap<String, Table>,
+    functions: FunctionRegistry,
+    /// Changed whenever a plan made before may no longer be valid or best
+    catalog_version: u64,
 }
 
 impl Execution {
     pub fn new() -> Self {
         Self {
             tables: HashMap::new(),
+            functions: FunctionRegistry::default(),
+            catalog_version: 0,
         }
     }
 
+    /// Make a scalar function callable from sql, it is looked up by name case
+    /// insensitively and replaces any built-in with the same name
+    pub fn register_scalar_function<F>(&mut self, name: &str, arity: impl Into<Arity>, func: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, QueryExecutionError> + 'static,
+    {
+        self.functions.register_scalar(name, arity, func);
+    }
+
+    /// Make an aggregate function callable from sql, `factory` is called to
+    /// create a fresh [`AggregateFunction`] for every group
+    pub fn register_aggregate_function<A, F>(
+        &mut self,
+        name: &str,
+        arity: impl Into<Arity>,
+        factory: F,
+    ) where
+        A: AggregateFunction + 'static,
+        F: Fn() -> A + 'static,
+    {
+        self.functions.register_aggregate(name, arity, factory);
+    }
+
+    /// Parse and plan a single statement that may use parameters like `?`,
+    /// `?1` or `:name`
+    pub fn prepare(&self, sql: &str) -> Result<PreparedStatement, QueryExecutionError> {
+        let query = SqlQuery::parse_statement(sql)
+            .map_err(|err| QueryExecutionError::Syntax(err.to_string()))?;
+        self.prepare_query(query)
+    }
+
+    /// Plan a parsed statement, see [`Execution::prepare`]
+    pub fn prepare_query(&self, query: SqlQuery) -> Result<PreparedStatement, QueryExecutionError> {
+        let mut statement = PreparedStatement::new(query, &self.tables)?;
+        if let (SqlQuery::Select(select), true) = (&statement.query, statement.reusable) {
+            let ctx = Context::new(&self.functions, &self.tables)
+                .with_parameter_types(statement.parameter_types());
+            let plan = select::with_plan(select, Env::new(&ctx), Ok)?;
+            statement.plan = Some((self.catalog_version, plan));
+        }
+        Ok(statement)
+    }
+
+    /// Run a prepared statement with the values bound to its parameters
+    pub fn execute(
+        &mut self,
+        statement: &mut PreparedStatement,
+    ) -> Result<ExecResponse, QueryExecutionError> {
+        let parameters = statement.parameters()?;
+        let SqlQuery::Select(select) = &statement.query else {
+            return self.run_with(
+                statement.query.clone(),
+                &parameters,
+                statement.parameter_types(),
+            );
+        };
+
+        let ctx = Context::new(&self.functions, &self.tables)
+            .with_parameters(&parameters)
+            .with_parameter_types(statement.parameter_types());
+        let env = Env::new(&ctx);
+        let result = match &statement.plan {
+            Some((version, plan)) if *version == self.catalog_version => {
+                operators::execute(plan, env)?
+            }
+            _ if statement.reusable => {
+                let plan = select::with_plan(select, env, Ok)?;
+                let result = operators::execute(&plan, env)?;
+                statement.plan = Some((self.catalog_version, plan));
+                result
+            }
+            _ => select::execute_select(select, env)?,
+        };
+        Ok(ExecResponse::Select(result))
+    }
+
+    /// Run a select and decode each row into `T`, see [`Row::deserialize`]
+    pub fn query_as<T: DeserializeOwned>(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<T>, QueryExecutionError> {
+        let mut statement = self.prepare(sql)?;
+        match self.execute(&mut statement)? {
+            ExecResponse::Select(result) => result.rows().iter().map(Row::deserialize).collect(),
+            _ => Err(QueryExecutionError::Deserialize(
+                "the statement returns no rows".to_owned(),
+            )),
+        }
+    }
+
+    /// The names of every table, sorted
+    pub fn table_names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.tables.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// The statement that creates `table` as it is now, `None` if there is
+    /// no such table
+    pub fn describe(&self, table: &str) -> Option<CreateStatement> {
+        self.tables.get(table).map(|t| CreateStatement {
+            table: table.to_owned(),
+            columns: t.columns().iter().cloned().collect(),
+        })
+    }
+
     pub fn run(&mut self, query: SqlQuery) -> Result<ExecResponse, QueryExecutionError> {
+        self.run_with(query, &[], &[])
+    }
+
+    /// Run `query` with `?<n>` evaluating to the n-th of `parameters`, whose
+    /// values have the types in `parameter_types`
+    fn run_with(
+        &mut self,
+        query: SqlQuery,
+        parameters: &[Value],
+        parameter_types: &[Option<SqlTypeInfo>],
+    ) -> Result<ExecResponse, QueryExecutionError> {
         match query {
             SqlQuery::Select(select) => {
-                let columns = select.fields;
-                let table = select.table;
-                let table = self
-                    .tables
-                    .get(&table)
-                    .ok_or(QueryExecutionError::TableNotFound(table))?;
-
-                Ok(ExecResponse::Select(table.select(columns)?))
+                let ctx = Context::new(&self.functions, &self.tables)
+                    .with_parameters(parameters)
+                    .with_parameter_types(parameter_types);
+                Ok(ExecResponse::Select(select::execute_select(
+                    &select,
+                    Env::new(&ctx),
+                )?))
+            }
+            SqlQuery::Explain(statement) => {
+                let ctx = Context::new(&self.functions, &self.tables)
+                    .with_parameters(parameters)
+                    .with_parameter_types(parameter_types);
+                let ctx = if statement.analyze {
+                    ctx.profiled()
+                } else {
+                    ctx
+                };
+                Ok(ExecResponse::Explain(explain::explain(
+                    &statement.query,
+                    Env::new(&ctx),
+                )?))
+            }
+            SqlQuery::Analyze(analyze) => {
+                match analyze.table {
+                    Some(name) => match self.tables.get_mut(&name) {
+                        Some(table) => table.analyze(),
+                        None => return Err(QueryExecutionError::TableNotFound(name)),
+                    },
+                    None => self.tables.values_mut().for_each(Table::analyze),
+                }
+                self.catalog_version += 1;
+                Ok(ExecResponse::Analyze)
             }
             SqlQuery::Insert(insert) => {
+                let ctx = Context::new(&self.functions, &self.tables).with_parameters(parameters);
+                let row = Row::new(Rc::from([]), vec![]);
+                let values = insert
+                    .values
+                    .iter()
+                    .map(|expr| eval::evaluate(expr, &Scope::new(&row, None, None), &ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let Some(table) = self.tables.get_mut(&insert.table) else {
                     return Err(QueryExecutionError::TableNotFound(insert.table));
                 };
 
-                table.insert(insert.values)?;
+                table.insert(values)?;
                 Ok(ExecResponse::Insert)
             }
+            SqlQuery::CreateIndex(index) => {
+                if self.tables.values().any(|t| t.has_index_named(&index.name)) {
+                    return Err(QueryExecutionError::IndexAlreadyExists(index.name));
+                }
+                let Some(table) = self.tables.get_mut(&index.table) else {
+                    return Err(QueryExecutionError::TableNotFound(index.table));
+                };
+
+                table.create_index(&index.name, &index.column)?;
+                self.catalog_version += 1;
+                Ok(ExecResponse::CreateIndex)
+            }
+            SqlQuery::Copy(statement) => match statement.direction {
+                CopyDirection::From => {
+                    let Some(table) = self.tables.get_mut(&statement.table) else {
+                        return Err(QueryExecutionError::TableNotFound(statement.table));
+                    };
+                    let report = copy::copy_from(table, &statement.path, statement.options)?;
+                    Ok(ExecResponse::Copy(report))
+                }
+                CopyDirection::To => {
+                    let Some(table) = self.tables.get(&statement.table) else {
+                        return Err(QueryExecutionError::TableNotFound(statement.table));
+                    };
+                    let report = copy::copy_to(table, &statement.path, statement.options)?;
+                    Ok(ExecResponse::Copy(report))
+                }
+            },
             SqlQuery::Create(create) => {
                 let table = Table::new(create.columns);
                 self.tables.insert(create.table, table);
+                self.catalog_version += 1;
                 Ok(ExecResponse::Create)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{ast::SqlQuery, parse::Parse, value::Value};
+
+    use crate::{
+        error::QueryExecutionError, functions::AggregateFunction, ExecResponse, Execution,
+    };
+
+    pub(crate) fn run_all(exec: &mut Execution, queries: &[&str]) -> Vec<Vec<Value>> {
+        let mut last = vec![];
+        for query in queries {
+            let (_, query) = SqlQuery::parse_from_raw(query).unwrap();
+            if let ExecResponse::Select(result) = exec.run(query).unwrap() {
+                last = result.into_iter().map(|row| row.into_values()).collect();
+            }
+        }
+        last
+    }
+
+    /// A new execution that has run `queries`
+    pub(crate) fn execution(queries: &[&str]) -> Execution {
+        let mut exec = Execution::new();
+        run_all(&mut exec, queries);
+        exec
+    }
+
+    pub(crate) fn num(n: i64) -> Value {
+        Value::Number(n.into())
+    }
+
+    #[test]
+    fn test_builtin_aggregates() {
+        let mut exec = Execution::new();
+        let rows = run_all(
+            &mut exec,
+            &[
+                "create table people (name string, age int);",
+                "insert into people values 'ann', 30;",
+                "insert into people values 'bob', 20;",
+                "insert into people values 'ann', 10;",
+                "select upper(name), count(*), sum(age) + 1 from people where age > 15 group by name;",
+            ],
+        );
+
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::String("ANN".into()), num(1), num(31)],
+                vec![Value::String("BOB".into()), num(1), num(21)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_functions() {
+        struct Product(i64);
+
+        impl AggregateFunction for Product {
+            fn step(&mut self, args: &[Value]) -> Result<(), QueryExecutionError> {
+                if let Value::Number(n) = &args[0] {
+                    self.0 *= n.to_string().parse::<i64>().unwrap();
+                }
+                Ok(())
+            }
+
+            fn finalize(&mut self) -> Result<Value, QueryExecutionError> {
+                Ok(Value::Number(self.0.into()))
+            }
+        }
+
+        let mut exec = Execution::new();
+        exec.register_scalar_function("double", 1, |args| match &args[0] {
+            Value::Number(n) => Ok(Value::Number(n * 2)),
+            v => Err(QueryExecutionError::FunctionError(format!(
+                "can not double {v}"
+            ))),
+        });
+        exec.register_aggregate_function("product", 1, || Product(1));
+
+        let rows = run_all(
+            &mut exec,
+            &[
+                "create table nums (n int);",
+                "insert into nums values 2;",
+                "insert into nums values 3;",
+                "select PRODUCT(double(n)) from nums;",
+            ],
+        );
+        assert_eq!(rows, vec![vec![num(24)]]);
+
+        let (_, query) = SqlQuery::parse_from_raw("select double(n, n) from nums;").unwrap();
+        assert!(matches!(
+            exec.run(query),
+            Err(QueryExecutionError::WrongArgumentCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_create_index() {
+        let mut exec = Execution::new();
+        let rows = run_all(
+            &mut exec,
+            &[
+                "create table people (name string, age int);",
+                "insert into people values 'ann', 30;",
+                "create index people_age on people (age);",
+                "insert into people values 'bob', 30;",
+                "insert into people values 'cid', 20;",
+                "select name from people where age = 30;",
+            ],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::String("ann".into())],
+                vec![Value::String("bob".into())],
+            ]
+        );
+
+        let mut run = |sql: &str| exec.run(SqlQuery::parse_from_raw(sql).unwrap().1);
+        assert!(matches!(
+            run("create index people_age on people (name);"),
+            Err(QueryExecutionError::IndexAlreadyExists(_))
+        ));
+        assert!(matches!(
+            run("create index missing on people (height);"),
+            Err(QueryExecutionError::ColumnDoesNotExist(_))
+        ));
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut exec = Execution::new();
+        run_all(
+            &mut exec,
+            &[
+                "create table people (name string, age int);",
+                "create table \"order\" (id int);",
+            ],
+        );
+        assert_eq!(exec.table_names(), ["order", "people"]);
+        assert_eq!(
+            exec.describe("people").unwrap().to_string(),
+            "CREATE TABLE people (name STRING, age INT)"
+        );
+        assert_eq!(
+            exec.describe("order").unwrap().to_string(),
+            "CREATE TABLE \"order\" (id INT)"
+        );
+        assert!(exec.describe("missing").is_none());
+    }
+}
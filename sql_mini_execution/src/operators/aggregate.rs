@@ -0,0 +1,126 @@
+use std::{collections::HashMap, rc::Rc};
+
+use sql_mini_parser::{
+    expr::{ColumnRef, Expression, FunctionArgs, FunctionCall},
+    value::Value,
+};
+
+use crate::{error::QueryExecutionError, eval::Env, functions::AggregateFunction, row::Row};
+
+use super::{collect, Buffer, Operator};
+
+/// Collapses the input into one row per group
+///
+/// Each output row holds the input columns of the group's first row followed
+/// by one [`aggregate_column`](crate::eval::aggregate_column) per aggregate
+/// call
+pub(crate) struct HashAggregate<'a> {
+    input: Box<dyn Operator + 'a>,
+    input_columns: Rc<[ColumnRef]>,
+    group_by: &'a [Expression],
+    aggregates: &'a [FunctionCall],
+    columns: Rc<[ColumnRef]>,
+    env: Env<'a>,
+    output: Option<Buffer>,
+}
+
+struct Group {
+    /// The first row of the group, used for columns outside of aggregates
+    first: Row,
+    accumulators: Vec<Box<dyn AggregateFunction>>,
+}
+
+impl<'a> HashAggregate<'a> {
+    pub(crate) fn new(
+        input: Box<dyn Operator + 'a>,
+        input_columns: Rc<[ColumnRef]>,
+        group_by: &'a [Expression],
+        aggregates: &'a [FunctionCall],
+        columns: Rc<[ColumnRef]>,
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            input,
+            input_columns,
+            group_by,
+            aggregates,
+            columns,
+            env,
+            output: None,
+        }
+    }
+
+    fn new_accumulators(&self) -> Result<Vec<Box<dyn AggregateFunction>>, QueryExecutionError> {
+        self.aggregates
+            .iter()
+            .map(|call| {
+                let arg_count = match &call.args {
+                    FunctionArgs::Wildcard => 0,
+                    FunctionArgs::List(args) => args.len(),
+                };
+                self.env.ctx.functions.new_aggregate(&call.name, arg_count)
+            })
+            .collect()
+    }
+
+    fn aggregate(&mut self) -> Result<Vec<Row>, QueryExecutionError> {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut group_index: HashMap<Vec<Value>, usize> = HashMap::new();
+
+        for row in collect(self.input.as_mut())? {
+            let key = self.env.evaluate_all(self.group_by, &row)?;
+
+            let idx = match group_index.get(&key) {
+                Some(idx) => *idx,
+                None => {
+                    groups.push(Group {
+                        first: row.clone(),
+                        accumulators: self.new_accumulators()?,
+                    });
+                    group_index.insert(key, groups.len() - 1);
+                    groups.len() - 1
+                }
+            };
+
+            let group = &mut groups[idx];
+            for (call, accumulator) in self.aggregates.iter().zip(group.accumulators.iter_mut()) {
+                let args = match &call.args {
+                    FunctionArgs::Wildcard => vec![],
+                    FunctionArgs::List(args) => self.env.evaluate_all(args, &row)?,
+                };
+                accumulator.step(&args)?;
+            }
+        }
+
+        // without a GROUP BY an empty input still aggregates to a single row
+        if groups.is_empty() && self.group_by.is_empty() {
+            groups.push(Group {
+                first: Row::new(
+                    self.input_columns.clone(),
+                    vec![Value::Null; self.input_columns.len()],
+                ),
+                accumulators: self.new_accumulators()?,
+            });
+        }
+
+        groups
+            .into_iter()
+            .map(|mut group| {
+                let mut values = group.first.into_values();
+                for accumulator in group.accumulators.iter_mut() {
+                    values.push(accumulator.finalize()?);
+                }
+                Ok(Row::new(self.columns.clone(), values))
+            })
+            .collect()
+    }
+}
+
+impl Operator for HashAggregate<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        if self.output.is_none() {
+            self.output = Some(self.aggregate()?.into_iter());
+        }
+        Ok(self.output.as_mut().and_then(Iterator::next))
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use sql_mini_parser::{expr::Expression, value::Value};
+
+use crate::{error::QueryExecutionError, eval::Env, row::Row};
+
+use super::Operator;
+
+/// Drops every row whose key was already seen, keeping the first
+///
+/// Keys are hashed, numbers that differ only in scale like `1.0` and `1`
+/// are the same key
+pub(crate) struct Distinct<'a> {
+    input: Box<dyn Operator + 'a>,
+    /// The expressions making up the key, every column without them
+    on: Option<&'a [Expression]>,
+    env: Env<'a>,
+    seen: HashSet<Vec<Value>>,
+}
+
+impl<'a> Distinct<'a> {
+    pub(crate) fn new(
+        input: Box<dyn Operator + 'a>,
+        on: Option<&'a [Expression]>,
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            input,
+            on,
+            env,
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl Operator for Distinct<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        while let Some(row) = self.input.next()? {
+            let key = match self.on {
+                Some(on) => self.env.evaluate_all(on, &row)?,
+                None => row.values().to_vec(),
+            };
+            if self.seen.insert(key) {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,41 @@
+use sql_mini_parser::expr::Expression;
+
+use crate::{
+    error::QueryExecutionError,
+    eval::{is_truthy, Env},
+    row::Row,
+};
+
+use super::Operator;
+
+/// The input rows for which the predicate is true
+pub(crate) struct Filter<'a> {
+    input: Box<dyn Operator + 'a>,
+    predicate: &'a Expression,
+    env: Env<'a>,
+}
+
+impl<'a> Filter<'a> {
+    pub(crate) fn new(
+        input: Box<dyn Operator + 'a>,
+        predicate: &'a Expression,
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            input,
+            predicate,
+            env,
+        }
+    }
+}
+
+impl Operator for Filter<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        while let Some(row) = self.input.next()? {
+            if is_truthy(&self.env.evaluate(self.predicate, &row)?) {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,41 @@
+use std::{rc::Rc, time::Instant};
+
+use crate::{error::QueryExecutionError, explain::Counters, row::Row};
+
+use super::Operator;
+
+/// The rows of another operator, counted along with the time spent
+/// producing them
+pub(crate) struct Instrumented<'a> {
+    input: Box<dyn Operator + 'a>,
+    counters: Rc<Counters>,
+    started: bool,
+}
+
+impl<'a> Instrumented<'a> {
+    pub(crate) fn new(input: Box<dyn Operator + 'a>, counters: Rc<Counters>) -> Self {
+        Self {
+            input,
+            counters,
+            started: false,
+        }
+    }
+}
+
+impl Operator for Instrumented<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        if !self.started {
+            self.started = true;
+            self.counters.loops.set(self.counters.loops.get() + 1);
+        }
+        let start = Instant::now();
+        let row = self.input.next();
+        self.counters
+            .elapsed
+            .set(self.counters.elapsed.get() + start.elapsed());
+        if let Ok(Some(_)) = row {
+            self.counters.rows.set(self.counters.rows.get() + 1);
+        }
+        row
+    }
+}
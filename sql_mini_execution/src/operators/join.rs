@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use sql_mini_parser::{
+    commands::JoinKind,
+    expr::{ColumnRef, Expression},
+    value::Value,
+};
+
+use crate::{
+    error::QueryExecutionError,
+    eval::{is_truthy, Env},
+    row::Row,
+};
+
+use super::{collect, Operator};
+
+/// Pairs every left row with every right row, keeping the pairs matching
+/// the join condition
+///
+/// The right side is read into memory on the first pull, the left side is
+/// streamed
+pub(crate) struct NestedLoopJoin<'a> {
+    left: Box<dyn Operator + 'a>,
+    right: Box<dyn Operator + 'a>,
+    kind: JoinKind,
+    on: Option<&'a Expression>,
+    columns: Rc<[ColumnRef]>,
+    env: Env<'a>,
+    right_rows: Option<Vec<Row>>,
+    /// The left row being joined, the position of the next right row to
+    /// pair it with, and whether it matched any right row yet
+    current: Option<(Row, usize, bool)>,
+}
+
+impl<'a> NestedLoopJoin<'a> {
+    pub(crate) fn new(
+        left: Box<dyn Operator + 'a>,
+        right: Box<dyn Operator + 'a>,
+        kind: JoinKind,
+        on: Option<&'a Expression>,
+        columns: Rc<[ColumnRef]>,
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            kind,
+            on,
+            columns,
+            env,
+            right_rows: None,
+            current: None,
+        }
+    }
+
+    fn concat(&self, left: &Row, right: &[Value]) -> Row {
+        let mut values = left.values().to_vec();
+        values.extend_from_slice(right);
+        Row::new(self.columns.clone(), values)
+    }
+}
+
+impl Operator for NestedLoopJoin<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        if self.right_rows.is_none() {
+            self.right_rows = Some(collect(self.right.as_mut())?);
+        }
+
+        loop {
+            let Some((left, position, matched)) = self.current.take() else {
+                match self.left.next()? {
+                    Some(row) => self.current = Some((row, 0, false)),
+                    None => return Ok(None),
+                }
+                continue;
+            };
+
+            let right_rows = self.right_rows.as_deref().unwrap_or_default();
+            for (i, right) in right_rows.iter().enumerate().skip(position) {
+                let row = self.concat(&left, right.values());
+                let keep = match self.on {
+                    Some(on) => is_truthy(&self.env.evaluate(on, &row)?),
+                    None => true,
+                };
+                if keep {
+                    self.current = Some((left, i + 1, true));
+                    return Ok(Some(row));
+                }
+            }
+
+            // a left row without a match is kept once, padded with NULLs
+            if self.kind == JoinKind::Left && !matched {
+                let right_width = self.columns.len() - left.values().len();
+                return Ok(Some(self.concat(&left, &vec![Value::Null; right_width])));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::value::Value;
+
+    use crate::tests::{execution, run_all};
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    const TABLES: &[&str] = &[
+        "create table emp (name string, dept string);",
+        "insert into emp values 'ann', 'eng';",
+        "insert into emp values 'bob', 'ops';",
+        "insert into emp values 'cid', NULL;",
+        "create table depts (id string, title string);",
+        "insert into depts values 'eng', 'Engineering';",
+        "insert into depts values 'ops', 'Operations';",
+        "insert into depts values 'hr', 'People';",
+    ];
+
+    #[test]
+    fn test_inner_and_left_join() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(
+            &mut exec,
+            &["select e.name, d.title from emp e join depts d on e.dept = d.id order by e.name;"],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![string("ann"), string("Engineering")],
+                vec![string("bob"), string("Operations")],
+            ]
+        );
+
+        let rows = run_all(
+            &mut exec,
+            &[
+                "select name, title from emp left outer join depts on dept = id \
+               where name <> 'bob' order by name;",
+            ],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![string("ann"), string("Engineering")],
+                vec![string("cid"), Value::Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cross_join() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(
+            &mut exec,
+            &["select count(*) from emp, depts where depts.id <> 'hr';"],
+        );
+        assert_eq!(rows, vec![vec![Value::Number(6.into())]]);
+
+        let rows = run_all(
+            &mut exec,
+            &["select a.name, b.name from emp a cross join emp b \
+               where a.name < b.name order by 1, 2;"],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![string("ann"), string("bob")],
+                vec![string("ann"), string("cid")],
+                vec![string("bob"), string("cid")],
+            ]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::rc::Rc;
+
+use bigdecimal::ToPrimitive;
+use sql_mini_parser::{expr::Expression, value::Value};
+
+use crate::{error::QueryExecutionError, eval::Env, row::Row};
+
+use super::Operator;
+
+/// Evaluate a LIMIT or OFFSET, which can not reference any columns of the query
+pub(crate) fn row_count(
+    expr: &Expression,
+    clause: &str,
+    env: Env,
+) -> Result<usize, QueryExecutionError> {
+    let empty = Row::new(Rc::from([]), vec![]);
+    match env.evaluate(expr, &empty)? {
+        Value::Number(n) if n.is_integer() => n.to_usize().ok_or(
+            QueryExecutionError::InvalidOperand(clause.into(), Value::Number(n)),
+        ),
+        v => Err(QueryExecutionError::InvalidOperand(clause.into(), v)),
+    }
+}
+
+/// Skips the first OFFSET rows and stops after LIMIT rows
+pub(crate) struct Limit<'a> {
+    input: Box<dyn Operator + 'a>,
+    limit: Option<&'a Expression>,
+    offset: Option<&'a Expression>,
+    env: Env<'a>,
+    /// The rows still to produce, evaluated on the first pull
+    remaining: Option<usize>,
+}
+
+impl<'a> Limit<'a> {
+    pub(crate) fn new(
+        input: Box<dyn Operator + 'a>,
+        limit: Option<&'a Expression>,
+        offset: Option<&'a Expression>,
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            input,
+            limit,
+            offset,
+            env,
+            remaining: None,
+        }
+    }
+}
+
+impl Operator for Limit<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining,
+            None => {
+                let offset = match self.offset {
+                    Some(offset) => row_count(offset, "OFFSET", self.env)?,
+                    None => 0,
+                };
+                for _ in 0..offset {
+                    if self.input.next()?.is_none() {
+                        break;
+                    }
+                }
+                match self.limit {
+                    Some(limit) => row_count(limit, "LIMIT", self.env)?,
+                    None => usize::MAX,
+                }
+            }
+        };
+
+        if remaining == 0 {
+            self.remaining = Some(0);
+            return Ok(None);
+        }
+        self.remaining = Some(remaining - 1);
+        self.input.next()
+    }
+}
@@ -0,0 +1,182 @@
+//! Physical operators, which run a [`LogicalPlan`] by pulling rows one at a
+//! time from the root of a tree of operators
+//!
+//! Operators that need every input row before producing any, like sorting,
+//! read their input on the first pull
+
+use std::rc::Rc;
+
+use crate::{
+    error::QueryExecutionError, eval::Env, explain::ProfileNode, plan::LogicalPlan,
+    result::ResultSet, row::Row,
+};
+
+mod aggregate;
+mod distinct;
+mod filter;
+mod instrumented;
+mod join;
+mod limit;
+mod project;
+mod scan;
+mod set_operation;
+mod sort;
+mod window;
+
+pub(crate) use limit::row_count;
+pub(crate) use sort::compare_keys;
+
+/// A node of the physical plan
+pub(crate) trait Operator {
+    /// The next row, `None` once every row was produced
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError>;
+}
+
+/// Pull every remaining row out of `operator`
+pub(crate) fn collect(operator: &mut dyn Operator) -> Result<Vec<Row>, QueryExecutionError> {
+    let mut rows = Vec::new();
+    while let Some(row) = operator.next()? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Build the operators running `plan`
+///
+/// With a `profile` of the plan every operator counts the rows it produces
+/// and the time it takes in the matching node
+pub(crate) fn build<'a>(
+    plan: &'a LogicalPlan,
+    env: Env<'a>,
+    profile: Option<&ProfileNode>,
+) -> Result<Box<dyn Operator + 'a>, QueryExecutionError> {
+    let build = |i: usize, input: &'a LogicalPlan| {
+        build(
+            input,
+            env,
+            profile.and_then(|profile| profile.inputs.get(i)),
+        )
+    };
+    let operator: Box<dyn Operator + 'a> = match plan {
+        LogicalPlan::Scan { table, columns, .. } => {
+            Box::new(env.ctx.table(table)?.into_iter().projected(columns.clone()))
+        }
+        LogicalPlan::IndexScan {
+            table,
+            columns,
+            column,
+            value,
+            ..
+        } => {
+            // a literal or parameter, which reads no columns
+            let value = env.evaluate(value, &Row::new(Rc::from([]), vec![]))?;
+            let rows = env
+                .ctx
+                .table(table)?
+                .lookup(column, &value)
+                .ok_or_else(|| QueryExecutionError::ColumnDoesNotExist(column.clone()))?;
+            Box::new(rows.projected(columns.clone()))
+        }
+        LogicalPlan::Values {
+            result, columns, ..
+        } => Box::new(scan::ValuesScan::new(result.clone(), columns.clone())),
+        LogicalPlan::Alias { input, columns, .. } => {
+            Box::new(scan::Rename::new(build(0, input)?, columns.clone()))
+        }
+        LogicalPlan::Filter { input, predicate } => {
+            Box::new(filter::Filter::new(build(0, input)?, predicate, env))
+        }
+        LogicalPlan::Join {
+            left,
+            right,
+            kind,
+            on,
+            columns,
+        } => Box::new(join::NestedLoopJoin::new(
+            build(0, left)?,
+            build(1, right)?,
+            *kind,
+            on.as_ref(),
+            columns.clone(),
+            env,
+        )),
+        LogicalPlan::Aggregate {
+            input,
+            group_by,
+            aggregates,
+            columns,
+        } => Box::new(aggregate::HashAggregate::new(
+            build(0, input)?,
+            input.columns(),
+            group_by,
+            aggregates,
+            columns.clone(),
+            env,
+        )),
+        LogicalPlan::Window { input, windows, .. } => {
+            Box::new(window::Windows::new(build(0, input)?, windows, env))
+        }
+        LogicalPlan::Project {
+            input,
+            exprs,
+            columns,
+        } => Box::new(project::Project::new(
+            build(0, input)?,
+            exprs,
+            columns.clone(),
+            env,
+        )),
+        LogicalPlan::Sort { input, order_by } => {
+            Box::new(sort::Sort::new(build(0, input)?, order_by, env))
+        }
+        LogicalPlan::Distinct { input, on } => Box::new(distinct::Distinct::new(
+            build(0, input)?,
+            on.as_deref(),
+            env,
+        )),
+        LogicalPlan::Limit {
+            input,
+            limit,
+            offset,
+        } => Box::new(limit::Limit::new(
+            build(0, input)?,
+            limit.as_ref(),
+            offset.as_ref(),
+            env,
+        )),
+        LogicalPlan::SetOperation { op, left, right } => {
+            Box::new(set_operation::SetOperation::new(
+                *op,
+                build(0, left)?,
+                build(1, right)?,
+                plan.columns(),
+            ))
+        }
+    };
+    Ok(match profile {
+        Some(profile) => Box::new(instrumented::Instrumented::new(
+            operator,
+            profile.counters.clone(),
+        )),
+        None => operator,
+    })
+}
+
+/// Run `plan` to completion
+pub(crate) fn execute(plan: &LogicalPlan, env: Env) -> Result<ResultSet, QueryExecutionError> {
+    let rows = collect(build(plan, env, None)?.as_mut())?;
+    Ok(ResultSet::new(plan.names(), rows))
+}
+
+/// Run `plan` to completion, measuring every operator into `profile`
+pub(crate) fn execute_profiled(
+    plan: &LogicalPlan,
+    env: Env,
+    profile: &ProfileNode,
+) -> Result<ResultSet, QueryExecutionError> {
+    let rows = collect(build(plan, env, Some(profile))?.as_mut())?;
+    Ok(ResultSet::new(plan.names(), rows))
+}
+
+/// Rows computed all at once, handed out one at a time
+type Buffer = std::vec::IntoIter<Row>;
@@ -0,0 +1,41 @@
+use std::rc::Rc;
+
+use sql_mini_parser::expr::{ColumnRef, Expression};
+
+use crate::{error::QueryExecutionError, eval::Env, row::Row};
+
+use super::Operator;
+
+/// One output row per input row, with a column per expression
+pub(crate) struct Project<'a> {
+    input: Box<dyn Operator + 'a>,
+    exprs: &'a [Expression],
+    columns: Rc<[ColumnRef]>,
+    env: Env<'a>,
+}
+
+impl<'a> Project<'a> {
+    pub(crate) fn new(
+        input: Box<dyn Operator + 'a>,
+        exprs: &'a [Expression],
+        columns: Rc<[ColumnRef]>,
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            input,
+            exprs,
+            columns,
+            env,
+        }
+    }
+}
+
+impl Operator for Project<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        let Some(row) = self.input.next()? else {
+            return Ok(None);
+        };
+        let values = self.env.evaluate_all(self.exprs, &row)?;
+        Ok(Some(Row::new(self.columns.clone(), values)))
+    }
+}
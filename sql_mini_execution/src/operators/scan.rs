@@ -0,0 +1,73 @@
+use std::rc::Rc;
+
+use sql_mini_parser::expr::ColumnRef;
+
+use crate::{
+    error::QueryExecutionError,
+    result::ResultSet,
+    row::Row,
+    table::{IndexIter, TableIter},
+};
+
+use super::Operator;
+
+// reading a stored table is the leaf of most plans
+impl Operator for TableIter<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        Ok(Iterator::next(self))
+    }
+}
+
+impl Operator for IndexIter<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        Ok(Iterator::next(self))
+    }
+}
+
+/// Rows of an already computed result, given new columns
+pub(crate) struct ValuesScan {
+    result: Rc<ResultSet>,
+    columns: Rc<[ColumnRef]>,
+    position: usize,
+}
+
+impl ValuesScan {
+    pub(crate) fn new(result: Rc<ResultSet>, columns: Rc<[ColumnRef]>) -> Self {
+        Self {
+            result,
+            columns,
+            position: 0,
+        }
+    }
+}
+
+impl Operator for ValuesScan {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        let Some(row) = self.result.rows().get(self.position) else {
+            return Ok(None);
+        };
+        self.position += 1;
+        Ok(Some(Row::new(self.columns.clone(), row.values().to_vec())))
+    }
+}
+
+/// The input rows, given new columns
+pub(crate) struct Rename<'a> {
+    input: Box<dyn Operator + 'a>,
+    columns: Rc<[ColumnRef]>,
+}
+
+impl<'a> Rename<'a> {
+    pub(crate) fn new(input: Box<dyn Operator + 'a>, columns: Rc<[ColumnRef]>) -> Self {
+        Self { input, columns }
+    }
+}
+
+impl Operator for Rename<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        Ok(self
+            .input
+            .next()?
+            .map(|row| Row::new(self.columns.clone(), row.into_values())))
+    }
+}
@@ -0,0 +1,54 @@
+use std::rc::Rc;
+
+use sql_mini_parser::{commands::SetOperator, expr::ColumnRef};
+
+use crate::{compound::combine, error::QueryExecutionError, row::Row};
+
+use super::{collect, Buffer, Operator};
+
+/// The rows of both inputs combined by a set operator
+pub(crate) struct SetOperation<'a> {
+    op: SetOperator,
+    left: Box<dyn Operator + 'a>,
+    right: Box<dyn Operator + 'a>,
+    columns: Rc<[ColumnRef]>,
+    output: Option<Buffer>,
+}
+
+impl<'a> SetOperation<'a> {
+    pub(crate) fn new(
+        op: SetOperator,
+        left: Box<dyn Operator + 'a>,
+        right: Box<dyn Operator + 'a>,
+        columns: Rc<[ColumnRef]>,
+    ) -> Self {
+        Self {
+            op,
+            left,
+            right,
+            columns,
+            output: None,
+        }
+    }
+
+    fn combine(&mut self) -> Result<Vec<Row>, QueryExecutionError> {
+        let values = |input: &mut dyn Operator| -> Result<Vec<_>, QueryExecutionError> {
+            Ok(collect(input)?.into_iter().map(Row::into_values).collect())
+        };
+        let left = values(self.left.as_mut())?;
+        let right = values(self.right.as_mut())?;
+        Ok(combine(self.op, left, right)
+            .into_iter()
+            .map(|values| Row::new(self.columns.clone(), values))
+            .collect())
+    }
+}
+
+impl Operator for SetOperation<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        if self.output.is_none() {
+            self.output = Some(self.combine()?.into_iter());
+        }
+        Ok(self.output.as_mut().and_then(Iterator::next))
+    }
+}
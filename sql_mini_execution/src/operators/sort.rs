@@ -0,0 +1,76 @@
+use std::cmp::Ordering;
+
+use sql_mini_parser::{commands::OrderByItem, value::Value};
+
+use crate::{
+    error::QueryExecutionError,
+    eval::{compare_values, Env},
+    row::Row,
+};
+
+use super::{collect, Buffer, Operator};
+
+/// Compare the sort keys of two rows, one value per ORDER BY item
+pub(crate) fn compare_keys(left: &[Value], right: &[Value], order_by: &[OrderByItem]) -> Ordering {
+    left.iter()
+        .zip(right)
+        .zip(order_by)
+        .map(|((l, r), item)| {
+            let ordering = compare_values(l, r);
+            if item.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// The input rows in ORDER BY order, rows sorting equal keep their input
+/// order
+pub(crate) struct Sort<'a> {
+    input: Box<dyn Operator + 'a>,
+    order_by: &'a [OrderByItem],
+    env: Env<'a>,
+    output: Option<Buffer>,
+}
+
+impl<'a> Sort<'a> {
+    pub(crate) fn new(
+        input: Box<dyn Operator + 'a>,
+        order_by: &'a [OrderByItem],
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            input,
+            order_by,
+            env,
+            output: None,
+        }
+    }
+
+    fn sort(&mut self) -> Result<Vec<Row>, QueryExecutionError> {
+        let mut keyed = collect(self.input.as_mut())?
+            .into_iter()
+            .map(|row| {
+                let key = self
+                    .env
+                    .evaluate_all(self.order_by.iter().map(|item| &item.expr), &row)?;
+                Ok((key, row))
+            })
+            .collect::<Result<Vec<_>, QueryExecutionError>>()?;
+
+        keyed.sort_by(|(left, _), (right, _)| compare_keys(left, right, self.order_by));
+        Ok(keyed.into_iter().map(|(_, row)| row).collect())
+    }
+}
+
+impl Operator for Sort<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        if self.output.is_none() {
+            self.output = Some(self.sort()?.into_iter());
+        }
+        Ok(self.output.as_mut().and_then(Iterator::next))
+    }
+}
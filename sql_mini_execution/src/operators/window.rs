@@ -0,0 +1,38 @@
+use sql_mini_parser::window::WindowFunction;
+
+use crate::{error::QueryExecutionError, eval::Env, row::Row, window::apply_windows};
+
+use super::{collect, Buffer, Operator};
+
+/// The input rows, each followed by the result of every window function
+pub(crate) struct Windows<'a> {
+    input: Box<dyn Operator + 'a>,
+    windows: &'a [WindowFunction],
+    env: Env<'a>,
+    output: Option<Buffer>,
+}
+
+impl<'a> Windows<'a> {
+    pub(crate) fn new(
+        input: Box<dyn Operator + 'a>,
+        windows: &'a [WindowFunction],
+        env: Env<'a>,
+    ) -> Self {
+        Self {
+            input,
+            windows,
+            env,
+            output: None,
+        }
+    }
+}
+
+impl Operator for Windows<'_> {
+    fn next(&mut self) -> Result<Option<Row>, QueryExecutionError> {
+        if self.output.is_none() {
+            let rows = collect(self.input.as_mut())?;
+            self.output = Some(apply_windows(rows, self.windows, self.env)?.into_iter());
+        }
+        Ok(self.output.as_mut().and_then(Iterator::next))
+    }
+}
@@ -0,0 +1,89 @@
+use std::rc::Rc;
+
+use sql_mini_parser::expr::Expression;
+
+use crate::{
+    eval::{evaluate, Context, Scope},
+    plan::LogicalPlan,
+    row::Row,
+};
+
+use super::{transform_up, Rule};
+
+/// Replaces expressions that do not depend on the row by their value, like
+/// `1 + 2` by `3`
+///
+/// Function calls are kept, a user function may not return the same value
+/// every time. So are expressions failing to evaluate, the error is only
+/// raised if the query reaches them
+pub(crate) struct ConstantFolding;
+
+impl Rule for ConstantFolding {
+    fn apply(&self, plan: LogicalPlan, ctx: &Context) -> LogicalPlan {
+        transform_up(plan, &mut |mut plan| {
+            for expr in plan.expressions_mut() {
+                fold(expr, ctx);
+            }
+            plan
+        })
+    }
+}
+
+fn fold(expr: &mut Expression, ctx: &Context) {
+    match expr {
+        // the nodes above refer to these by how they are written
+        Expression::Function(call) if ctx.functions.is_aggregate(&call.name) => return,
+        Expression::Window(_) => return,
+        _ => {}
+    }
+    for child in expr.children_mut() {
+        fold(child, ctx);
+    }
+
+    let foldable = matches!(
+        expr,
+        Expression::Unary { .. }
+            | Expression::Binary { .. }
+            | Expression::IsNull { .. }
+            | Expression::Case { .. }
+            | Expression::Between { .. }
+            | Expression::InList { .. }
+            | Expression::Pattern { .. }
+    ) && expr
+        .children()
+        .into_iter()
+        .all(|child| matches!(child, Expression::Literal(_)));
+    if foldable {
+        let empty = Row::new(Rc::from([]), vec![]);
+        if let Ok(value) = evaluate(expr, &Scope::new(&empty, None, None), ctx) {
+            *expr = Expression::Literal(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        optimizer::tests::{optimized, TABLES},
+        tests::execution,
+    };
+
+    use super::ConstantFolding;
+
+    #[test]
+    fn test_constant_folding() {
+        let exec = execution(TABLES);
+        assert_eq!(
+            optimized(
+                &exec,
+                &ConstantFolding,
+                "select salary * (2 + 3), upper('a' || 'b'), sum(1 + 1) from emp \
+                 where salary > 10 * 10 and name like 'a' || '%' and 1 / 0 = 1"
+            ),
+            "Project: salary * 5 AS salary * (2 + 3), upper('ab') AS upper('a' || 'b'), sum(1 + 1)\n\
+             \x20 Aggregate: sum(1 + 1)\n\
+             \x20   Filter: salary > 100 AND name LIKE 'a%' AND 1 / 0 = 1\n\
+             \x20     Scan: emp (name, dept, salary)"
+        );
+    }
+}
@@ -0,0 +1,400 @@
+use bigdecimal::ToPrimitive;
+use sql_mini_parser::{
+    commands::JoinKind,
+    expr::{BinaryOperator, ColumnRef, Expression, UnaryOperator},
+    value::Value,
+};
+
+use crate::{
+    eval::{compare_values, Context},
+    plan::LogicalPlan,
+    stats::{ColumnStats, TableStats},
+};
+
+/// Fraction of the rows a condition on a column without statistics keeps
+const EQUALITY_SELECTIVITY: f64 = 0.1;
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+
+/// The cost of reading a row through an index relative to reading it while
+/// scanning the table, every row found costs a lookup
+pub(crate) const INDEX_ROW_COST: f64 = 4.0;
+
+/// Estimates how many rows plans produce, from the statistics `ANALYZE`
+/// stored for the tables they read
+///
+/// Tables that were never analyzed count their rows, and conditions on
+/// their columns keep a fixed fraction of the rows
+pub(crate) struct Estimator<'c, 'a> {
+    ctx: &'c Context<'a>,
+}
+
+impl<'c, 'a> Estimator<'c, 'a> {
+    pub(crate) fn new(ctx: &'c Context<'a>) -> Self {
+        Self { ctx }
+    }
+
+    fn table_stats(&self, table: &str) -> Option<&'a TableStats> {
+        self.ctx.tables.get(table)?.stats()
+    }
+
+    /// Whether the statistics of every table `plan` reads are known
+    pub(crate) fn analyzed(&self, plan: &LogicalPlan) -> bool {
+        match plan {
+            LogicalPlan::Scan { table, .. } | LogicalPlan::IndexScan { table, .. } => {
+                self.table_stats(table).is_some()
+            }
+            plan => plan.inputs().into_iter().all(|input| self.analyzed(input)),
+        }
+    }
+
+    /// The estimated number of rows `plan` produces
+    pub(crate) fn rows(&self, plan: &LogicalPlan) -> f64 {
+        match plan {
+            LogicalPlan::Scan { table, .. } => self.table_rows(table),
+            LogicalPlan::IndexScan {
+                table,
+                column,
+                value,
+                ..
+            } => self.table_rows(table) * self.index_selectivity(table, column, value),
+            LogicalPlan::Values { result, .. } => result.len() as f64,
+            LogicalPlan::Filter { input, predicate } => {
+                self.rows(input) * self.selectivity(predicate, input)
+            }
+            LogicalPlan::Join {
+                left,
+                right,
+                kind,
+                on,
+                ..
+            } => {
+                let left_rows = self.rows(left);
+                let pairs = left_rows * self.rows(right);
+                let rows = match on {
+                    Some(on) => pairs * self.selectivity(on, plan),
+                    None => pairs,
+                };
+                match kind {
+                    // every left row is kept
+                    JoinKind::Left => rows.max(left_rows),
+                    _ => rows,
+                }
+            }
+            LogicalPlan::Aggregate {
+                input, group_by, ..
+            } => {
+                let rows = self.rows(input);
+                if group_by.is_empty() {
+                    return 1.0;
+                }
+                let groups = group_by.iter().try_fold(1.0, |groups, expr| match expr {
+                    Expression::Column(column) => self
+                        .column_stats(input, column)
+                        .map(|(_, stats)| groups * stats.distinct.max(1) as f64),
+                    _ => None,
+                });
+                groups
+                    .unwrap_or(rows * EQUALITY_SELECTIVITY)
+                    .min(rows)
+                    .max(1.0)
+            }
+            LogicalPlan::Limit { input, limit, .. } => {
+                let rows = self.rows(input);
+                match limit {
+                    Some(Expression::Literal(Value::Number(n))) => {
+                        n.to_f64().map_or(rows, |n| rows.min(n.max(0.0)))
+                    }
+                    _ => rows,
+                }
+            }
+            LogicalPlan::SetOperation { left, right, .. } => self.rows(left) + self.rows(right),
+            LogicalPlan::Alias { input, .. }
+            | LogicalPlan::Window { input, .. }
+            | LogicalPlan::Project { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input, .. } => self.rows(input),
+        }
+    }
+
+    fn table_rows(&self, table: &str) -> f64 {
+        match self.table_stats(table) {
+            Some(stats) => stats.row_count as f64,
+            None => self.ctx.tables.get(table).map_or(0.0, |t| t.len() as f64),
+        }
+    }
+
+    /// The fraction of the rows of `table` whose `column` holds `value`
+    pub(crate) fn index_selectivity(&self, table: &str, column: &str, value: &Expression) -> f64 {
+        let stats = self
+            .table_stats(table)
+            .and_then(|stats| Some((stats, stats.columns.get(column)?)));
+        match value {
+            Expression::Literal(value) => equality(stats, value),
+            // a parameter is only known when the query runs, assume it is
+            // one of the column's values
+            _ => stats.map_or(EQUALITY_SELECTIVITY, |stats @ (_, column)| {
+                non_null(stats) / column.distinct.max(1) as f64
+            }),
+        }
+    }
+
+    /// Whether finding the rows of `table` whose `column` holds `value`
+    /// through an index is cheaper than reading every row
+    ///
+    /// Without statistics the index is assumed to be selective enough
+    pub(crate) fn index_cheaper(&self, table: &str, column: &str, value: &Expression) -> bool {
+        self.table_stats(table).is_none()
+            || self.index_selectivity(table, column, value) * INDEX_ROW_COST < 1.0
+    }
+
+    /// The statistics of the table column `column` refers to in the rows of
+    /// `plan`, as long as they are the rows of the table
+    fn column_stats(
+        &self,
+        plan: &LogicalPlan,
+        column: &ColumnRef,
+    ) -> Option<(&'a TableStats, &'a ColumnStats)> {
+        match plan {
+            LogicalPlan::Scan { table, alias, .. }
+            | LogicalPlan::IndexScan { table, alias, .. } => {
+                if column.table.as_ref().is_some_and(|table| table != alias) {
+                    return None;
+                }
+                let stats = self.table_stats(table)?;
+                Some((stats, stats.columns.get(&column.name)?))
+            }
+            LogicalPlan::Filter { .. }
+            | LogicalPlan::Join { .. }
+            | LogicalPlan::Sort { .. }
+            | LogicalPlan::Distinct { .. }
+            | LogicalPlan::Limit { .. } => plan
+                .inputs()
+                .into_iter()
+                .find_map(|input| self.column_stats(input, column)),
+            _ => None,
+        }
+    }
+
+    /// The estimated fraction of the rows of `plan` for which `predicate` is
+    /// true
+    pub(crate) fn selectivity(&self, predicate: &Expression, plan: &LogicalPlan) -> f64 {
+        let stats = |expr: &Expression| match expr {
+            Expression::Column(column) => self.column_stats(plan, column),
+            _ => None,
+        };
+
+        match predicate {
+            Expression::Literal(Value::Bool(true)) => 1.0,
+            Expression::Literal(_) => 0.0,
+            Expression::Binary {
+                left,
+                op: BinaryOperator::And,
+                right,
+            } => self.selectivity(left, plan) * self.selectivity(right, plan),
+            Expression::Binary {
+                left,
+                op: BinaryOperator::Or,
+                right,
+            } => {
+                let (l, r) = (self.selectivity(left, plan), self.selectivity(right, plan));
+                l + r - l * r
+            }
+            Expression::Unary {
+                op: UnaryOperator::Not,
+                expr,
+            } => 1.0 - self.selectivity(expr, plan),
+            Expression::Binary { left, op, right } => {
+                match (left.as_ref(), right.as_ref()) {
+                    (Expression::Column(_), Expression::Column(_)) => match op {
+                        BinaryOperator::Eq => {
+                            // every value of the side with fewer distinct
+                            // values matches one on the other side
+                            let distinct = |expr| stats(expr).map(|(_, c)| c.distinct.max(1));
+                            match distinct(left).max(distinct(right)) {
+                                Some(distinct) => 1.0 / distinct as f64,
+                                None => EQUALITY_SELECTIVITY,
+                            }
+                        }
+                        BinaryOperator::NotEq => 1.0 - EQUALITY_SELECTIVITY,
+                        _ => RANGE_SELECTIVITY,
+                    },
+                    (column @ Expression::Column(_), Expression::Literal(value)) => {
+                        comparison(stats(column), *op, value)
+                    }
+                    (Expression::Literal(value), column @ Expression::Column(_)) => {
+                        comparison(stats(column), flipped(*op), value)
+                    }
+                    _ => DEFAULT_SELECTIVITY,
+                }
+            }
+            Expression::IsNull { expr, negated } => {
+                let nulls = match stats(expr) {
+                    Some((table, column)) if table.row_count > 0 => {
+                        column.nulls as f64 / table.row_count as f64
+                    }
+                    _ => EQUALITY_SELECTIVITY,
+                };
+                if *negated {
+                    1.0 - nulls
+                } else {
+                    nulls
+                }
+            }
+            Expression::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let fraction = match (low.as_ref(), high.as_ref()) {
+                    (Expression::Literal(low), Expression::Literal(high)) => {
+                        let stats = stats(expr);
+                        (comparison(stats, BinaryOperator::LtEq, high)
+                            - comparison(stats, BinaryOperator::Lt, low))
+                        .max(0.0)
+                    }
+                    _ => RANGE_SELECTIVITY,
+                };
+                if *negated {
+                    1.0 - fraction
+                } else {
+                    fraction
+                }
+            }
+            Expression::InList {
+                expr,
+                negated,
+                list,
+            } => {
+                let stats = stats(expr);
+                let fraction = list
+                    .iter()
+                    .map(|item| match item {
+                        Expression::Literal(value) => equality(stats, value),
+                        _ => EQUALITY_SELECTIVITY,
+                    })
+                    .sum::<f64>()
+                    .min(1.0);
+                if *negated {
+                    1.0 - fraction
+                } else {
+                    fraction
+                }
+            }
+            _ => DEFAULT_SELECTIVITY,
+        }
+    }
+}
+
+/// The comparison with the sides swapped, `a < b` becoming `b > a`
+fn flipped(op: BinaryOperator) -> BinaryOperator {
+    match op {
+        BinaryOperator::Lt => BinaryOperator::Gt,
+        BinaryOperator::LtEq => BinaryOperator::GtEq,
+        BinaryOperator::Gt => BinaryOperator::Lt,
+        BinaryOperator::GtEq => BinaryOperator::LtEq,
+        op => op,
+    }
+}
+
+/// The fraction of the rows whose column holds values other than NULL
+fn non_null((table, column): (&TableStats, &ColumnStats)) -> f64 {
+    match table.row_count {
+        0 => 0.0,
+        rows => 1.0 - column.nulls as f64 / rows as f64,
+    }
+}
+
+/// The fraction of the rows whose column equals `value`
+fn equality(stats: Option<(&TableStats, &ColumnStats)>, value: &Value) -> f64 {
+    let Some(stats @ (_, column)) = stats else {
+        return EQUALITY_SELECTIVITY;
+    };
+    let (Some(min), Some(max)) = (&column.min, &column.max) else {
+        return 0.0;
+    };
+    let outside = compare_values(value, min).is_lt() || compare_values(value, max).is_gt();
+    if value.is_null() || outside {
+        return 0.0;
+    }
+    non_null(stats) / column.distinct.max(1) as f64
+}
+
+/// The fraction of the rows whose column compares to `value` with `op`
+fn comparison(
+    stats: Option<(&TableStats, &ColumnStats)>,
+    op: BinaryOperator,
+    value: &Value,
+) -> f64 {
+    let equal = equality(stats, value);
+    let below = stats.and_then(|stats @ (_, column)| {
+        Some((
+            non_null(stats),
+            column.fraction_below(value)? * non_null(stats),
+        ))
+    });
+    match (op, below) {
+        (BinaryOperator::Eq, _) => equal,
+        (BinaryOperator::NotEq, _) => 1.0 - equal,
+        (BinaryOperator::Lt, Some((_, below))) => below,
+        (BinaryOperator::LtEq, Some((_, below))) => below + equal,
+        (BinaryOperator::Gt, Some((all, below))) => all - below - equal,
+        (BinaryOperator::GtEq, Some((all, below))) => all - below,
+        _ => RANGE_SELECTIVITY,
+    }
+    .clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{expr::Expression, parse::Parse};
+
+    use crate::{
+        eval::Context,
+        optimizer::tests::TABLES,
+        plan::LogicalPlan,
+        tests::{execution, run_all},
+    };
+
+    use super::Estimator;
+
+    #[test]
+    fn test_selectivity() {
+        let mut exec = execution(TABLES);
+        for i in 0..100 {
+            run_all(
+                &mut exec,
+                &[&format!(
+                    "insert into emp values 'e{i}', 'd{}', {i};",
+                    i % 4
+                )],
+            );
+        }
+        run_all(&mut exec, &["analyze emp;"]);
+
+        let ctx = Context::new(&exec.functions, &exec.tables);
+        let estimator = Estimator::new(&ctx);
+        let scan = LogicalPlan::Scan {
+            table: "emp".into(),
+            alias: "emp".into(),
+            columns: exec.tables["emp"].columns().refs(Some("emp")),
+        };
+        let estimate = |sql: &str| {
+            let (_, expr) = Expression::parse_from_raw(sql).unwrap();
+            estimator.rows(&LogicalPlan::Filter {
+                input: Box::new(scan.clone()),
+                predicate: expr,
+            })
+        };
+
+        // 103 rows: 100 numbered ones and the three in setup
+        assert_eq!(estimator.rows(&scan), 103.0);
+        // six departments, eng and ops from setup and four numbered ones
+        assert!((15.0..=20.0).contains(&estimate("dept = 'd1'")));
+        assert_eq!(estimate("dept = 'zzz'"), 0.0);
+        assert!((20.0..=35.0).contains(&estimate("salary < 25")));
+        assert!((3.0..=6.0).contains(&estimate("salary < 25 and dept = 'd1'")));
+        assert!((45.0..=60.0).contains(&estimate("salary between 25 and 75")));
+    }
+}
@@ -0,0 +1,196 @@
+use sql_mini_parser::{
+    commands::SqlTypeInfo,
+    expr::{BinaryOperator, Expression, Parameter},
+    value::Value,
+};
+
+use crate::{eval::Context, plan::LogicalPlan, table::Table};
+
+use super::{conjoin, conjuncts, cost::Estimator, transform_up, Rule};
+
+/// Reads a table through an index instead of scanning every row when a
+/// filter right above the scan compares an indexed column to a constant or
+/// to a parameter
+///
+/// With statistics the index is only used when it finds few enough rows to
+/// be cheaper than the scan, and the one finding the fewest is picked
+pub(crate) struct IndexSelection;
+
+impl Rule for IndexSelection {
+    fn apply(&self, plan: LogicalPlan, ctx: &Context) -> LogicalPlan {
+        transform_up(plan, &mut |plan| match plan {
+            LogicalPlan::Filter { input, predicate } => match *input {
+                LogicalPlan::Scan {
+                    table,
+                    alias,
+                    columns,
+                } => {
+                    let Ok(stored) = ctx.table(&table) else {
+                        return LogicalPlan::Filter {
+                            input: Box::new(LogicalPlan::Scan {
+                                table,
+                                alias,
+                                columns,
+                            }),
+                            predicate,
+                        };
+                    };
+                    let estimator = Estimator::new(ctx);
+                    let mut conditions = conjuncts(predicate);
+                    let found = conditions
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, condition)| {
+                            let (column, value) = lookup(condition, &alias, stored, ctx)?;
+                            let selectivity = estimator.index_selectivity(&table, &column, &value);
+                            estimator.index_cheaper(&table, &column, &value).then_some((
+                                i,
+                                (column, value),
+                                selectivity,
+                            ))
+                        })
+                        .min_by(|(_, _, l), (_, _, r)| l.total_cmp(r))
+                        .map(|(i, lookup, _)| (i, lookup));
+                    let scan = match found {
+                        Some((i, (column, value))) => {
+                            conditions.remove(i);
+                            LogicalPlan::IndexScan {
+                                table,
+                                alias,
+                                columns,
+                                column,
+                                value,
+                            }
+                        }
+                        None => LogicalPlan::Scan {
+                            table,
+                            alias,
+                            columns,
+                        },
+                    };
+                    match conjoin(conditions) {
+                        Some(predicate) => LogicalPlan::Filter {
+                            input: Box::new(scan),
+                            predicate,
+                        },
+                        None => scan,
+                    }
+                }
+                input => LogicalPlan::Filter {
+                    input: Box::new(input),
+                    predicate,
+                },
+            },
+            plan => plan,
+        })
+    }
+}
+
+/// The indexed column and the literal or parameter `condition` requires it
+/// to hold
+///
+/// The value has to be of the column's type, the index matches values
+/// exactly while comparisons convert between types, so a parameter is only
+/// used when binding it checks its values have the column's type
+fn lookup(
+    condition: &Expression,
+    alias: &str,
+    table: &Table,
+    ctx: &Context,
+) -> Option<(String, Expression)> {
+    let Expression::Binary {
+        left,
+        op: BinaryOperator::Eq,
+        right,
+    } = condition
+    else {
+        return None;
+    };
+    let (column, value) = match (left.as_ref(), right.as_ref()) {
+        (Expression::Column(column), value) | (value, Expression::Column(column)) => {
+            (column, value)
+        }
+        _ => return None,
+    };
+    if column.table.as_ref().is_some_and(|table| table != alias) {
+        return None;
+    }
+    let stored = table.columns().find_column(&column.name).ok()?;
+    let same_type = match value {
+        Expression::Literal(value) => matches!(
+            (stored.type_info, value),
+            (SqlTypeInfo::Int, Value::Number(_)) | (SqlTypeInfo::String, Value::String(_))
+        ),
+        Expression::Parameter(Parameter::Numbered(n)) => {
+            ctx.parameter_type(*n) == Some(stored.type_info)
+        }
+        _ => false,
+    };
+    (same_type && table.has_index_on(&column.name)).then(|| (column.name.clone(), value.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        optimizer::tests::{optimized, TABLES},
+        tests::{execution, run_all},
+    };
+
+    use super::IndexSelection;
+
+    #[test]
+    fn test_index_selection() {
+        let exec = execution(TABLES);
+        assert_eq!(
+            optimized(
+                &exec,
+                &IndexSelection,
+                "select name from emp where salary > 60 and 'eng' = dept"
+            ),
+            "Project: name\n\
+             \x20 Filter: salary > 60\n\
+             \x20   IndexScan: emp ON dept = 'eng' (name, dept, salary)"
+        );
+
+        // without an index, or with a value of another type, every row is read
+        for sql in [
+            "select name from emp where name = 'ann'",
+            "select name from emp where dept = 1",
+        ] {
+            assert!(
+                optimized(&exec, &IndexSelection, sql).ends_with("Scan: emp (name, dept, salary)")
+            );
+        }
+    }
+
+    #[test]
+    fn test_index_selection_with_statistics() {
+        let mut exec = execution(TABLES);
+        for i in 0..20 {
+            run_all(
+                &mut exec,
+                &[&format!("insert into emp values 'e{i}', 'eng', {i};")],
+            );
+        }
+        run_all(
+            &mut exec,
+            &["create index emp_name on emp (name);", "analyze emp;"],
+        );
+
+        // most employees are in eng, scanning them all is cheaper
+        let sql = "select name from emp where dept = 'eng'";
+        assert!(optimized(&exec, &IndexSelection, sql).ends_with("Scan: emp (name, dept, salary)"));
+
+        // while names are unique
+        assert_eq!(
+            optimized(
+                &exec,
+                &IndexSelection,
+                "select name from emp where dept = 'ops' and name = 'cid'"
+            ),
+            "Project: name\n\
+             \x20 Filter: dept = 'ops'\n\
+             \x20   IndexScan: emp ON name = 'cid' (name, dept, salary)"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::rc::Rc;
+
+use sql_mini_parser::{
+    commands::JoinKind,
+    expr::{ColumnRef, Expression},
+};
+
+use crate::{eval::Context, plan::LogicalPlan};
+
+use super::{column_refs, conjoin, conjuncts, cost::Estimator, has_subquery, resolves, Rule};
+
+/// Reorders chains of inner and cross joins so the joins producing the
+/// fewest rows run first, as estimated from the statistics of the tables
+///
+/// The chain starts with the input producing the fewest rows, then joins
+/// one input at a time, each time the one producing the fewest rows with
+/// those joined so far. Join conditions move to the first join having every
+/// column they read.
+///
+/// Only chains of at least three inputs reading analyzed tables are
+/// reordered, the order rows come out in changes with it
+pub(crate) struct JoinReorder;
+
+impl Rule for JoinReorder {
+    fn apply(&self, plan: LogicalPlan, ctx: &Context) -> LogicalPlan {
+        reorder(plan, &Estimator::new(ctx))
+    }
+}
+
+fn reorder(plan: LogicalPlan, estimator: &Estimator) -> LogicalPlan {
+    if !reorderable(&plan, estimator) {
+        return plan.map_inputs(|input| reorder(input, estimator));
+    }
+
+    let mut inputs = Vec::new();
+    let mut conditions = Vec::new();
+    flatten(plan, &mut inputs, &mut conditions);
+    let inputs = inputs
+        .into_iter()
+        .map(|input| reorder(input, estimator))
+        .collect();
+    join_greedily(inputs, conditions, estimator)
+}
+
+/// Whether `plan` is a join chain worth reordering
+fn reorderable(plan: &LogicalPlan, estimator: &Estimator) -> bool {
+    fn chain<'p>(plan: &'p LogicalPlan, inputs: &mut Vec<&'p LogicalPlan>) {
+        match plan {
+            LogicalPlan::Join {
+                left,
+                right,
+                kind: JoinKind::Inner | JoinKind::Cross,
+                ..
+            } => {
+                chain(left, inputs);
+                chain(right, inputs);
+            }
+            plan => inputs.push(plan),
+        }
+    }
+
+    if !matches!(
+        plan,
+        LogicalPlan::Join {
+            kind: JoinKind::Inner | JoinKind::Cross,
+            ..
+        }
+    ) {
+        return false;
+    }
+    let mut inputs = Vec::new();
+    chain(plan, &mut inputs);
+
+    // columns are found by name, reordering inputs sharing a qualified name
+    // would change which one is read
+    let columns = plan.columns();
+    let distinct_names = columns
+        .iter()
+        .enumerate()
+        .all(|(i, column)| column.table.is_some() && !columns[..i].contains(column));
+    inputs.len() >= 3 && distinct_names && inputs.iter().all(|input| estimator.analyzed(input))
+}
+
+/// Split a join chain into its inputs and the conditions of its joins
+fn flatten(plan: LogicalPlan, inputs: &mut Vec<LogicalPlan>, conditions: &mut Vec<Expression>) {
+    match plan {
+        LogicalPlan::Join {
+            left,
+            right,
+            kind: JoinKind::Inner | JoinKind::Cross,
+            on,
+            ..
+        } => {
+            flatten(*left, inputs, conditions);
+            flatten(*right, inputs, conditions);
+            conditions.extend(on.into_iter().flat_map(conjuncts));
+        }
+        plan => inputs.push(plan),
+    }
+}
+
+fn join_greedily(
+    mut inputs: Vec<LogicalPlan>,
+    mut conditions: Vec<Expression>,
+    estimator: &Estimator,
+) -> LogicalPlan {
+    let chain: Vec<ColumnRef> = inputs
+        .iter()
+        .flat_map(|input| input.columns().to_vec())
+        .collect();
+    let cheapest = |candidates: &[LogicalPlan]| {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, l), (_, r)| estimator.rows(l).total_cmp(&estimator.rows(r)))
+            .map_or(0, |(i, _)| i)
+    };
+
+    let first = cheapest(&inputs);
+    let mut joined = inputs.remove(first);
+    while !inputs.is_empty() {
+        let last = inputs.len() == 1;
+        let candidates: Vec<_> = inputs
+            .iter()
+            .map(|input| {
+                let mut conditions = conditions.clone();
+                join(joined.clone(), input.clone(), &mut conditions, &chain, last)
+            })
+            .collect();
+        let next = cheapest(&candidates);
+        joined = join(joined, inputs.remove(next), &mut conditions, &chain, last);
+    }
+    joined
+}
+
+/// Join `left` and `right` on the `conditions` reading only their columns,
+/// or on every remaining one for the `last` join of the chain
+fn join(
+    left: LogicalPlan,
+    right: LogicalPlan,
+    conditions: &mut Vec<Expression>,
+    chain: &[ColumnRef],
+    last: bool,
+) -> LogicalPlan {
+    let columns: Rc<[ColumnRef]> = left
+        .columns()
+        .iter()
+        .chain(right.columns().iter())
+        .cloned()
+        .collect();
+    let (placed, remaining) = std::mem::take(conditions)
+        .into_iter()
+        .partition(|condition| last || placeable(condition, &columns, chain));
+    *conditions = remaining;
+
+    let on = conjoin(placed);
+    LogicalPlan::Join {
+        left: Box::new(left),
+        right: Box::new(right),
+        kind: if on.is_some() {
+            JoinKind::Inner
+        } else {
+            JoinKind::Cross
+        },
+        on,
+        columns,
+    }
+}
+
+/// Whether every column of the chain `condition` reads is in `columns`,
+/// references to enclosing queries can be read anywhere
+fn placeable(condition: &Expression, columns: &[ColumnRef], chain: &[ColumnRef]) -> bool {
+    if has_subquery(condition) {
+        return false;
+    }
+    let mut refs = Vec::new();
+    column_refs(condition, &mut refs);
+    refs.iter()
+        .all(|column| resolves(column, columns) || !resolves(column, chain))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        optimizer::{tests::optimized, Optimizer, PredicatePushdown, Rule},
+        tests::run_all,
+        Execution,
+    };
+
+    use super::JoinReorder;
+
+    #[test]
+    fn test_join_reorder() {
+        let mut exec = Execution::new();
+        run_all(
+            &mut exec,
+            &[
+                "create table big (id int);",
+                "create table mid (id int);",
+                "create table small (id int);",
+            ],
+        );
+        for (table, rows) in [("big", 50), ("mid", 20), ("small", 3)] {
+            for i in 0..rows {
+                run_all(&mut exec, &[&format!("insert into {table} values {i};")]);
+            }
+        }
+        let rules: Vec<Box<dyn Rule>> = vec![Box::new(PredicatePushdown), Box::new(JoinReorder)];
+        let optimizer = Optimizer { rules };
+        let sql =
+            "select big.id from big, mid, small where big.id = small.id and mid.id = small.id";
+
+        // without statistics the joins run as written
+        assert_eq!(
+            optimized(&exec, &optimizer, sql),
+            "Project: big.id\n\
+             \x20 Join: INNER ON big.id = small.id AND mid.id = small.id\n\
+             \x20   Join: CROSS\n\
+             \x20     Scan: big (id)\n\
+             \x20     Scan: mid (id)\n\
+             \x20   Scan: small (id)"
+        );
+
+        run_all(&mut exec, &["analyze;"]);
+        assert_eq!(
+            optimized(&exec, &optimizer, sql),
+            "Project: big.id\n\
+             \x20 Join: INNER ON mid.id = small.id\n\
+             \x20   Join: INNER ON big.id = small.id\n\
+             \x20     Scan: small (id)\n\
+             \x20     Scan: big (id)\n\
+             \x20   Scan: mid (id)"
+        );
+        let rows = run_all(&mut exec, &[&format!("{sql} order by 1;")]);
+        assert_eq!(rows.len(), 3);
+    }
+}
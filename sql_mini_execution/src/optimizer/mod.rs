@@ -0,0 +1,181 @@
+//! Rule based rewrites of a [`LogicalPlan`] into an equivalent plan that is
+//! cheaper to run
+//!
+//! Every rule produces the same rows as the plan it was given, in the same
+//! order wherever the query defines one
+
+use sql_mini_parser::expr::{BinaryOperator, ColumnRef, Expression};
+
+use crate::{eval::Context, plan::LogicalPlan};
+
+mod constant_folding;
+mod cost;
+mod index_selection;
+mod join_reorder;
+mod predicate_pushdown;
+mod projection_pruning;
+mod simplify;
+mod sort_elimination;
+
+pub(crate) use constant_folding::ConstantFolding;
+pub(crate) use index_selection::IndexSelection;
+pub(crate) use join_reorder::JoinReorder;
+pub(crate) use predicate_pushdown::PredicatePushdown;
+pub(crate) use projection_pruning::ProjectionPruning;
+pub(crate) use simplify::SimplifyBooleans;
+pub(crate) use sort_elimination::SortElimination;
+
+/// A single rewrite of a plan
+pub(crate) trait Rule {
+    fn apply(&self, plan: LogicalPlan, ctx: &Context) -> LogicalPlan;
+}
+
+/// Runs every rule over a plan, in order
+pub(crate) struct Optimizer {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                Box::new(ConstantFolding),
+                Box::new(SimplifyBooleans),
+                // filters pushed down to a scan can pick an index
+                Box::new(PredicatePushdown),
+                // with the join conditions next to the joins
+                Box::new(JoinReorder),
+                Box::new(IndexSelection),
+                Box::new(SortElimination),
+                // last, so it sees every column the other rules left in use
+                Box::new(ProjectionPruning),
+            ],
+        }
+    }
+}
+
+impl Rule for Optimizer {
+    fn apply(&self, plan: LogicalPlan, ctx: &Context) -> LogicalPlan {
+        self.rules
+            .iter()
+            .fold(plan, |plan, rule| rule.apply(plan, ctx))
+    }
+}
+
+/// Apply `f` to every node of `plan`, inputs before the nodes reading them
+pub(crate) fn transform_up(
+    plan: LogicalPlan,
+    f: &mut dyn FnMut(LogicalPlan) -> LogicalPlan,
+) -> LogicalPlan {
+    let plan = plan.map_inputs(|input| transform_up(input, f));
+    f(plan)
+}
+
+/// Split a predicate into the conditions AND-ed together
+pub(crate) fn conjuncts(expr: Expression) -> Vec<Expression> {
+    match expr {
+        Expression::Binary {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut conditions = conjuncts(*left);
+            conditions.extend(conjuncts(*right));
+            conditions
+        }
+        expr => vec![expr],
+    }
+}
+
+/// AND conditions back together, `None` without any
+pub(crate) fn conjoin(conditions: Vec<Expression>) -> Option<Expression> {
+    conditions
+        .into_iter()
+        .reduce(|left, right| Expression::binary(left, BinaryOperator::And, right))
+}
+
+/// Whether `expr` contains a subquery, whose references to the columns of
+/// the current row can not be seen
+pub(crate) fn has_subquery(expr: &Expression) -> bool {
+    match expr {
+        Expression::Subquery(_) | Expression::InSubquery { .. } | Expression::Exists(_) => true,
+        expr => expr.children().into_iter().any(has_subquery),
+    }
+}
+
+/// Collect every column `expr` references outside of subqueries
+pub(crate) fn column_refs(expr: &Expression, refs: &mut Vec<ColumnRef>) {
+    match expr {
+        Expression::Column(column) => refs.push(column.clone()),
+        expr => expr
+            .children()
+            .into_iter()
+            .for_each(|child| column_refs(child, refs)),
+    }
+}
+
+/// Whether `column` refers to any of `columns`, the same way
+/// [`Row::resolve`](crate::row::Row::resolve) finds it
+pub(crate) fn resolves(column: &ColumnRef, columns: &[ColumnRef]) -> bool {
+    columns
+        .iter()
+        .any(|col| col.name == column.name && (column.table.is_none() || col.table == column.table))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use sql_mini_parser::{commands::CompoundSelect, parse::Parse, value::Value};
+
+    use crate::{
+        binder::Binder,
+        eval::{Context, Env},
+        tests::{execution, run_all},
+        Execution,
+    };
+
+    use super::{Optimizer, Rule};
+
+    pub(crate) const TABLES: &[&str] = &[
+        "create table emp (name string, dept string, salary int);",
+        "create index emp_dept on emp (dept);",
+        "insert into emp values 'ann', 'eng', 100;",
+        "insert into emp values 'bob', 'eng', 80;",
+        "insert into emp values 'cid', 'ops', 50;",
+        "create table depts (name string, floor int);",
+        "insert into depts values 'eng', 1;",
+        "insert into depts values 'ops', 2;",
+    ];
+
+    /// The plan of `sql` after applying `rule`
+    pub(crate) fn optimized(exec: &Execution, rule: &dyn Rule, sql: &str) -> String {
+        let (_, query) = CompoundSelect::parse_from_raw(sql).unwrap();
+        let ctx = Context::new(&exec.functions, &exec.tables);
+        let plan = Binder::new(Env::new(&ctx)).bind_query(&query).unwrap();
+        rule.apply(plan, &ctx).to_string()
+    }
+
+    #[test]
+    fn test_all_rules() {
+        let mut exec = execution(TABLES);
+        let sql = "select e.name, d.floor from emp e, depts d \
+                   where e.dept = d.name and e.dept = 'eng' and (1 = 1 or e.salary > 10) \
+                   order by e.name";
+        assert_eq!(
+            optimized(&exec, &Optimizer::default(), sql),
+            "Project: e.name, d.floor\n\
+             \x20 Sort: e.name\n\
+             \x20   Join: INNER ON e.dept = d.name\n\
+             \x20     IndexScan: emp AS e ON dept = 'eng' (name, dept)\n\
+             \x20     Scan: depts AS d (name, floor)"
+        );
+
+        let rows = run_all(&mut exec, &[&format!("{sql};")]);
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::String("ann".into()), Value::Number(1.into())],
+                vec![Value::String("bob".into()), Value::Number(1.into())],
+            ]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use sql_mini_parser::{commands::JoinKind, expr::Expression};
+
+use crate::{eval::Context, plan::LogicalPlan};
+
+use super::{column_refs, conjoin, conjuncts, has_subquery, resolves, Rule};
+
+/// Moves filter conditions down the plan, as close to the rows they read as
+/// they can go, so fewer rows reach the nodes above
+///
+/// Conditions on both sides of an inner join become its join condition, and
+/// a cross join with one becomes an inner join
+pub(crate) struct PredicatePushdown;
+
+impl Rule for PredicatePushdown {
+    fn apply(&self, plan: LogicalPlan, _ctx: &Context) -> LogicalPlan {
+        pushdown(plan)
+    }
+}
+
+fn pushdown(plan: LogicalPlan) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Filter { input, predicate } => push(*input, conjuncts(predicate)),
+        // the join's own condition may move to its sides
+        plan @ LogicalPlan::Join { .. } => push(plan, vec![]),
+        plan => plan.map_inputs(pushdown),
+    }
+}
+
+/// Place `conditions`, filtering the rows of `plan`, as low in it as they
+/// can go
+fn push(plan: LogicalPlan, mut conditions: Vec<Expression>) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Filter { input, predicate } => {
+            conditions.extend(conjuncts(predicate));
+            push(*input, conditions)
+        }
+        // removing rows does not change the order of the others
+        LogicalPlan::Sort { input, order_by } => LogicalPlan::Sort {
+            input: Box::new(push(*input, conditions)),
+            order_by,
+        },
+        LogicalPlan::Join {
+            left,
+            right,
+            kind,
+            on,
+            columns,
+        } => {
+            let (left_columns, right_columns) = (left.columns(), right.columns());
+            let side = |condition: &Expression| {
+                let mut refs = Vec::new();
+                column_refs(condition, &mut refs);
+                (
+                    refs.iter().any(|col| resolves(col, &left_columns)),
+                    refs.iter().any(|col| resolves(col, &right_columns)),
+                )
+            };
+            let keeps_left_rows = kind == JoinKind::Left;
+
+            let (mut to_left, mut to_right, mut join_on, mut above) =
+                (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+            // a LEFT JOIN keeps every left row whatever its condition says,
+            // only what it requires of the right rows can filter them early
+            for condition in on.into_iter().flat_map(conjuncts) {
+                match side(&condition) {
+                    _ if has_subquery(&condition) => join_on.push(condition),
+                    (true, false) if !keeps_left_rows => to_left.push(condition),
+                    (false, true) => to_right.push(condition),
+                    _ => join_on.push(condition),
+                }
+            }
+            // the right side of a LEFT JOIN is padded with NULLs after the
+            // join, so conditions on it have to wait until then
+            for condition in conditions {
+                match side(&condition) {
+                    _ if has_subquery(&condition) => above.push(condition),
+                    (true, false) => to_left.push(condition),
+                    _ if keeps_left_rows => above.push(condition),
+                    (false, true) => to_right.push(condition),
+                    _ => join_on.push(condition),
+                }
+            }
+
+            let on = conjoin(join_on);
+            let kind = match kind {
+                JoinKind::Cross if on.is_some() => JoinKind::Inner,
+                kind => kind,
+            };
+            let join = LogicalPlan::Join {
+                left: Box::new(push(*left, to_left)),
+                right: Box::new(push(*right, to_right)),
+                kind,
+                on,
+                columns,
+            };
+            filter(join, above)
+        }
+        plan => filter(plan.map_inputs(pushdown), conditions),
+    }
+}
+
+/// `plan` filtered by `conditions`, if there are any
+fn filter(plan: LogicalPlan, conditions: Vec<Expression>) -> LogicalPlan {
+    match conjoin(conditions) {
+        Some(predicate) => LogicalPlan::Filter {
+            input: Box::new(plan),
+            predicate,
+        },
+        None => plan,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        optimizer::tests::{optimized, TABLES},
+        tests::execution,
+    };
+
+    use super::PredicatePushdown;
+
+    #[test]
+    fn test_pushdown_into_joins() {
+        let exec = execution(TABLES);
+        assert_eq!(
+            optimized(
+                &exec,
+                &PredicatePushdown,
+                "select e.name from emp e, depts d \
+                 where e.dept = d.name and e.salary > 60 and d.floor = 1 order by e.name"
+            ),
+            "Project: e.name\n\
+             \x20 Sort: e.name\n\
+             \x20   Join: INNER ON e.dept = d.name\n\
+             \x20     Filter: e.salary > 60\n\
+             \x20       Scan: emp AS e (name, dept, salary)\n\
+             \x20     Filter: d.floor = 1\n\
+             \x20       Scan: depts AS d (name, floor)"
+        );
+
+        // conditions on the padded side of a LEFT JOIN stay above it
+        assert_eq!(
+            optimized(
+                &exec,
+                &PredicatePushdown,
+                "select e.name from emp e left join depts d on e.dept = d.name and d.floor = 1 \
+                 where e.salary > 60 and d.floor is null"
+            ),
+            "Project: e.name\n\
+             \x20 Filter: d.floor IS NULL\n\
+             \x20   Join: LEFT ON e.dept = d.name\n\
+             \x20     Filter: e.salary > 60\n\
+             \x20       Scan: emp AS e (name, dept, salary)\n\
+             \x20     Filter: d.floor = 1\n\
+             \x20       Scan: depts AS d (name, floor)"
+        );
+    }
+
+    #[test]
+    fn test_pushdown_stops() {
+        let exec = execution(TABLES);
+        // filtering before grouping or limiting would change the result
+        assert_eq!(
+            optimized(
+                &exec,
+                &PredicatePushdown,
+                "select name from (select name, salary from emp limit 2) x where salary > 60"
+            ),
+            "Project: name\n\
+             \x20 Filter: salary > 60\n\
+             \x20   Alias: x (name, salary)\n\
+             \x20     Limit: 2\n\
+             \x20       Project: name, salary\n\
+             \x20         Scan: emp (name, dept, salary)"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::rc::Rc;
+
+use sql_mini_parser::expr::{ColumnRef, Expression, FunctionArgs};
+
+use crate::{eval::Context, plan::LogicalPlan};
+
+use super::{column_refs, has_subquery, Rule};
+
+/// Reads only the columns of a table that the rest of the plan uses
+pub(crate) struct ProjectionPruning;
+
+impl Rule for ProjectionPruning {
+    fn apply(&self, plan: LogicalPlan, _ctx: &Context) -> LogicalPlan {
+        prune(plan, None)
+    }
+}
+
+/// `required` is the columns the nodes above read, `None` for all of them
+fn prune(plan: LogicalPlan, required: Option<Vec<ColumnRef>>) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Scan {
+            table,
+            alias,
+            columns,
+        } => LogicalPlan::Scan {
+            table,
+            alias,
+            columns: keep(columns, required),
+        },
+        LogicalPlan::IndexScan {
+            table,
+            alias,
+            columns,
+            column,
+            value,
+        } => LogicalPlan::IndexScan {
+            table,
+            alias,
+            columns: keep(columns, required),
+            column,
+            value,
+        },
+        plan @ LogicalPlan::Values { .. } => plan,
+        // rows are renamed by position
+        plan @ (LogicalPlan::Alias { .. }
+        | LogicalPlan::SetOperation { .. }
+        | LogicalPlan::Distinct { on: None, .. }) => plan.map_inputs(|input| prune(input, None)),
+        LogicalPlan::Filter { input, predicate } => LogicalPlan::Filter {
+            input: Box::new(prune(*input, with(required, [&predicate]))),
+            predicate,
+        },
+        LogicalPlan::Join {
+            left,
+            right,
+            kind,
+            on,
+            ..
+        } => {
+            let required = with(required, on.iter());
+            let left = prune(*left, required.clone());
+            let right = prune(*right, required);
+            let columns = left
+                .columns()
+                .iter()
+                .chain(right.columns().iter())
+                .cloned()
+                .collect();
+            LogicalPlan::Join {
+                left: Box::new(left),
+                right: Box::new(right),
+                kind,
+                on,
+                columns,
+            }
+        }
+        LogicalPlan::Aggregate {
+            input,
+            group_by,
+            aggregates,
+            columns,
+        } => {
+            let calls: Vec<_> = aggregates
+                .iter()
+                .cloned()
+                .map(Expression::Function)
+                .collect();
+            let input = prune(*input, with(required, group_by.iter().chain(&calls)));
+            let columns = appended(&input, &columns, aggregates.len());
+            LogicalPlan::Aggregate {
+                input: Box::new(input),
+                group_by,
+                aggregates,
+                columns,
+            }
+        }
+        LogicalPlan::Window {
+            input,
+            windows,
+            columns,
+        } => {
+            let exprs: Vec<_> = windows
+                .iter()
+                .flat_map(|window| {
+                    let args = match &window.call.args {
+                        FunctionArgs::Wildcard => &[][..],
+                        FunctionArgs::List(args) => args,
+                    };
+                    args.iter()
+                        .chain(&window.window.partition_by)
+                        .chain(window.window.order_by.iter().map(|item| &item.expr))
+                })
+                .collect();
+            let input = prune(*input, with(required, exprs));
+            let columns = appended(&input, &columns, windows.len());
+            LogicalPlan::Window {
+                input: Box::new(input),
+                windows,
+                columns,
+            }
+        }
+        // the columns read above are those of the projection, not its input
+        LogicalPlan::Project {
+            input,
+            exprs,
+            columns,
+        } => LogicalPlan::Project {
+            input: Box::new(prune(*input, with(Some(Vec::new()), &exprs))),
+            exprs,
+            columns,
+        },
+        LogicalPlan::Sort { input, order_by } => LogicalPlan::Sort {
+            input: Box::new(prune(
+                *input,
+                with(required, order_by.iter().map(|item| &item.expr)),
+            )),
+            order_by,
+        },
+        LogicalPlan::Distinct {
+            input,
+            on: Some(on),
+        } => LogicalPlan::Distinct {
+            input: Box::new(prune(*input, with(required, &on))),
+            on: Some(on),
+        },
+        LogicalPlan::Limit {
+            input,
+            limit,
+            offset,
+        } => LogicalPlan::Limit {
+            input: Box::new(prune(*input, required)),
+            limit,
+            offset,
+        },
+    }
+}
+
+/// `required` along with the columns `exprs` read
+///
+/// The columns a subquery reads can not be told, so any requires them all
+fn with<'e>(
+    required: Option<Vec<ColumnRef>>,
+    exprs: impl IntoIterator<Item = &'e Expression>,
+) -> Option<Vec<ColumnRef>> {
+    let mut required = required?;
+    for expr in exprs {
+        if has_subquery(expr) {
+            return None;
+        }
+        column_refs(expr, &mut required);
+    }
+    Some(required)
+}
+
+/// The columns of a table that are required
+fn keep(columns: Rc<[ColumnRef]>, required: Option<Vec<ColumnRef>>) -> Rc<[ColumnRef]> {
+    let Some(required) = required else {
+        return columns;
+    };
+    columns
+        .iter()
+        .filter(|col| {
+            required
+                .iter()
+                .any(|req| req.name == col.name && (req.table.is_none() || req.table == col.table))
+        })
+        .cloned()
+        .collect()
+}
+
+/// The columns of `input` followed by the last `count` of `columns`, those
+/// a node appends to its input rows
+fn appended(input: &LogicalPlan, columns: &[ColumnRef], count: usize) -> Rc<[ColumnRef]> {
+    input
+        .columns()
+        .iter()
+        .chain(&columns[columns.len() - count..])
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        optimizer::tests::{optimized, TABLES},
+        tests::execution,
+    };
+
+    use super::ProjectionPruning;
+
+    #[test]
+    fn test_projection_pruning() {
+        let exec = execution(TABLES);
+        assert_eq!(
+            optimized(
+                &exec,
+                &ProjectionPruning,
+                "select e.name, sum(e.salary) from emp e join depts d on e.dept = d.name \
+                 group by e.name"
+            ),
+            "Project: e.name, sum(e.salary)\n\
+             \x20 Aggregate: sum(e.salary) GROUP BY e.name\n\
+             \x20   Join: INNER ON e.dept = d.name\n\
+             \x20     Scan: emp AS e (name, dept, salary)\n\
+             \x20     Scan: depts AS d (name)"
+        );
+
+        // a subquery may read any column
+        assert_eq!(
+            optimized(
+                &exec,
+                &ProjectionPruning,
+                "select name from depts where exists (select 1 from emp where dept = floor)"
+            ),
+            "Project: name\n\
+             \x20 Filter: EXISTS (SELECT 1 FROM emp WHERE dept = floor)\n\
+             \x20   Scan: depts (name, floor)"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use sql_mini_parser::{
+    expr::{BinaryOperator, Expression},
+    value::Value,
+};
+
+use crate::{eval::Context, plan::LogicalPlan};
+
+use super::{transform_up, Rule};
+
+/// Simplifies `AND` and `OR` with a literal true or false side, then drops
+/// filters and join conditions that are always true
+pub(crate) struct SimplifyBooleans;
+
+impl Rule for SimplifyBooleans {
+    fn apply(&self, plan: LogicalPlan, ctx: &Context) -> LogicalPlan {
+        transform_up(plan, &mut |mut plan| {
+            for expr in plan.expressions_mut() {
+                simplify(expr, ctx);
+            }
+            match plan {
+                LogicalPlan::Filter {
+                    input,
+                    predicate: Expression::Literal(Value::Bool(true)),
+                } => *input,
+                LogicalPlan::Join {
+                    left,
+                    right,
+                    kind,
+                    on: Some(Expression::Literal(Value::Bool(true))),
+                    columns,
+                } => LogicalPlan::Join {
+                    left,
+                    right,
+                    kind,
+                    on: None,
+                    columns,
+                },
+                plan => plan,
+            }
+        })
+    }
+}
+
+fn simplify(expr: &mut Expression, ctx: &Context) {
+    match expr {
+        // the nodes above refer to these by how they are written
+        Expression::Function(call) if ctx.functions.is_aggregate(&call.name) => return,
+        Expression::Window(_) => return,
+        _ => {}
+    }
+    for child in expr.children_mut() {
+        simplify(child, ctx);
+    }
+
+    let Expression::Binary {
+        op: op @ (BinaryOperator::And | BinaryOperator::Or),
+        ..
+    } = expr
+    else {
+        return;
+    };
+    // the value that decides the outcome on its own, true for OR
+    let decisive = *op == BinaryOperator::Or;
+    let Expression::Binary { left, right, .. } =
+        std::mem::replace(expr, Expression::Literal(Value::Null))
+    else {
+        unreachable!()
+    };
+    *expr = match (*left, *right) {
+        (Expression::Literal(Value::Bool(b)), other)
+        | (other, Expression::Literal(Value::Bool(b))) => {
+            if b == decisive {
+                Expression::Literal(Value::Bool(b))
+            } else {
+                other
+            }
+        }
+        (left, right) => Expression::binary(
+            left,
+            if decisive {
+                BinaryOperator::Or
+            } else {
+                BinaryOperator::And
+            },
+            right,
+        ),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        optimizer::tests::{optimized, TABLES},
+        tests::execution,
+    };
+
+    use super::SimplifyBooleans;
+
+    #[test]
+    fn test_simplify_booleans() {
+        let exec = execution(TABLES);
+        assert_eq!(
+            optimized(
+                &exec,
+                &SimplifyBooleans,
+                "select name from emp where (salary > 1 and true) or (false and name = 'x')"
+            ),
+            "Project: name\n\
+             \x20 Filter: salary > 1\n\
+             \x20   Scan: emp (name, dept, salary)"
+        );
+
+        // a filter that is always true is dropped
+        assert_eq!(
+            optimized(
+                &exec,
+                &SimplifyBooleans,
+                "select e.name from emp e join depts d on true where e.name = 'x' or true"
+            ),
+            "Project: e.name\n\
+             \x20 Join: INNER\n\
+             \x20   Scan: emp AS e (name, dept, salary)\n\
+             \x20   Scan: depts AS d (name, floor)"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use sql_mini_parser::expr::Expression;
+
+use crate::{eval::Context, plan::LogicalPlan};
+
+use super::{transform_up, Rule};
+
+/// Removes sorts that can not change the order of the rows: sorts by
+/// constants, sorts of a single row, and sorts whose order a later sort
+/// replaces
+pub(crate) struct SortElimination;
+
+impl Rule for SortElimination {
+    fn apply(&self, plan: LogicalPlan, _ctx: &Context) -> LogicalPlan {
+        transform_up(plan, &mut |plan| match plan {
+            LogicalPlan::Sort { input, order_by } => {
+                let order_by: Vec<_> = order_by
+                    .into_iter()
+                    .filter(|item| !matches!(item.expr, Expression::Literal(_)))
+                    .collect();
+                if order_by.is_empty() || single_row(&input) {
+                    return *input;
+                }
+                LogicalPlan::Sort {
+                    input: Box::new(unsorted(*input)),
+                    order_by,
+                }
+            }
+            plan => plan,
+        })
+    }
+}
+
+/// `plan` without the sorts whose order nothing depends on before the next
+/// sort
+fn unsorted(plan: LogicalPlan) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Sort { input, .. } => unsorted(*input),
+        plan @ (LogicalPlan::Filter { .. }
+        | LogicalPlan::Project { .. }
+        | LogicalPlan::Alias { .. }
+        | LogicalPlan::Distinct { on: None, .. }) => plan.map_inputs(unsorted),
+        plan => plan,
+    }
+}
+
+/// Whether `plan` produces at most one row
+fn single_row(plan: &LogicalPlan) -> bool {
+    match plan {
+        LogicalPlan::Aggregate { group_by, .. } => group_by.is_empty(),
+        LogicalPlan::Filter { input, .. }
+        | LogicalPlan::Project { input, .. }
+        | LogicalPlan::Alias { input, .. }
+        | LogicalPlan::Distinct { input, .. } => single_row(input),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        optimizer::tests::{optimized, TABLES},
+        tests::execution,
+    };
+
+    use super::SortElimination;
+
+    #[test]
+    fn test_sort_elimination() {
+        let exec = execution(TABLES);
+        assert_eq!(
+            optimized(
+                &exec,
+                &SortElimination,
+                "select name from (select name from emp order by salary) x order by 'a', name"
+            ),
+            "Project: name\n\
+             \x20 Sort: name\n\
+             \x20   Alias: x (name)\n\
+             \x20     Project: name\n\
+             \x20       Scan: emp (name, dept, salary)"
+        );
+
+        assert_eq!(
+            optimized(
+                &exec,
+                &SortElimination,
+                "select count(*) from emp order by count(*)"
+            ),
+            "Project: count(*)\n\
+             \x20 Aggregate: count(*)\n\
+             \x20   Scan: emp (name, dept, salary)"
+        );
+
+        // a limit depends on the order of its input
+        assert!(optimized(
+            &exec,
+            &SortElimination,
+            "select name from (select name from emp order by salary limit 1) x order by name"
+        )
+        .contains("Sort: salary"));
+    }
+}
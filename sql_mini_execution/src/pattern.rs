@@ -0,0 +1,207 @@
+use sql_mini_parser::expr::PatternOperator;
+
+use crate::error::QueryExecutionError;
+
+/// A single element of a compiled LIKE or GLOB pattern
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `_` or `?`
+    AnyOne,
+    /// `%` or `*`
+    AnyMany,
+    /// `[abc]`, `[a-z]` or `[^a-z]`, GLOB only
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Literal(l) => *l == c,
+            Token::AnyOne => true,
+            Token::AnyMany => false,
+            Token::Class { negated, ranges } => {
+                ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)) != *negated
+            }
+        }
+    }
+}
+
+/// A compiled pattern for one of the [`PatternOperator`]s
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern {
+    tokens: Vec<Token>,
+    ignore_case: bool,
+}
+
+impl Pattern {
+    pub(crate) fn new(
+        op: PatternOperator,
+        pattern: &str,
+        escape: Option<&str>,
+    ) -> Result<Self, QueryExecutionError> {
+        let escape = match escape {
+            None => None,
+            Some(escape) => {
+                let mut chars = escape.chars();
+                match (chars.next(), chars.next(), op) {
+                    (Some(c), None, PatternOperator::Like | PatternOperator::ILike) => Some(c),
+                    _ => return Err(QueryExecutionError::InvalidPattern(escape.to_owned())),
+                }
+            }
+        };
+
+        let ignore_case = op == PatternOperator::ILike;
+        let pattern = if ignore_case {
+            pattern.to_lowercase()
+        } else {
+            pattern.to_owned()
+        };
+
+        let tokens = match op {
+            PatternOperator::Like | PatternOperator::ILike => like_tokens(&pattern, escape)?,
+            PatternOperator::Glob => glob_tokens(&pattern)?,
+        };
+
+        Ok(Self {
+            tokens,
+            ignore_case,
+        })
+    }
+
+    /// Whether the whole of `text` matches the pattern
+    pub(crate) fn matches(&self, text: &str) -> bool {
+        let text: Vec<char> = if self.ignore_case {
+            text.to_lowercase().chars().collect()
+        } else {
+            text.chars().collect()
+        };
+
+        // greedy match that backtracks to the last `AnyMany` on a mismatch
+        let (mut t, mut p) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None;
+        while t < text.len() {
+            match self.tokens.get(p) {
+                Some(Token::AnyMany) => {
+                    backtrack = Some((p, t));
+                    p += 1;
+                }
+                Some(token) if token.matches(text[t]) => {
+                    t += 1;
+                    p += 1;
+                }
+                _ => match backtrack {
+                    Some((star_p, star_t)) => {
+                        p = star_p + 1;
+                        t = star_t + 1;
+                        backtrack = Some((star_p, star_t + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        self.tokens[p..]
+            .iter()
+            .all(|token| *token == Token::AnyMany)
+    }
+}
+
+fn like_tokens(pattern: &str, escape: Option<char>) -> Result<Vec<Token>, QueryExecutionError> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if Some(c) == escape => match chars.next() {
+                Some(escaped) => Token::Literal(escaped),
+                None => return Err(QueryExecutionError::InvalidPattern(pattern.to_owned())),
+            },
+            '%' => Token::AnyMany,
+            '_' => Token::AnyOne,
+            c => Token::Literal(c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn glob_tokens(pattern: &str) -> Result<Vec<Token>, QueryExecutionError> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '*' => Token::AnyMany,
+            '?' => Token::AnyOne,
+            '[' => {
+                let negated = chars.next_if_eq(&'^').is_some();
+                let mut ranges = Vec::new();
+                // a `]` right after the opening bracket is a literal
+                if let Some(c) = chars.next_if_eq(&']') {
+                    ranges.push((c, c));
+                }
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(lo) => {
+                            let mut lookahead = chars.clone();
+                            let hi = match (lookahead.next(), lookahead.next()) {
+                                (Some('-'), Some(hi)) if hi != ']' => {
+                                    chars.nth(1);
+                                    hi
+                                }
+                                _ => lo,
+                            };
+                            ranges.push((lo, hi));
+                        }
+                        None => {
+                            return Err(QueryExecutionError::InvalidPattern(pattern.to_owned()))
+                        }
+                    }
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Literal(c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::expr::PatternOperator;
+
+    use super::Pattern;
+
+    fn matches(op: PatternOperator, pattern: &str, escape: Option<&str>, text: &str) -> bool {
+        Pattern::new(op, pattern, escape).unwrap().matches(text)
+    }
+
+    #[test]
+    fn test_like() {
+        assert!(matches(PatternOperator::Like, "a%c", None, "abbbc"));
+        assert!(matches(PatternOperator::Like, "a_c", None, "abc"));
+        assert!(!matches(PatternOperator::Like, "a_c", None, "abbc"));
+        assert!(!matches(PatternOperator::Like, "ABC", None, "abc"));
+        assert!(matches(PatternOperator::ILike, "A%", None, "abc"));
+        assert!(matches(PatternOperator::Like, "100!%", Some("!"), "100%"));
+        assert!(!matches(PatternOperator::Like, "100!%", Some("!"), "1000"));
+        assert!(Pattern::new(PatternOperator::Like, "a", Some("!!")).is_err());
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(matches(PatternOperator::Glob, "*.rs", None, "main.rs"));
+        assert!(matches(
+            PatternOperator::Glob,
+            "file?.[a-c]",
+            None,
+            "file1.b"
+        ));
+        assert!(!matches(PatternOperator::Glob, "[^0-9]*", None, "1abc"));
+        assert!(!matches(PatternOperator::Glob, "*.RS", None, "main.rs"));
+    }
+}
@@ -0,0 +1,429 @@
+use std::{fmt, rc::Rc};
+
+use sql_mini_parser::{
+    commands::{JoinKind, OrderByItem, SetOperator},
+    display::fmt_list,
+    expr::{ColumnRef, Expression, FunctionCall},
+    window::WindowFunction,
+};
+
+use crate::{binder::column_name, result::ResultSet};
+
+/// What a query computes, as a tree of relational operations with every
+/// name resolved, before deciding how to run it
+///
+/// Every node knows the columns of the rows it produces
+#[derive(Debug, Clone)]
+pub enum LogicalPlan {
+    /// Every row of a stored table, qualified with `alias`
+    Scan {
+        table: String,
+        alias: String,
+        columns: Rc<[ColumnRef]>,
+    },
+    /// The rows of a stored table whose indexed `column` holds `value`, a
+    /// literal or a parameter evaluated when the query runs
+    IndexScan {
+        table: String,
+        alias: String,
+        columns: Rc<[ColumnRef]>,
+        column: String,
+        value: Expression,
+    },
+    /// Rows that were already computed, like those of a CTE
+    Values {
+        name: String,
+        result: Rc<ResultSet>,
+        columns: Rc<[ColumnRef]>,
+    },
+    /// The rows of a derived table, requalified with its alias
+    Alias {
+        input: Box<LogicalPlan>,
+        alias: String,
+        columns: Rc<[ColumnRef]>,
+    },
+    /// The rows for which `predicate` is true
+    Filter {
+        input: Box<LogicalPlan>,
+        predicate: Expression,
+    },
+    /// Pairs of rows from both sides, each the left columns followed by the
+    /// right columns
+    Join {
+        left: Box<LogicalPlan>,
+        right: Box<LogicalPlan>,
+        kind: JoinKind,
+        on: Option<Expression>,
+        columns: Rc<[ColumnRef]>,
+    },
+    /// One row per group, the input columns of the group's first row
+    /// followed by the result of every aggregate call
+    Aggregate {
+        input: Box<LogicalPlan>,
+        group_by: Vec<Expression>,
+        aggregates: Vec<FunctionCall>,
+        columns: Rc<[ColumnRef]>,
+    },
+    /// The input rows, each followed by the result of every window function
+    Window {
+        input: Box<LogicalPlan>,
+        windows: Vec<WindowFunction>,
+        columns: Rc<[ColumnRef]>,
+    },
+    /// One output column per expression
+    Project {
+        input: Box<LogicalPlan>,
+        exprs: Vec<Expression>,
+        columns: Rc<[ColumnRef]>,
+    },
+    Sort {
+        input: Box<LogicalPlan>,
+        order_by: Vec<OrderByItem>,
+    },
+    /// The first row of every set of rows with the same values for `on`, or
+    /// for every column without it
+    Distinct {
+        input: Box<LogicalPlan>,
+        on: Option<Vec<Expression>>,
+    },
+    /// `LIMIT` and `OFFSET`, expressions that can not reference any columns
+    Limit {
+        input: Box<LogicalPlan>,
+        limit: Option<Expression>,
+        offset: Option<Expression>,
+    },
+    /// The rows of two plans with the same number of columns combined by a
+    /// set operator, named after the left side
+    SetOperation {
+        op: SetOperator,
+        left: Box<LogicalPlan>,
+        right: Box<LogicalPlan>,
+    },
+}
+
+impl LogicalPlan {
+    /// The columns of every row this plan produces
+    pub fn columns(&self) -> Rc<[ColumnRef]> {
+        match self {
+            Self::Scan { columns, .. }
+            | Self::IndexScan { columns, .. }
+            | Self::Values { columns, .. }
+            | Self::Alias { columns, .. }
+            | Self::Join { columns, .. }
+            | Self::Aggregate { columns, .. }
+            | Self::Window { columns, .. }
+            | Self::Project { columns, .. } => columns.clone(),
+            Self::Filter { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Distinct { input, .. }
+            | Self::Limit { input, .. } => input.columns(),
+            Self::SetOperation { left, .. } => left.columns(),
+        }
+    }
+
+    /// The description of this node alone, without its inputs
+    pub fn describe(&self) -> String {
+        struct Node<'a>(&'a LogicalPlan);
+
+        impl fmt::Display for Node<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_node(f)
+            }
+        }
+
+        Node(self).to_string()
+    }
+
+    /// The names of the output columns
+    pub fn names(&self) -> Rc<[String]> {
+        self.columns().iter().map(|col| col.name.clone()).collect()
+    }
+
+    /// The plans this one reads its rows from
+    pub fn inputs(&self) -> Vec<&LogicalPlan> {
+        match self {
+            Self::Scan { .. } | Self::IndexScan { .. } | Self::Values { .. } => vec![],
+            Self::Alias { input, .. }
+            | Self::Filter { input, .. }
+            | Self::Aggregate { input, .. }
+            | Self::Window { input, .. }
+            | Self::Project { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Distinct { input, .. }
+            | Self::Limit { input, .. } => vec![input],
+            Self::Join { left, right, .. } | Self::SetOperation { left, right, .. } => {
+                vec![left, right]
+            }
+        }
+    }
+}
+
+impl LogicalPlan {
+    /// Rebuild this node with every input replaced by `f` of it
+    pub(crate) fn map_inputs(self, mut f: impl FnMut(LogicalPlan) -> LogicalPlan) -> Self {
+        let mut map = |input: Box<LogicalPlan>| Box::new(f(*input));
+        match self {
+            plan @ (Self::Scan { .. } | Self::IndexScan { .. } | Self::Values { .. }) => plan,
+            Self::Alias {
+                input,
+                alias,
+                columns,
+            } => Self::Alias {
+                input: map(input),
+                alias,
+                columns,
+            },
+            Self::Filter { input, predicate } => Self::Filter {
+                input: map(input),
+                predicate,
+            },
+            Self::Join {
+                left,
+                right,
+                kind,
+                on,
+                columns,
+            } => Self::Join {
+                left: map(left),
+                right: map(right),
+                kind,
+                on,
+                columns,
+            },
+            Self::Aggregate {
+                input,
+                group_by,
+                aggregates,
+                columns,
+            } => Self::Aggregate {
+                input: map(input),
+                group_by,
+                aggregates,
+                columns,
+            },
+            Self::Window {
+                input,
+                windows,
+                columns,
+            } => Self::Window {
+                input: map(input),
+                windows,
+                columns,
+            },
+            Self::Project {
+                input,
+                exprs,
+                columns,
+            } => Self::Project {
+                input: map(input),
+                exprs,
+                columns,
+            },
+            Self::Sort { input, order_by } => Self::Sort {
+                input: map(input),
+                order_by,
+            },
+            Self::Distinct { input, on } => Self::Distinct {
+                input: map(input),
+                on,
+            },
+            Self::Limit {
+                input,
+                limit,
+                offset,
+            } => Self::Limit {
+                input: map(input),
+                limit,
+                offset,
+            },
+            Self::SetOperation { op, left, right } => Self::SetOperation {
+                op,
+                left: map(left),
+                right: map(right),
+            },
+        }
+    }
+
+    /// The expressions this node evaluates for each row
+    ///
+    /// Aggregate calls and window functions are left out like in
+    /// [`LogicalPlan::expressions_mut`]
+    pub(crate) fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Self::Scan { .. }
+            | Self::IndexScan { .. }
+            | Self::Values { .. }
+            | Self::Alias { .. }
+            | Self::Window { .. }
+            | Self::SetOperation { .. } => vec![],
+            Self::Filter { predicate, .. } => vec![predicate],
+            Self::Join { on, .. } => on.iter().collect(),
+            Self::Aggregate { group_by, .. } => group_by.iter().collect(),
+            Self::Project { exprs, .. } => exprs.iter().collect(),
+            Self::Sort { order_by, .. } => order_by.iter().map(|item| &item.expr).collect(),
+            Self::Distinct { on, .. } => on.iter().flatten().collect(),
+            Self::Limit { limit, offset, .. } => limit.iter().chain(offset).collect(),
+        }
+    }
+
+    /// The expressions this node evaluates for each row, to rewrite them
+    ///
+    /// Aggregate calls and window functions are left out, the nodes above
+    /// refer to their results by how they are written
+    pub(crate) fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Self::Scan { .. }
+            | Self::IndexScan { .. }
+            | Self::Values { .. }
+            | Self::Alias { .. }
+            | Self::Window { .. }
+            | Self::SetOperation { .. } => vec![],
+            Self::Filter { predicate, .. } => vec![predicate],
+            Self::Join { on, .. } => on.iter_mut().collect(),
+            Self::Aggregate { group_by, .. } => group_by.iter_mut().collect(),
+            Self::Project { exprs, .. } => exprs.iter_mut().collect(),
+            Self::Sort { order_by, .. } => order_by.iter_mut().map(|item| &mut item.expr).collect(),
+            Self::Distinct { on, .. } => on.iter_mut().flatten().collect(),
+            Self::Limit { limit, offset, .. } => limit.iter_mut().chain(offset).collect(),
+        }
+    }
+}
+
+/// Write the names of `columns` in parentheses
+fn fmt_columns(f: &mut fmt::Formatter<'_>, columns: &[ColumnRef]) -> fmt::Result {
+    let names: Vec<_> = columns.iter().map(|col| &col.name).collect();
+    write!(f, " (")?;
+    fmt_list(f, &names)?;
+    write!(f, ")")
+}
+
+/// `<table> [AS <alias>]`
+fn fmt_table(f: &mut fmt::Formatter<'_>, table: &str, alias: &str) -> fmt::Result {
+    write!(f, "{table}")?;
+    if alias != table {
+        write!(f, " AS {alias}")?;
+    }
+    Ok(())
+}
+
+impl LogicalPlan {
+    /// Write the description of this node alone, on a single line
+    fn fmt_node(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scan {
+                table,
+                alias,
+                columns,
+            } => {
+                write!(f, "Scan: ")?;
+                fmt_table(f, table, alias)?;
+                fmt_columns(f, columns)
+            }
+            Self::IndexScan {
+                table,
+                alias,
+                columns,
+                column,
+                value,
+            } => {
+                write!(f, "IndexScan: ")?;
+                fmt_table(f, table, alias)?;
+                write!(f, " ON {column} = {value}")?;
+                fmt_columns(f, columns)
+            }
+            Self::Values { name, columns, .. } => {
+                write!(f, "Values: {name}")?;
+                fmt_columns(f, columns)
+            }
+            Self::Alias { alias, columns, .. } => {
+                write!(f, "Alias: {alias}")?;
+                fmt_columns(f, columns)
+            }
+            Self::Filter { predicate, .. } => write!(f, "Filter: {predicate}"),
+            Self::Join { kind, on, .. } => {
+                let kind = match kind {
+                    JoinKind::Inner => "INNER",
+                    JoinKind::Left => "LEFT",
+                    JoinKind::Cross => "CROSS",
+                };
+                write!(f, "Join: {kind}")?;
+                if let Some(on) = on {
+                    write!(f, " ON {on}")?;
+                }
+                Ok(())
+            }
+            Self::Aggregate {
+                group_by,
+                aggregates,
+                ..
+            } => {
+                write!(f, "Aggregate: ")?;
+                fmt_list(f, aggregates)?;
+                if !group_by.is_empty() {
+                    write!(
+                        f,
+                        "{}GROUP BY ",
+                        if aggregates.is_empty() { "" } else { " " }
+                    )?;
+                    fmt_list(f, group_by)?;
+                }
+                Ok(())
+            }
+            Self::Window { windows, .. } => {
+                write!(f, "Window: ")?;
+                fmt_list(f, windows)
+            }
+            Self::Project { exprs, columns, .. } => {
+                write!(f, "Project: ")?;
+                for (i, (expr, column)) in exprs.iter().zip(columns.iter()).enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{expr}")?;
+                    if column.name != column_name(expr) {
+                        write!(f, " AS {}", column.name)?;
+                    }
+                }
+                Ok(())
+            }
+            Self::Sort { order_by, .. } => {
+                write!(f, "Sort: ")?;
+                fmt_list(f, order_by)
+            }
+            Self::Distinct { on: None, .. } => write!(f, "Distinct"),
+            Self::Distinct { on: Some(on), .. } => {
+                write!(f, "Distinct: ON (")?;
+                fmt_list(f, on)?;
+                write!(f, ")")
+            }
+            Self::Limit { limit, offset, .. } => {
+                write!(f, "Limit:")?;
+                if let Some(limit) = limit {
+                    write!(f, " {limit}")?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, " OFFSET {offset}")?;
+                }
+                Ok(())
+            }
+            Self::SetOperation { op, .. } => write!(f, "SetOperation: {op}"),
+        }
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        self.fmt_node(f)?;
+        for input in self.inputs() {
+            writeln!(f)?;
+            input.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// One node per line, inputs indented below the node reading them
+impl fmt::Display for LogicalPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+
+use sql_mini_parser::{
+    ast::SqlQuery,
+    commands::{
+        CompoundSelect, Distinct, SelectField, SelectStatement, SqlTypeInfo, TableReference,
+    },
+    expr::{BinaryOperator, ColumnRef, Expression, Parameter},
+    value::Value,
+};
+
+use crate::{
+    error::QueryExecutionError,
+    plan::LogicalPlan,
+    table::{self, Table},
+};
+
+/// The largest parameter number, like SQLite
+const MAX_PARAMETERS: usize = 32766;
+
+/// A statement parsed and planned once by [`crate::Execution::prepare`], run
+/// any number of times with different values bound to its parameters
+///
+/// Parameters are numbered like SQLite: `?<n>` is parameter n, `?` is one
+/// past the largest number before it and every `:<name>` with the same name
+/// is one parameter, numbered like `?` where the name first appears
+#[derive(Debug)]
+pub struct PreparedStatement {
+    pub(crate) query: SqlQuery,
+    /// The name of each parameter, `None` for `?` and `?<n>`
+    names: Vec<Option<String>>,
+    /// The type of the column each parameter is compared to or inserted into
+    types: Vec<Option<SqlTypeInfo>>,
+    values: Vec<Option<Value>>,
+    /// False when binding the query materializes CTEs, so the plan holds
+    /// rows that have to be computed again for every run
+    pub(crate) reusable: bool,
+    /// The plan and the catalog version it was made for
+    pub(crate) plan: Option<(u64, LogicalPlan)>,
+}
+
+impl PreparedStatement {
+    pub(crate) fn new(
+        mut query: SqlQuery,
+        tables: &HashMap<String, Table>,
+    ) -> Result<Self, QueryExecutionError> {
+        let mut numbering = Numbering {
+            tables,
+            scopes: Vec::new(),
+            names: Vec::new(),
+            types: Vec::new(),
+            error: None,
+        };
+        numbering.query(&mut query);
+        if let Some(error) = numbering.error {
+            return Err(error);
+        }
+
+        let reusable = match &query {
+            SqlQuery::Select(select) => !materializes(select),
+            _ => false,
+        };
+        Ok(Self {
+            query,
+            values: vec![None; numbering.names.len()],
+            names: numbering.names,
+            types: numbering.types,
+            reusable,
+            plan: None,
+        })
+    }
+
+    /// The largest parameter number
+    pub fn parameter_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The number of the parameter called `name`, with or without its `:`
+    pub fn parameter_index(&self, name: &str) -> Option<usize> {
+        let name = name.strip_prefix(':').unwrap_or(name);
+        self.names
+            .iter()
+            .position(|slot| slot.as_deref() == Some(name))
+            .map(|i| i + 1)
+    }
+
+    /// The type of the column each parameter is compared to or inserted into
+    pub(crate) fn parameter_types(&self) -> &[Option<SqlTypeInfo>] {
+        &self.types
+    }
+
+    /// Bind `value` to parameter `index`, counting from 1
+    ///
+    /// A parameter compared to or inserted into a column only takes values
+    /// of the column's type or NULL
+    pub fn bind(&mut self, index: usize, value: Value) -> Result<(), QueryExecutionError> {
+        if index == 0 || index > self.values.len() {
+            return Err(QueryExecutionError::UnknownParameter(format!("?{index}")));
+        }
+        if let Some(expected) = self.types[index - 1] {
+            if !table::accepts(expected, &value) {
+                return Err(QueryExecutionError::ParameterTypeMismatch {
+                    parameter: self.parameter_name(index),
+                    expected,
+                    value,
+                });
+            }
+        }
+        self.values[index - 1] = Some(value);
+        Ok(())
+    }
+
+    /// Bind `value` to the parameter called `name`, see [`Self::bind`]
+    pub fn bind_named(&mut self, name: &str, value: Value) -> Result<(), QueryExecutionError> {
+        let index = self
+            .parameter_index(name)
+            .ok_or_else(|| QueryExecutionError::UnknownParameter(name.to_owned()))?;
+        self.bind(index, value)
+    }
+
+    /// Forget every bound value
+    pub fn clear_bindings(&mut self) {
+        self.values.iter_mut().for_each(|value| *value = None);
+    }
+
+    /// The bound values in parameter order, fails if one is missing
+    pub(crate) fn parameters(&self) -> Result<Vec<Value>, QueryExecutionError> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                value.clone().ok_or_else(|| {
+                    QueryExecutionError::UnboundParameter(self.parameter_name(i + 1))
+                })
+            })
+            .collect()
+    }
+
+    fn parameter_name(&self, index: usize) -> String {
+        match &self.names[index - 1] {
+            Some(name) => format!(":{name}"),
+            None => format!("?{index}"),
+        }
+    }
+}
+
+/// Whether binding `query` materializes a CTE
+fn materializes(query: &CompoundSelect) -> bool {
+    fn from(table: &TableReference) -> bool {
+        match table {
+            TableReference::Table { .. } => false,
+            TableReference::Subquery { query, .. } => materializes(query),
+            TableReference::Join { left, right, .. } => from(left) || from(right),
+        }
+    }
+
+    query.with.is_some()
+        || std::iter::once(&query.first)
+            .chain(query.compounds.iter().map(|term| &term.select))
+            .any(|select| from(&select.from))
+}
+
+/// Numbers the parameters of a statement in the order they appear and finds
+/// the type of the column each is compared to or inserted into
+struct Numbering<'a> {
+    tables: &'a HashMap<String, Table>,
+    /// The stored tables of each enclosing select and their names in it,
+    /// innermost last
+    scopes: Vec<Vec<(String, &'a Table)>>,
+    names: Vec<Option<String>>,
+    types: Vec<Option<SqlTypeInfo>>,
+    error: Option<QueryExecutionError>,
+}
+
+impl<'a> Numbering<'a> {
+    fn query(&mut self, query: &mut SqlQuery) {
+        match query {
+            SqlQuery::Select(select) => self.select(select),
+            SqlQuery::Explain(explain) => self.select(&mut explain.query),
+            SqlQuery::Insert(insert) => {
+                let columns: Vec<_> = self
+                    .tables
+                    .get(&insert.table)
+                    .map(|table| table.columns().iter().map(|col| col.type_info).collect())
+                    .unwrap_or_default();
+                for (value, column) in insert
+                    .values
+                    .iter_mut()
+                    .zip(columns.into_iter().map(Some).chain(std::iter::repeat(None)))
+                {
+                    self.expression(value);
+                    if let (Expression::Parameter(Parameter::Numbered(n)), Some(column)) =
+                        (value, column)
+                    {
+                        self.expect(*n, column);
+                    }
+                }
+            }
+            SqlQuery::Create(_)
+            | SqlQuery::CreateIndex(_)
+            | SqlQuery::Analyze(_)
+            | SqlQuery::Copy(_) => {}
+        }
+    }
+
+    fn select(&mut self, query: &mut CompoundSelect) {
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.ctes {
+                self.select(&mut cte.query);
+            }
+        }
+        self.core(&mut query.first);
+        for term in &mut query.compounds {
+            self.core(&mut term.select);
+        }
+
+        // ORDER BY can use the columns of the first select
+        self.scopes.push(tables_in(self.tables, &query.first.from));
+        for item in &mut query.order_by {
+            self.expression(&mut item.expr);
+        }
+        self.scopes.pop();
+        for expr in query.limit.iter_mut().chain(&mut query.offset) {
+            self.expression(expr);
+        }
+    }
+
+    fn core(&mut self, select: &mut SelectStatement) {
+        self.scopes.push(tables_in(self.tables, &select.from));
+        if let Some(Distinct::On(exprs)) = &mut select.distinct {
+            exprs.iter_mut().for_each(|expr| self.expression(expr));
+        }
+        for field in &mut select.fields {
+            if let SelectField::Expression { expr, .. } = field {
+                self.expression(expr);
+            }
+        }
+        self.from(&mut select.from);
+        for expr in select.where_clause.iter_mut().chain(&mut select.group_by) {
+            self.expression(expr);
+        }
+        self.scopes.pop();
+    }
+
+    fn from(&mut self, table: &mut TableReference) {
+        match table {
+            TableReference::Table { .. } => {}
+            TableReference::Subquery { query, .. } => self.select(query),
+            TableReference::Join {
+                left, right, on, ..
+            } => {
+                self.from(left);
+                self.from(right);
+                if let Some(on) = on {
+                    self.expression(on);
+                }
+            }
+        }
+    }
+
+    /// Replace every parameter in `expr` with its number
+    fn expression(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::Parameter(parameter) => {
+                *parameter = Parameter::Numbered(self.number(parameter));
+            }
+            Expression::Subquery(query) | Expression::Exists(query) => self.select(query),
+            Expression::InSubquery { expr, subquery, .. } => {
+                self.expression(expr);
+                self.select(subquery);
+            }
+            _ => {
+                for child in expr.children_mut() {
+                    self.expression(child);
+                }
+                self.infer(expr);
+            }
+        }
+    }
+
+    fn number(&mut self, parameter: &Parameter) -> usize {
+        let named = match parameter {
+            Parameter::Named(name) => {
+                match self.names.iter().position(|n| n.as_ref() == Some(name)) {
+                    Some(i) => return i + 1,
+                    None => Some(name.clone()),
+                }
+            }
+            _ => None,
+        };
+        let n = match parameter {
+            Parameter::Numbered(n) => *n,
+            _ => self.names.len() + 1,
+        };
+        if n > MAX_PARAMETERS {
+            self.error
+                .get_or_insert(QueryExecutionError::UnknownParameter(format!("?{n}")));
+            return n;
+        }
+        if n > self.names.len() {
+            self.names.resize(n, None);
+            self.types.resize(n, None);
+        }
+        if named.is_some() {
+            self.names[n - 1] = named;
+        }
+        n
+    }
+
+    /// Give the parameters `expr` compares to a column the column's type
+    fn infer(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Binary {
+                left,
+                op:
+                    BinaryOperator::Eq
+                    | BinaryOperator::NotEq
+                    | BinaryOperator::Lt
+                    | BinaryOperator::LtEq
+                    | BinaryOperator::Gt
+                    | BinaryOperator::GtEq,
+                right,
+            } => {
+                self.compare(left, right);
+                self.compare(right, left);
+            }
+            Expression::Between {
+                expr, low, high, ..
+            } => {
+                self.compare(expr, low);
+                self.compare(expr, high);
+            }
+            Expression::InList { expr, list, .. } => {
+                for item in list {
+                    self.compare(expr, item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn compare(&mut self, column: &Expression, parameter: &Expression) {
+        if let (Expression::Column(column), Expression::Parameter(Parameter::Numbered(n))) =
+            (column, parameter)
+        {
+            if let Some(column) = self.column_type(column) {
+                self.expect(*n, column);
+            }
+        }
+    }
+
+    /// The type of `column` in the innermost select that has it
+    fn column_type(&self, column: &ColumnRef) -> Option<SqlTypeInfo> {
+        self.scopes
+            .iter()
+            .rev()
+            .flatten()
+            .filter(|(name, _)| column.table.as_ref().is_none_or(|table| table == name))
+            .find_map(|(_, table)| {
+                table
+                    .columns()
+                    .iter()
+                    .find(|col| col.name == column.name)
+                    .map(|col| col.type_info)
+            })
+    }
+
+    /// Parameter `n` takes values of type `column`, the first column found
+    /// for a parameter wins
+    fn expect(&mut self, n: usize, column: SqlTypeInfo) {
+        if let Some(expected) = self.types.get_mut(n - 1) {
+            expected.get_or_insert(column);
+        }
+    }
+}
+
+/// The stored tables read by `from` and their names in the query
+fn tables_in<'a>(
+    tables: &'a HashMap<String, Table>,
+    from: &TableReference,
+) -> Vec<(String, &'a Table)> {
+    match from {
+        TableReference::Table { name, alias } => tables
+            .get(name)
+            .map(|table| (alias.clone().unwrap_or_else(|| name.clone()), table))
+            .into_iter()
+            .collect(),
+        TableReference::Subquery { .. } => vec![],
+        TableReference::Join { left, right, .. } => {
+            let mut found = tables_in(tables, left);
+            found.extend(tables_in(tables, right));
+            found
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{ast::SqlQuery, value::Value};
+
+    use crate::{
+        error::QueryExecutionError,
+        tests::{execution, num, run_all},
+        ExecResponse,
+    };
+
+    fn rows(response: ExecResponse) -> Vec<Vec<Value>> {
+        match response {
+            ExecResponse::Select(result) => {
+                result.into_iter().map(|row| row.into_values()).collect()
+            }
+            response => panic!("expected rows, got {response}"),
+        }
+    }
+
+    const TABLES: &[&str] = &[
+        "create table people (name string, age int);",
+        "insert into people values 'ann', 30;",
+        "insert into people values 'bob', 20;",
+    ];
+
+    #[test]
+    fn test_numbering() {
+        let exec = execution(TABLES);
+        let statement = exec
+            .prepare("select name from people where age > ? and name = :name or age < ?5 or age = ? or name <> :name;")
+            .unwrap();
+
+        assert_eq!(statement.parameter_count(), 6);
+        assert_eq!(statement.parameter_index(":name"), Some(2));
+        assert_eq!(statement.parameter_index("name"), Some(2));
+        assert_eq!(statement.parameter_index("age"), None);
+        let SqlQuery::Select(select) = &statement.query else {
+            panic!("expected a select");
+        };
+        assert_eq!(
+            select.to_string(),
+            "SELECT name FROM people WHERE age > ?1 AND name = ?2 OR age < ?5 OR age = ?6 OR name <> ?2"
+        );
+    }
+
+    #[test]
+    fn test_execute() {
+        let mut exec = execution(TABLES);
+        let mut insert = exec.prepare("insert into people values :name, ?;").unwrap();
+        insert
+            .bind_named("name", Value::String("cid".into()))
+            .unwrap();
+        insert.bind(2, num(40)).unwrap();
+        exec.execute(&mut insert).unwrap();
+
+        let mut select = exec
+            .prepare("select name from people where age between ? and ?2 + 10 order by name;")
+            .unwrap();
+        select.bind(1, num(25)).unwrap();
+        select.bind(2, num(30)).unwrap();
+        assert_eq!(
+            rows(exec.execute(&mut select).unwrap()),
+            vec![
+                vec![Value::String("ann".into())],
+                vec![Value::String("cid".into())]
+            ]
+        );
+
+        // the plan is reused with new values and made again after the schema changes
+        select.bind(1, num(0)).unwrap();
+        select.bind(2, num(40)).unwrap();
+        assert_eq!(rows(exec.execute(&mut select).unwrap()).len(), 3);
+        run_all(&mut exec, &["create index people_age on people (age);"]);
+        assert_eq!(rows(exec.execute(&mut select).unwrap()).len(), 3);
+
+        select.clear_bindings();
+        assert!(matches!(
+            exec.execute(&mut select),
+            Err(QueryExecutionError::UnboundParameter(name)) if name == "?1"
+        ));
+    }
+
+    #[test]
+    fn test_index_lookup() {
+        let mut exec = execution(TABLES);
+        run_all(&mut exec, &["create index people_age on people (age);"]);
+        let mut select = exec
+            .prepare("select name from people where age = ?;")
+            .unwrap();
+        let plan = select.plan.as_ref().map(|(_, plan)| plan.to_string());
+        assert!(plan.unwrap().contains("IndexScan: people ON age = ?1"));
+
+        for (age, expected) in [
+            (num(20), vec![vec![Value::from("bob")]]),
+            (Value::Null, vec![]),
+        ] {
+            select.bind(1, age).unwrap();
+            assert_eq!(rows(exec.execute(&mut select).unwrap()), expected);
+        }
+
+        let mut explain = exec
+            .prepare("explain select name from people where age = ?;")
+            .unwrap();
+        explain.bind(1, num(30)).unwrap();
+        let plan = exec.execute(&mut explain).unwrap().to_string();
+        assert!(plan.contains("IndexScan: people ON age = ?1"), "{plan}");
+    }
+
+    #[test]
+    fn test_bind_errors() {
+        let exec = execution(TABLES);
+        let mut statement = exec
+            .prepare("select name from people p where p.age = ? and ? = name and ? > 1;")
+            .unwrap();
+
+        assert!(matches!(
+            statement.bind(1, Value::String("old".into())),
+            Err(QueryExecutionError::ParameterTypeMismatch { .. })
+        ));
+        assert_eq!(
+            statement.bind(2, num(1)).unwrap_err().to_string(),
+            "Parameter ?2 is compared to or inserted into a STRING column, got 1"
+        );
+        statement.bind(1, Value::Null).unwrap();
+        statement.bind(3, Value::String("any".into())).unwrap();
+        assert!(matches!(
+            statement.bind(4, num(1)),
+            Err(QueryExecutionError::UnknownParameter(_))
+        ));
+        assert!(matches!(
+            statement.bind_named("age", num(1)),
+            Err(QueryExecutionError::UnknownParameter(_))
+        ));
+
+        let mut insert = exec.prepare("insert into people values ?, ?;").unwrap();
+        assert!(matches!(
+            insert.bind(2, Value::String("old".into())),
+            Err(QueryExecutionError::ParameterTypeMismatch { .. })
+        ));
+        assert!(matches!(
+            exec.prepare("select name from people; select age from people;"),
+            Err(QueryExecutionError::Syntax(_))
+        ));
+    }
+}
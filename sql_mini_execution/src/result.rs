@@ -0,0 +1,54 @@
+use std::rc::Rc;
+
+use sql_mini_parser::{expr::ColumnRef, value::Value};
+
+use crate::row::Row;
+
+/// The rows produced by a query
+#[derive(Debug, Clone)]
+pub struct ResultSet {
+    /// The names of the output columns
+    pub columns: Rc<[String]>,
+    rows: Vec<Row>,
+}
+
+impl ResultSet {
+    pub fn new(columns: Rc<[String]>, rows: Vec<Row>) -> Self {
+        Self { columns, rows }
+    }
+
+    /// Build a result from bare values, one `Vec` per row
+    pub(crate) fn from_values(
+        columns: Rc<[String]>,
+        rows: impl IntoIterator<Item = Vec<Value>>,
+    ) -> Self {
+        let refs: Rc<[ColumnRef]> = columns.iter().map(ColumnRef::new).collect();
+        let rows = rows
+            .into_iter()
+            .map(|values| Row::new(refs.clone(), values))
+            .collect();
+        Self { columns, rows }
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+impl IntoIterator for ResultSet {
+    type Item = Row;
+
+    type IntoIter = std::vec::IntoIter<Row>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
@@ -1,24 +1,57 @@
-use std::collections::HashMap;
+use std::rc::Rc;
 
-use sql_mini_parser::value::Value;
+use serde::Deserialize;
+use sql_mini_parser::{expr::ColumnRef, value::Value};
 
 use crate::error::QueryExecutionError;
 
+/// A row of values, along with the (possibly table qualified) columns they
+/// belong to
 #[derive(Debug, Clone)]
-pub struct Row<'a> {
-    id: usize,
-    data: HashMap<&'a String, &'a Value>,
+pub struct Row {
+    columns: Rc<[ColumnRef]>,
+    values: Vec<Value>,
 }
 
-impl<'a> Row<'a> {
-    pub fn new(id: usize, data: HashMap<&'a String, &'a Value>) -> Self {
-        Self { id, data }
+impl Row {
+    pub fn new(columns: Rc<[ColumnRef]>, values: Vec<Value>) -> Self {
+        Self { columns, values }
     }
 
-    pub fn get(&self, column: &String) -> Result<Value, QueryExecutionError> {
-        self.data.get(&column).map_or_else(
-            || Err(QueryExecutionError::ColumnDoesNotExist(column.to_owned())),
-            |val| Ok((*val).clone()),
-        )
+    pub fn get(&self, column: &str) -> Result<Value, QueryExecutionError> {
+        self.columns
+            .iter()
+            .position(|col| col.name == column)
+            .map(|idx| self.values[idx].clone())
+            .ok_or_else(|| QueryExecutionError::ColumnDoesNotExist(column.to_owned()))
+    }
+
+    /// Find a column, only matching the table of the row's columns when
+    /// `column` is qualified
+    pub fn resolve(&self, column: &ColumnRef) -> Option<&Value> {
+        self.columns
+            .iter()
+            .position(|col| {
+                col.name == column.name && (column.table.is_none() || col.table == column.table)
+            })
+            .map(|idx| &self.values[idx])
+    }
+
+    pub fn columns(&self) -> &[ColumnRef] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    /// Decode the row into `T`, struct fields are read from the columns with
+    /// the same name and tuples from the columns in order
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T, QueryExecutionError> {
+        T::deserialize(self)
     }
 }
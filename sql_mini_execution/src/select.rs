@@ -0,0 +1,190 @@
+use sql_mini_parser::commands::{CompoundSelect, SelectStatement};
+
+use crate::{
+    binder::Binder,
+    error::QueryExecutionError,
+    eval::Env,
+    operators,
+    optimizer::{Optimizer, Rule},
+    plan::LogicalPlan,
+    result::ResultSet,
+};
+
+/// Run a query
+///
+/// `env.outer` is the row of the enclosing query when running a subquery,
+/// and `env.correlated` is set if the query references any of its columns
+pub(crate) fn execute_select(
+    query: &CompoundSelect,
+    env: Env,
+) -> Result<ResultSet, QueryExecutionError> {
+    with_plan(query, env, |plan| operators::execute(&plan, env))
+}
+
+/// Bind and optimize `query`, then hand its plan to `run`
+pub(crate) fn with_plan<T>(
+    query: &CompoundSelect,
+    env: Env,
+    run: impl FnOnce(LogicalPlan) -> Result<T, QueryExecutionError>,
+) -> Result<T, QueryExecutionError> {
+    // CTEs are only visible to this query, so drop them however it ends
+    let depth = env.ctx.cte_depth();
+    let result = Binder::new(env)
+        .bind_query(query)
+        .map(|plan| Optimizer::default().apply(plan, env.ctx))
+        .and_then(run);
+    env.ctx.unbind_ctes(depth);
+    result
+}
+
+/// Run a single select of a compound on its own
+pub(crate) fn execute_core(
+    select: &SelectStatement,
+    env: Env,
+) -> Result<ResultSet, QueryExecutionError> {
+    let plan = Binder::new(env).bind_select(select, &[])?;
+    let plan = Optimizer::default().apply(plan, env.ctx);
+    operators::execute(&plan, env)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use sql_mini_parser::value::Value;
+
+    use crate::{
+        tests::{execution, num, run_all},
+        Execution,
+    };
+
+    const TABLES: &[&str] = &[
+        "create table emp (name string, dept string, salary int);",
+        "insert into emp values 'ann', 'eng', 100;",
+        "insert into emp values 'bob', 'eng', 80;",
+        "insert into emp values 'cid', 'ops', 50;",
+        "insert into emp values 'dan', 'ops', 70;",
+        "create table depts (name string);",
+        "insert into depts values 'eng';",
+        "insert into depts values 'hr';",
+    ];
+
+    #[test]
+    fn test_correlated_subquery() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(
+            &mut exec,
+            &["select e.name from emp e where e.salary > \
+               (select avg(i.salary) from emp i where i.dept = e.dept);"],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::String("ann".into())],
+                vec![Value::String("dan".into())]
+            ]
+        );
+
+        let rows = run_all(
+            &mut exec,
+            &["select name from depts d where not exists \
+               (select name from emp where dept = d.name);"],
+        );
+        assert_eq!(rows, vec![vec![Value::String("hr".into())]]);
+    }
+
+    #[test]
+    fn test_uncorrelated_subquery_runs_once() {
+        let mut exec = execution(TABLES);
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        exec.register_scalar_function("tick", 1, move |args| {
+            counter.set(counter.get() + 1);
+            Ok(args[0].clone())
+        });
+
+        let rows = run_all(
+            &mut exec,
+            &["select count(*) from emp where dept in (select tick(name) from depts);"],
+        );
+        assert_eq!(rows, vec![vec![num(2)]]);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_cached_subquery_in_cte_scopes() {
+        let mut exec = execution(TABLES);
+        // the same subquery text reads a different x in each scope
+        let rows = run_all(
+            &mut exec,
+            &["with x as (select name from emp where dept = 'eng') \
+               select name from emp \
+               where name in (select name from x) \
+               or name in (with x as (select name from emp where dept = 'ops') \
+                           select name from x where name in (select name from x)) \
+               order by name;"],
+        );
+        assert_eq!(
+            rows,
+            [["ann"], ["bob"], ["cid"], ["dan"]].map(|row| row.map(Value::from).to_vec())
+        );
+    }
+
+    #[test]
+    fn test_derived_table() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(
+            &mut exec,
+            &["select t.dept, t.total from \
+               (select dept, sum(salary) as total from emp group by dept) as t \
+               where t.total > 150;"],
+        );
+        assert_eq!(rows, vec![vec![Value::String("eng".into()), num(180)]]);
+    }
+
+    #[test]
+    fn test_distinct() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(
+            &mut exec,
+            &["select distinct dept from emp order by dept desc;"],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::String("ops".into())],
+                vec![Value::String("eng".into())]
+            ]
+        );
+
+        // the first row of each department in ORDER BY order
+        let rows = run_all(
+            &mut exec,
+            &["select distinct on (dept) name from emp order by dept, salary;"],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::String("bob".into())],
+                vec![Value::String("cid".into())]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_distinct_ignores_scale() {
+        let mut exec = Execution::new();
+        let rows = run_all(
+            &mut exec,
+            &[
+                "create table nums (n int);",
+                "insert into nums values 1;",
+                "insert into nums values 1.0;",
+                "insert into nums values 1.00;",
+                "insert into nums values 2;",
+                "select distinct n from nums;",
+            ],
+        );
+        assert_eq!(rows, vec![vec![num(1)], vec![num(2)]]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Statistics about the contents of a table, collected by `ANALYZE` for the
+//! planner to estimate how many rows its choices produce
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use bigdecimal::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use sql_mini_parser::value::Value;
+
+use crate::{eval::compare_values, table::Table};
+
+/// Number of buckets of a column's histogram
+const HISTOGRAM_BUCKETS: usize = 8;
+
+/// Statistics of a whole table, as of the last `ANALYZE`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableStats {
+    pub row_count: u64,
+    /// Statistics of every column, by name
+    pub columns: HashMap<String, ColumnStats>,
+}
+
+/// Statistics of the values of one column
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    /// Estimated number of distinct values besides NULL
+    pub distinct: u64,
+    pub nulls: u64,
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    /// Bounds of buckets holding about as many values each, the first is the
+    /// smallest value and the last the largest
+    pub histogram: Vec<Value>,
+}
+
+impl TableStats {
+    pub(crate) fn collect(table: &Table) -> Self {
+        let columns = table
+            .columns()
+            .iter()
+            .map(|column| {
+                let values = table.values(&column.name);
+                (column.name.clone(), ColumnStats::collect(values))
+            })
+            .collect();
+
+        Self {
+            row_count: table.len() as u64,
+            columns,
+        }
+    }
+}
+
+impl ColumnStats {
+    fn collect<'a>(values: impl Iterator<Item = &'a Value>) -> Self {
+        let mut sketch = HyperLogLog::default();
+        let mut nulls = 0;
+        let mut sorted = Vec::new();
+        for value in values {
+            if value.is_null() {
+                nulls += 1;
+                continue;
+            }
+            sketch.add(value);
+            sorted.push(value);
+        }
+        sorted.sort_by(|l, r| compare_values(l, r));
+
+        let buckets = HISTOGRAM_BUCKETS.min(sorted.len());
+        let histogram = match buckets {
+            0 => vec![],
+            _ => (0..=buckets)
+                .map(|i| sorted[i * (sorted.len() - 1) / buckets].clone())
+                .collect(),
+        };
+
+        Self {
+            distinct: sketch.estimate(),
+            nulls,
+            min: sorted.first().cloned().cloned(),
+            max: sorted.last().cloned().cloned(),
+            histogram,
+        }
+    }
+
+    /// The estimated fraction of the non NULL values below `value`, `None`
+    /// without a histogram
+    pub(crate) fn fraction_below(&self, value: &Value) -> Option<f64> {
+        let buckets = self.histogram.len().checked_sub(1).filter(|&n| n > 0)?;
+        let below: f64 = self
+            .histogram
+            .windows(2)
+            .map(|bounds| {
+                let (low, high) = (&bounds[0], &bounds[1]);
+                if compare_values(value, low).is_le() {
+                    0.0
+                } else if compare_values(value, high).is_gt() {
+                    1.0
+                } else {
+                    interpolate(low, high, value).unwrap_or(0.5)
+                }
+            })
+            .sum();
+        Some(below / buckets as f64)
+    }
+}
+
+/// Where `value` falls between `low` and `high`, if they are all numbers
+fn interpolate(low: &Value, high: &Value, value: &Value) -> Option<f64> {
+    let number = |value: &Value| match value {
+        Value::Number(n) => n.to_f64(),
+        _ => None,
+    };
+    let (low, high, value) = (number(low)?, number(high)?, number(value)?);
+    (high > low).then(|| (value - low) / (high - low))
+}
+
+/// Number of bits of a hash choosing a register
+const HLL_BITS: u32 = 10;
+const HLL_REGISTERS: usize = 1 << HLL_BITS;
+
+/// A HyperLogLog sketch, estimating the number of distinct values it was
+/// given in a fixed amount of memory
+struct HyperLogLog {
+    /// The longest run of leading zeros seen by each register, plus one
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    fn add(&mut self, value: &Value) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let register = (hash >> (64 - HLL_BITS)) as usize;
+        let rank = ((hash << HLL_BITS).leading_zeros() + 1).min(64 - HLL_BITS + 1) as u8;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // few values leave registers empty, counting those is more precise
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        let estimate = if estimate <= 2.5 * m && empty > 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            estimate
+        };
+        estimate.round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::value::Value;
+
+    use crate::{
+        tests::{num, run_all},
+        Execution,
+    };
+
+    #[test]
+    fn test_analyze() {
+        let mut exec = Execution::new();
+        run_all(
+            &mut exec,
+            &["create table t (n int, s string);", "analyze t;"],
+        );
+        assert_eq!(exec.tables["t"].stats().unwrap().row_count, 0);
+
+        for i in 0..1000 {
+            let s = if i % 10 == 0 {
+                "null".into()
+            } else {
+                format!("'{}'", i % 3)
+            };
+            run_all(&mut exec, &[&format!("insert into t values {i}, {s};")]);
+        }
+        run_all(&mut exec, &["analyze;"]);
+
+        let stats = exec.tables["t"].stats().unwrap();
+        assert_eq!(stats.row_count, 1000);
+
+        let n = &stats.columns["n"];
+        assert!((950..=1050).contains(&n.distinct), "{}", n.distinct);
+        assert_eq!(
+            (n.min.clone(), n.max.clone()),
+            (Some(num(0)), Some(num(999)))
+        );
+        assert_eq!(n.histogram.len(), 9);
+        let below = n.fraction_below(&num(250)).unwrap();
+        assert!((below - 0.25).abs() < 0.01, "{below}");
+
+        let s = &stats.columns["s"];
+        assert_eq!((s.distinct, s.nulls), (3, 100));
+        assert_eq!(s.min, Some(Value::String("0".into())));
+    }
+}
@@ -6,10 +6,11 @@ use std::{
 use serde::{Deserialize, Serialize};
 use sql_mini_parser::{
     commands::{Column, SqlTypeInfo},
+    expr::ColumnRef,
     value::Value,
 };
 
-use crate::{error::QueryExecutionError, row::Row};
+use crate::{error::QueryExecutionError, row::Row, stats::TableStats};
 
 // NOTE: map of col name => data
 /// A row stored in a table
@@ -34,6 +35,34 @@ impl ColumnInfo {
             .find(|col| col.name == *column_name)
             .ok_or_else(|| QueryExecutionError::ColumnDoesNotExist(column_name.to_owned()))
     }
+
+    /// References to every column, in definition order, optionally qualified
+    /// with a table name
+    pub fn refs(&self, table: Option<&str>) -> Rc<[ColumnRef]> {
+        self.iter()
+            .map(|col| ColumnRef {
+                table: table.map(str::to_owned),
+                name: col.name.clone(),
+            })
+            .collect()
+    }
+}
+
+/// The ids of the rows holding each value of a column
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Index {
+    name: String,
+    column: String,
+    /// Rebuilt from the rows when a table is loaded, see [`Table::reindex`]
+    #[serde(skip)]
+    entries: HashMap<Value, Vec<usize>>,
+}
+
+impl Index {
+    fn add(&mut self, id: usize, row: &StoredRow) {
+        let value = row.data.get(&self.column).cloned().unwrap_or(Value::Null);
+        self.entries.entry(value).or_default().push(id);
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -43,6 +72,13 @@ pub(crate) struct Table {
 
     /// Column info for all columns in the table
     columns: ColumnInfo,
+
+    #[serde(default)]
+    indexes: Vec<Index>,
+
+    /// As of the last `ANALYZE`, `None` before it
+    #[serde(default)]
+    stats: Option<TableStats>,
 }
 
 impl Table {
@@ -51,7 +87,90 @@ impl Table {
         Self {
             rows: BTreeMap::new(),
             columns: columns.into(),
+            indexes: Vec::new(),
+            stats: None,
+        }
+    }
+
+    /// Index the values of `column`, so rows can be found by value
+    pub fn create_index(&mut self, name: &str, column: &str) -> Result<(), QueryExecutionError> {
+        self.columns.find_column(&column.to_owned())?;
+        let mut index = Index {
+            name: name.to_owned(),
+            column: column.to_owned(),
+            entries: HashMap::new(),
+        };
+        for (id, row) in &self.rows {
+            index.add(*id, row);
         }
+        self.indexes.push(index);
+        Ok(())
+    }
+
+    /// Fill the indexes from the rows, as they are not stored with the table
+    pub fn reindex(&mut self) {
+        for index in &mut self.indexes {
+            index.entries.clear();
+            for (id, row) in &self.rows {
+                index.add(*id, row);
+            }
+        }
+    }
+
+    /// Whether an index called `name` exists
+    pub fn has_index_named(&self, name: &str) -> bool {
+        self.indexes.iter().any(|index| index.name == name)
+    }
+
+    /// Whether `column` is indexed
+    pub fn has_index_on(&self, column: &str) -> bool {
+        self.indexes.iter().any(|index| index.column == column)
+    }
+
+    /// The rows whose `column` holds `value`, `None` if the column is not
+    /// indexed
+    ///
+    /// Values are matched by hash, so numbers that differ only in scale are
+    /// equal and NULL matches NULL
+    pub fn lookup(&self, column: &str, value: &Value) -> Option<IndexIter<'_>> {
+        let index = self.indexes.iter().find(|index| index.column == column)?;
+        // nulls are indexed but equal nothing
+        let ids = match value {
+            Value::Null => &[][..],
+            value => index.entries.get(value).map_or(&[][..], Vec::as_slice),
+        };
+        Some(IndexIter {
+            table: self,
+            ids: ids.iter(),
+            refs: self.columns.refs(None),
+        })
+    }
+
+    /// Collect the statistics of the table's current rows
+    pub fn analyze(&mut self) {
+        self.stats = Some(TableStats::collect(self));
+    }
+
+    pub fn stats(&self) -> Option<&TableStats> {
+        self.stats.as_ref()
+    }
+
+    /// Number of rows stored
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The values of `column` in every row
+    pub fn values<'a>(&'a self, column: &'a str) -> impl Iterator<Item = &'a Value> {
+        static NULL: Value = Value::Null;
+        self.rows
+            .values()
+            .map(move |row| row.data.get(column).unwrap_or(&NULL))
+    }
+
+    /// The table's column definitions
+    pub fn columns(&self) -> &ColumnInfo {
+        &self.columns
     }
 
     /// Insert values (a row) into the table
@@ -66,25 +185,34 @@ impl Table {
         let row = values
             .into_iter()
             .zip(self.columns.iter())
-            .map(|(value, col)| match (col.type_info, value) {
-                (SqlTypeInfo::String, v @ Value::String(_)) => Ok((col.name.to_owned(), v)),
-                (SqlTypeInfo::Int, v @ Value::Number(_)) => Ok((col.name.to_owned(), v)), // TODO: when we add floats make sure number is an int
-                (_, v) => Err(QueryExecutionError::InsertTypeMismatch(col.type_info, v)),
+            .map(|(value, col)| {
+                if accepts(col.type_info, &value) {
+                    Ok((col.name.to_owned(), value))
+                } else {
+                    Err(QueryExecutionError::InsertTypeMismatch(
+                        col.type_info,
+                        value,
+                    ))
+                }
             })
             .collect::<Result<HashMap<_, _>, _>>()?;
 
-        self.rows.insert(id, row.into());
+        let row = row.into();
+        for index in &mut self.indexes {
+            index.add(id, &row);
+        }
+        self.rows.insert(id, row);
         Ok(())
     }
+}
 
-    pub fn select(&self, columns: Vec<String>) -> Result<TableIter, QueryExecutionError> {
-        let selected_columns = columns
-            .into_iter()
-            .map(|name| self.columns.find_column(&name).map(|col| col.clone()))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let col_info = Rc::new(selected_columns.into());
-        Ok(TableIter::new(self.rows.iter(), col_info))
+/// Whether a column of type `type_info` can hold `value`
+pub(crate) fn accepts(type_info: SqlTypeInfo, value: &Value) -> bool {
+    match (type_info, value) {
+        (SqlTypeInfo::String, Value::String(_)) => true,
+        (SqlTypeInfo::Int, Value::Number(_)) => true, // TODO: when we add floats make sure number is an int
+        (_, Value::Null) => true,
+        _ => false,
     }
 }
 
@@ -95,6 +223,8 @@ pub struct TableIter<'a> {
     map_iter: std::collections::btree_map::Iter<'a, usize, StoredRow>,
     /// The columns of the [`Table`]
     pub columns: Rc<ColumnInfo>,
+    /// Column references shared by every produced [`Row`]
+    refs: Rc<[ColumnRef]>,
 }
 
 impl<'a> TableIter<'a> {
@@ -102,28 +232,85 @@ impl<'a> TableIter<'a> {
         map_iter: std::collections::btree_map::Iter<'a, usize, StoredRow>,
         columns: Rc<ColumnInfo>,
     ) -> Self {
-        Self { map_iter, columns }
+        let refs = columns.refs(None);
+        Self {
+            map_iter,
+            columns,
+            refs,
+        }
+    }
+
+    /// Qualify the columns of produced rows with `table`
+    pub fn qualified(mut self, table: &str) -> Self {
+        self.refs = self.columns.refs(Some(table));
+        self
+    }
+
+    /// Only produce `refs`, some of the table's columns qualified with any
+    /// table name
+    pub fn projected(mut self, refs: Rc<[ColumnRef]>) -> Self {
+        self.refs = refs;
+        self
+    }
+
+    /// The columns of every produced row
+    pub fn refs(&self) -> Rc<[ColumnRef]> {
+        self.refs.clone()
     }
 }
 
-impl<'a> Iterator for TableIter<'a> {
-    type Item = Row<'a>;
+/// Build a [`Row`] of the `refs` columns of a stored row
+fn to_row(stored: &StoredRow, refs: &Rc<[ColumnRef]>) -> Row {
+    // columns missing from the stored row were not given on insert
+    let values = refs
+        .iter()
+        .map(|col| stored.data.get(&col.name).cloned().unwrap_or(Value::Null))
+        .collect();
+
+    Row::new(refs.clone(), values)
+}
+
+impl Iterator for TableIter<'_> {
+    type Item = Row;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.map_iter.next().map(|(id, data)| {
-            let projected_data = data
-                .data
-                .iter()
-                .filter_map(|(key, value)| self.columns.find_column(key).ok().map(|_| (key, value)))
-                .collect();
-
-            Row::new(*id, projected_data)
-        })
+        self.map_iter
+            .next()
+            .map(|(_, stored)| to_row(stored, &self.refs))
+    }
+}
+
+/// Iterator of the [`Row`]s of a table found through an index
+#[derive(Debug)]
+pub struct IndexIter<'a> {
+    table: &'a Table,
+    ids: std::slice::Iter<'a, usize>,
+    refs: Rc<[ColumnRef]>,
+}
+
+impl IndexIter<'_> {
+    /// Only produce `refs`, some of the table's columns qualified with any
+    /// table name
+    pub fn projected(mut self, refs: Rc<[ColumnRef]>) -> Self {
+        self.refs = refs;
+        self
+    }
+}
+
+impl Iterator for IndexIter<'_> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        self.table
+            .rows
+            .get(id)
+            .map(|stored| to_row(stored, &self.refs))
     }
 }
 
 impl<'a> IntoIterator for &'a Table {
-    type Item = Row<'a>;
+    type Item = Row;
 
     type IntoIter = TableIter<'a>;
 
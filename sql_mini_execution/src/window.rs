@@ -0,0 +1,364 @@
+use std::{collections::HashMap, rc::Rc};
+
+use bigdecimal::ToPrimitive;
+use sql_mini_parser::{
+    expr::{ColumnRef, Expression, FunctionArgs},
+    value::Value,
+    window::{FrameBound, WindowFunction},
+};
+
+use crate::{
+    error::QueryExecutionError,
+    eval::{Context, Env},
+    functions::{check_arity, Arity},
+    operators::compare_keys,
+    row::Row,
+};
+
+/// Name of the column holding the already computed result of a window function
+pub(crate) fn window_column(window: &WindowFunction) -> String {
+    format!("#{window}")
+}
+
+/// Collect every distinct window function in `expr` into `windows`
+pub(crate) fn collect_windows(expr: &Expression, windows: &mut Vec<WindowFunction>) {
+    match expr {
+        Expression::Window(window) => {
+            if !windows.contains(window) {
+                windows.push(window.as_ref().clone());
+            }
+        }
+        expr => expr
+            .children()
+            .into_iter()
+            .for_each(|child| collect_windows(child, windows)),
+    }
+}
+
+/// What a window function computes for each row
+enum WindowKind {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag,
+    Lead,
+    FirstValue,
+    /// An aggregate function over the rows of the frame
+    Aggregate,
+}
+
+impl WindowKind {
+    fn new(window: &WindowFunction, ctx: &Context) -> Result<Self, QueryExecutionError> {
+        let call = &window.call;
+        let (kind, arity) = match call.name.to_lowercase().as_str() {
+            "row_number" => (Self::RowNumber, Arity::Exact(0)),
+            "rank" => (Self::Rank, Arity::Exact(0)),
+            "dense_rank" => (Self::DenseRank, Arity::Exact(0)),
+            "lag" => (Self::Lag, Arity::Range(1, 3)),
+            "lead" => (Self::Lead, Arity::Range(1, 3)),
+            "first_value" => (Self::FirstValue, Arity::Exact(1)),
+            name if ctx.functions.is_aggregate(name) => return Ok(Self::Aggregate),
+            _ => return Err(QueryExecutionError::FunctionNotFound(call.name.clone())),
+        };
+        let arg_count = match &call.args {
+            FunctionArgs::Wildcard => 0,
+            FunctionArgs::List(args) => args.len(),
+        };
+        check_arity(&call.name, arity, arg_count)?;
+        Ok(kind)
+    }
+}
+
+/// Compute every window function for every row, appending one
+/// [`window_column`] per function to each row
+pub(crate) fn apply_windows(
+    rows: Vec<Row>,
+    windows: &[WindowFunction],
+    env: Env,
+) -> Result<Vec<Row>, QueryExecutionError> {
+    let Some(first) = rows.first() else {
+        return Ok(rows);
+    };
+
+    let results = windows
+        .iter()
+        .map(|window| evaluate_window(&rows, window, env))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let columns: Rc<[ColumnRef]> = first
+        .columns()
+        .iter()
+        .cloned()
+        .chain(windows.iter().map(|w| ColumnRef::new(window_column(w))))
+        .collect();
+
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut values = row.into_values();
+            values.extend(results.iter().map(|result| result[i].clone()));
+            Row::new(columns.clone(), values)
+        })
+        .collect())
+}
+
+/// The position in a sorted partition a frame bound refers to, which may be
+/// outside of the partition
+fn bound_position(bound: FrameBound, pos: usize, len: usize) -> isize {
+    let pos = pos as isize;
+    match bound {
+        FrameBound::UnboundedPreceding => 0,
+        FrameBound::Preceding(n) => pos - n as isize,
+        FrameBound::CurrentRow => pos,
+        FrameBound::Following(n) => pos + n as isize,
+        FrameBound::UnboundedFollowing => len as isize - 1,
+    }
+}
+
+/// The value of a window function for every row, in the order of `rows`
+fn evaluate_window(
+    rows: &[Row],
+    window: &WindowFunction,
+    env: Env,
+) -> Result<Vec<Value>, QueryExecutionError> {
+    let ctx = env.ctx;
+    let kind = WindowKind::new(window, ctx)?;
+    let spec = &window.window;
+    if let Some(frame) = &spec.frame {
+        if frame.start == FrameBound::UnboundedFollowing
+            || frame.end == FrameBound::UnboundedPreceding
+        {
+            return Err(QueryExecutionError::InvalidWindowFrame(frame.to_string()));
+        }
+    }
+
+    // split the rows into partitions, keeping the order partitions first appear in
+    let mut partitions: Vec<Vec<usize>> = Vec::new();
+    let mut partition_index: HashMap<Vec<Value>, usize> = HashMap::new();
+    let mut order_keys = Vec::with_capacity(rows.len());
+    let mut args = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let key = env.evaluate_all(&spec.partition_by, row)?;
+        order_keys.push(env.evaluate_all(spec.order_by.iter().map(|item| &item.expr), row)?);
+        args.push(match &window.call.args {
+            FunctionArgs::Wildcard => vec![],
+            FunctionArgs::List(list) => env.evaluate_all(list, row)?,
+        });
+
+        let idx = *partition_index.entry(key).or_insert_with(|| {
+            partitions.push(Vec::new());
+            partitions.len() - 1
+        });
+        partitions[idx].push(i);
+    }
+
+    let mut results = vec![Value::Null; rows.len()];
+    for mut partition in partitions {
+        partition.sort_by(|&l, &r| compare_keys(&order_keys[l], &order_keys[r], &spec.order_by));
+        let len = partition.len();
+
+        // rows sorting equal share a rank and, without a frame, a window
+        let mut peer_start = vec![0; len];
+        let mut peer_end = vec![0; len];
+        let mut dense = vec![0; len];
+        for pos in 1..len {
+            let peers = order_keys[partition[pos]] == order_keys[partition[pos - 1]];
+            peer_start[pos] = if peers { peer_start[pos - 1] } else { pos };
+            dense[pos] = if peers {
+                dense[pos - 1]
+            } else {
+                dense[pos - 1] + 1
+            };
+        }
+        for pos in (0..len).rev() {
+            peer_end[pos] = if pos + 1 < len && peer_start[pos + 1] == peer_start[pos] {
+                peer_end[pos + 1]
+            } else {
+                pos
+            };
+        }
+
+        let frame = |pos: usize| -> Option<(usize, usize)> {
+            let (start, end) = match &spec.frame {
+                Some(frame) => (
+                    bound_position(frame.start, pos, len).max(0),
+                    bound_position(frame.end, pos, len).min(len as isize - 1),
+                ),
+                None if spec.order_by.is_empty() => (0, len as isize - 1),
+                None => (0, peer_end[pos] as isize),
+            };
+            (start <= end).then_some((start as usize, end as usize))
+        };
+
+        for (pos, &row) in partition.iter().enumerate() {
+            let row_args = &args[row];
+            results[row] = match kind {
+                WindowKind::RowNumber => Value::Number((pos as u64 + 1).into()),
+                WindowKind::Rank => Value::Number((peer_start[pos] as u64 + 1).into()),
+                WindowKind::DenseRank => Value::Number((dense[pos] as u64 + 1).into()),
+                WindowKind::Lag | WindowKind::Lead => {
+                    let offset = match row_args.get(1) {
+                        Some(Value::Number(n)) if n.is_integer() => {
+                            n.to_usize().ok_or_else(|| {
+                                QueryExecutionError::InvalidOperand(
+                                    window.call.name.clone(),
+                                    Value::Number(n.clone()),
+                                )
+                            })?
+                        }
+                        Some(v) => {
+                            return Err(QueryExecutionError::InvalidOperand(
+                                window.call.name.clone(),
+                                v.clone(),
+                            ))
+                        }
+                        None => 1,
+                    };
+                    let target = match kind {
+                        WindowKind::Lag => pos.checked_sub(offset),
+                        _ => pos.checked_add(offset).filter(|&target| target < len),
+                    };
+                    match target {
+                        Some(target) => args[partition[target]][0].clone(),
+                        None => row_args.get(2).cloned().unwrap_or(Value::Null),
+                    }
+                }
+                WindowKind::FirstValue => frame(pos)
+                    .map(|(start, _)| args[partition[start]][0].clone())
+                    .unwrap_or(Value::Null),
+                WindowKind::Aggregate => {
+                    let mut accumulator = ctx
+                        .functions
+                        .new_aggregate(&window.call.name, row_args.len())?;
+                    if let Some((start, end)) = frame(pos) {
+                        for &other in &partition[start..=end] {
+                            accumulator.step(&args[other])?;
+                        }
+                    }
+                    accumulator.finalize()?
+                }
+            };
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use sql_mini_parser::{ast::SqlQuery, parse::Parse, value::Value};
+
+    use crate::{
+        error::QueryExecutionError,
+        tests::{execution, num, run_all},
+    };
+
+    const TABLES: &[&str] = &[
+        "create table scores (name string, team string, score int);",
+        "insert into scores values 'ann', 'red', 10;",
+        "insert into scores values 'bob', 'red', 30;",
+        "insert into scores values 'cid', 'red', 30;",
+        "insert into scores values 'dan', 'blue', 20;",
+        "insert into scores values 'eve', 'blue', 5;",
+    ];
+
+    #[test]
+    fn test_ranking() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(
+            &mut exec,
+            &[
+                "select name, row_number() over (partition by team order by score desc, name), \
+                 rank() over (partition by team order by score desc), \
+                 dense_rank() over (partition by team order by score desc) \
+               from scores order by team, name;",
+            ],
+        );
+        let ranks: Vec<_> = rows.into_iter().map(|row| row[1..].to_vec()).collect();
+        assert_eq!(
+            ranks,
+            vec![
+                vec![num(1), num(1), num(1)],
+                vec![num(2), num(2), num(2)],
+                vec![num(3), num(3), num(2)],
+                vec![num(1), num(1), num(1)],
+                vec![num(2), num(1), num(1)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut exec = execution(TABLES);
+        let rows = run_all(
+            &mut exec,
+            &["select lag(score) over (order by score), lead(score, 2, 0) over (order by score), \
+                 first_value(name) over (partition by team order by score desc) \
+               from scores order by score;"],
+        );
+        let string = |s: &str| Value::String(s.into());
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Null, num(20), string("dan")],
+                vec![num(5), num(30), string("bob")],
+                vec![num(10), num(30), string("dan")],
+                vec![num(20), num(0), string("bob")],
+                vec![num(30), num(0), string("bob")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_frames() {
+        let mut exec = execution(TABLES);
+        // without a frame rows with the same score are summed together
+        let rows = run_all(
+            &mut exec,
+            &["select sum(score) over (order by score), \
+                 sum(score) over (order by score rows between 1 preceding and current row), \
+                 count(*) over () \
+               from scores order by score;"],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![num(5), num(5), num(5)],
+                vec![num(15), num(15), num(5)],
+                vec![num(35), num(30), num(5)],
+                vec![num(95), num(50), num(5)],
+                vec![num(95), num(60), num(5)],
+            ]
+        );
+
+        // windows run after grouping
+        let rows = run_all(
+            &mut exec,
+            &[
+                "select team, rank() over (order by sum(score) desc) from scores group by team \
+               order by team;",
+            ],
+        );
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::String("blue".into()), num(2)],
+                vec![Value::String("red".into()), num(1)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_misplaced_window() {
+        let mut exec = execution(TABLES);
+        let (_, query) = SqlQuery::parse_from_raw(
+            "select name from scores where rank() over (order by score) = 1;",
+        )
+        .unwrap();
+        assert!(matches!(
+            exec.run(query),
+            Err(QueryExecutionError::MisplacedWindowFunction(_))
+        ));
+    }
+}
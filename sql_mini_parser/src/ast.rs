@@ -1,44 +1,220 @@
+use std::fmt;
+
 use nom::{
     branch::alt,
-    character::complete::{char, multispace0},
-    combinator::map,
+    combinator::{map, opt},
     error::context,
-    sequence::preceded,
+    sequence::{delimited, terminated},
     Parser,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    commands::{CreateStatement, InsertStatement, SelectStatement},
-    parse::{peek_then_cut, Parse},
+    commands::{
+        AnalyzeStatement, CompoundSelect, CopyStatement, CreateIndexStatement, CreateStatement,
+        ExplainStatement, InsertStatement,
+    },
+    error::ParseError,
+    lexer::{tokenize, Ident, Keyword, Symbol},
+    parse::{comma_sep, identifier, keyword, peek_then_cut, symbol, Parse, ParseResult, Tokens},
+    script,
 };
 
+/// `<name> [(<columns>)] AS (<query>)`
+///
+/// In a recursive WITH clause the selects after the first may read the
+/// rows the CTE produced so far
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct CommonTableExpression {
+    pub name: String,
+    /// Renames the columns of the query, empty to keep its names
+    pub columns: Vec<String>,
+    pub query: CompoundSelect,
+}
+
+impl fmt::Display for CommonTableExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Ident(&self.name))?;
+        if !self.columns.is_empty() {
+            write!(f, " (")?;
+            for (i, column) in self.columns.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", Ident(column))?;
+            }
+            write!(f, ")")?;
+        }
+        write!(f, " AS ({})", self.query)
+    }
+}
+
+impl Parse for CommonTableExpression {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let (remaining_input, (name, columns, _, query)) = context(
+            "Common Table Expression",
+            (
+                context("CTE Name", identifier),
+                opt(delimited(
+                    symbol(Symbol::LeftParen),
+                    comma_sep(identifier),
+                    symbol(Symbol::RightParen),
+                )),
+                keyword(Keyword::As),
+                delimited(
+                    symbol(Symbol::LeftParen),
+                    CompoundSelect::parse,
+                    symbol(Symbol::RightParen),
+                ),
+            ),
+        )
+        .parse(input)?;
+
+        Ok((
+            remaining_input,
+            Self {
+                name,
+                columns: columns.unwrap_or_default(),
+                query,
+            },
+        ))
+    }
+}
+
+/// `WITH [RECURSIVE] <cte>, ...` naming queries that the query can read
+/// from like tables
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct WithClause {
+    pub recursive: bool,
+    pub ctes: Vec<CommonTableExpression>,
+}
+
+impl fmt::Display for WithClause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WITH ")?;
+        if self.recursive {
+            write!(f, "RECURSIVE ")?;
+        }
+        for (i, cte) in self.ctes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{cte}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Parse for WithClause {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let (remaining_input, (_, recursive, ctes)) = context(
+            "With Clause",
+            (
+                keyword(Keyword::With),
+                opt(keyword(Keyword::Recursive)),
+                comma_sep(CommonTableExpression::parse),
+            ),
+        )
+        .parse(input)?;
+
+        Ok((
+            remaining_input,
+            Self {
+                recursive: recursive.is_some(),
+                ctes,
+            },
+        ))
+    }
+}
+
+// statements are parsed and run one at a time, boxing the select buys nothing
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum SqlQuery {
-    Select(SelectStatement),
+    Select(CompoundSelect),
     Create(CreateStatement),
+    CreateIndex(CreateIndexStatement),
     Insert(InsertStatement),
+    Explain(ExplainStatement),
+    Analyze(AnalyzeStatement),
+    Copy(CopyStatement),
 }
 
-impl<'a> Parse<'a> for SqlQuery {
-    fn parse(input: crate::parse::RawSpan<'a>) -> crate::parse::ParseResult<'a, Self> {
-        let (remaining_input, (query, _, _, _)) = context(
+impl fmt::Display for SqlQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Select(select) => write!(f, "{select}"),
+            Self::Create(create) => write!(f, "{create}"),
+            Self::CreateIndex(index) => write!(f, "{index}"),
+            Self::Insert(insert) => write!(f, "{insert}"),
+            Self::Explain(explain) => write!(f, "{explain}"),
+            Self::Analyze(analyze) => write!(f, "{analyze}"),
+            Self::Copy(copy) => write!(f, "{copy}"),
+        }
+    }
+}
+
+impl SqlQuery {
+    /// Parse a statement without its terminating semicolon
+    pub(crate) fn statement(input: Tokens<'_>) -> ParseResult<'_, Self> {
+        context(
             "Query",
-            preceded(
-                multispace0,
-                (
-                    alt((
-                        peek_then_cut("select", map(SelectStatement::parse, SqlQuery::Select)),
-                        peek_then_cut("create", map(CreateStatement::parse, SqlQuery::Create)),
-                        peek_then_cut("insert", map(InsertStatement::parse, SqlQuery::Insert)),
-                    )),
-                    multispace0,
-                    char(';'),
-                    multispace0,
+            alt((
+                peek_then_cut(
+                    keyword(Keyword::Select),
+                    map(CompoundSelect::parse, SqlQuery::Select),
                 ),
-            ),
+                peek_then_cut(
+                    keyword(Keyword::With),
+                    map(CompoundSelect::parse, SqlQuery::Select),
+                ),
+                peek_then_cut(
+                    (keyword(Keyword::Create), keyword(Keyword::Table)),
+                    map(CreateStatement::parse, SqlQuery::Create),
+                ),
+                peek_then_cut(
+                    (keyword(Keyword::Create), keyword(Keyword::Index)),
+                    map(CreateIndexStatement::parse, SqlQuery::CreateIndex),
+                ),
+                peek_then_cut(
+                    keyword(Keyword::Insert),
+                    map(InsertStatement::parse, SqlQuery::Insert),
+                ),
+                peek_then_cut(
+                    keyword(Keyword::Explain),
+                    map(ExplainStatement::parse, SqlQuery::Explain),
+                ),
+                peek_then_cut(
+                    keyword(Keyword::Analyze),
+                    map(AnalyzeStatement::parse, SqlQuery::Analyze),
+                ),
+                peek_then_cut(
+                    keyword(Keyword::Copy),
+                    map(CopyStatement::parse, SqlQuery::Copy),
+                ),
+            )),
         )
-        .parse(input)?;
+        .parse(input)
+    }
+
+    /// Parse a script holding exactly one statement, see [`script::parse_script`]
+    pub fn parse_statement(input: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(input)?;
+        let tokens = Tokens::new(&tokens, input);
+        let Some((rest, statement)) = script::next_statement(tokens)? else {
+            return Err(ParseError::at(input, input.len()));
+        };
+        if !rest.is_empty() {
+            return Err(ParseError::at(input, rest.offset()));
+        }
+        Ok(statement.query)
+    }
+}
+
+impl Parse for SqlQuery {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let (remaining_input, query) =
+            terminated(SqlQuery::statement, symbol(Symbol::Semicolon)).parse(input)?;
 
         Ok((remaining_input, query))
     }
@@ -47,7 +223,10 @@ impl<'a> Parse<'a> for SqlQuery {
 #[cfg(test)]
 mod tests {
     use crate::{
-        commands::{Column, SqlTypeInfo},
+        commands::{
+            Column, SelectField, SelectStatement, SetOperator, SqlTypeInfo, TableReference,
+        },
+        expr::Expression,
         value::Value,
     };
 
@@ -62,15 +241,16 @@ mod tests {
     #[test]
     fn test_select() {
         let expected = SelectStatement {
-            table: String::from("users"),
-            fields: vec![String::from("name"), String::from("id")],
+            from: TableReference::table("users"),
+            fields: vec![SelectField::column("name"), SelectField::column("id")],
+            ..Default::default()
         };
 
         assert_eq!(
             SqlQuery::parse_from_raw("select name, id from users;")
                 .unwrap()
                 .1,
-            SqlQuery::Select(expected)
+            SqlQuery::Select(expected.into())
         );
     }
 
@@ -79,8 +259,8 @@ mod tests {
         let expected = InsertStatement {
             table: String::from("users"),
             values: vec![
-                Value::String(String::from("john")),
-                Value::String(String::from("jane")),
+                Expression::Literal(Value::String(String::from("john"))),
+                Expression::Literal(Value::String(String::from("jane"))),
             ],
         };
 
@@ -114,4 +294,398 @@ mod tests {
             SqlQuery::Create(expected)
         );
     }
+
+    #[test]
+    fn test_with() {
+        let (_, query) = SqlQuery::parse_from_raw(
+            "WITH RECURSIVE cnt (x) AS (SELECT n FROM one UNION ALL SELECT x + 1 FROM cnt WHERE x < 3) SELECT x FROM cnt;",
+        )
+        .unwrap();
+
+        let SqlQuery::Select(select) = query else {
+            panic!("expected a select, got {query:?}");
+        };
+        let with = select.with.expect("expected a with clause");
+        assert!(with.recursive);
+        assert_eq!(with.ctes[0].name, "cnt");
+        assert_eq!(with.ctes[0].columns, vec![String::from("x")]);
+        assert_eq!(with.ctes[0].query.compounds[0].op, SetOperator::UnionAll);
+    }
+
+    mod round_trip {
+        use std::str::FromStr;
+
+        use bigdecimal::BigDecimal;
+        use proptest::{collection::vec, option, prelude::*};
+
+        use crate::{
+            ast::{CommonTableExpression, SqlQuery, WithClause},
+            commands::{
+                AnalyzeStatement, Column, CompoundSelect, CompoundTerm, CopyDirection, CopyOptions,
+                CopyStatement, CreateIndexStatement, CreateStatement, Distinct, ExplainStatement,
+                InsertStatement, JoinKind, OrderByItem, SelectField, SelectStatement, SetOperator,
+                SqlTypeInfo, TableReference,
+            },
+            expr::{
+                BinaryOperator, ColumnRef, Expression, FunctionArgs, FunctionCall, Parameter,
+                PatternOperator, UnaryOperator, WhenClause,
+            },
+            value::Value,
+            window::{FrameBound, WindowFrame, WindowFunction, WindowSpec},
+        };
+
+        /// Plain names, keywords and names that need quotes
+        fn name() -> impl Strategy<Value = String> {
+            prop_oneof![
+                4 => "[a-z_][a-z0-9_]{0,5}",
+                1 => Just("select".to_owned()),
+                1 => Just("Rows".to_owned()),
+                1 => "[a-zA-Z0-9 .\"'-]{0,6}",
+            ]
+        }
+
+        fn value() -> impl Strategy<Value = Value> {
+            prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::Bool),
+                "[0-9]{1,6}(\\.[0-9]{1,3})?"
+                    .prop_map(|n| Value::Number(BigDecimal::from_str(&n).unwrap())),
+                "[ -~]{0,8}".prop_map(Value::String),
+            ]
+        }
+
+        fn leaf() -> impl Strategy<Value = Expression> {
+            prop_oneof![
+                value().prop_map(Expression::Literal),
+                prop_oneof![
+                    Just(Parameter::Next),
+                    (1..100usize).prop_map(Parameter::Numbered),
+                    "[a-z_][a-z0-9_]{0,5}".prop_map(Parameter::Named),
+                ]
+                .prop_map(Expression::Parameter),
+                (option::of(name()), name())
+                    .prop_map(|(table, name)| Expression::Column(ColumnRef { table, name })),
+            ]
+        }
+
+        /// A select reading from one table, to nest in expressions without
+        /// recursing forever
+        fn simple_select() -> impl Strategy<Value = CompoundSelect> {
+            (leaf(), name(), option::of(leaf())).prop_map(|(field, table, where_clause)| {
+                SelectStatement {
+                    fields: vec![SelectField::Expression {
+                        expr: field,
+                        alias: None,
+                    }],
+                    from: TableReference::table(table),
+                    where_clause,
+                    ..Default::default()
+                }
+                .into()
+            })
+        }
+
+        fn window_spec(expr: BoxedStrategy<Expression>) -> impl Strategy<Value = WindowSpec> {
+            let bound = prop_oneof![
+                Just(FrameBound::UnboundedPreceding),
+                (0..10u64).prop_map(FrameBound::Preceding),
+                Just(FrameBound::CurrentRow),
+                (0..10u64).prop_map(FrameBound::Following),
+                Just(FrameBound::UnboundedFollowing),
+            ];
+            (
+                vec(expr.clone(), 0..2),
+                vec(order_by_item(expr), 0..2),
+                option::of((bound.clone(), bound)),
+            )
+                .prop_map(|(partition_by, order_by, frame)| WindowSpec {
+                    partition_by,
+                    order_by,
+                    frame: frame.map(|(start, end)| WindowFrame { start, end }),
+                })
+        }
+
+        fn expression() -> BoxedStrategy<Expression> {
+            leaf()
+                .prop_recursive(3, 24, 3, |inner| {
+                    let boxed = || inner.clone().prop_map(Box::new);
+                    let binary_op = prop_oneof![
+                        Just(BinaryOperator::Or),
+                        Just(BinaryOperator::And),
+                        Just(BinaryOperator::Eq),
+                        Just(BinaryOperator::NotEq),
+                        Just(BinaryOperator::LtEq),
+                        Just(BinaryOperator::Gt),
+                        Just(BinaryOperator::Plus),
+                        Just(BinaryOperator::Minus),
+                        Just(BinaryOperator::Multiply),
+                        Just(BinaryOperator::Modulo),
+                        Just(BinaryOperator::Concat),
+                    ];
+                    let pattern_op = prop_oneof![
+                        Just(PatternOperator::Like),
+                        Just(PatternOperator::ILike),
+                        Just(PatternOperator::Glob),
+                    ];
+                    let call = (
+                        name(),
+                        prop_oneof![
+                            Just(FunctionArgs::Wildcard),
+                            vec(inner.clone(), 0..3).prop_map(FunctionArgs::List),
+                        ],
+                    )
+                        .prop_map(|(name, args)| FunctionCall { name, args })
+                        .boxed();
+                    prop_oneof![
+                        (
+                            prop_oneof![Just(UnaryOperator::Not), Just(UnaryOperator::Minus)],
+                            boxed()
+                        )
+                            .prop_map(|(op, expr)| Expression::Unary { op, expr }),
+                        (boxed(), binary_op, boxed())
+                            .prop_map(|(left, op, right)| Expression::Binary { left, op, right }),
+                        (boxed(), any::<bool>())
+                            .prop_map(|(expr, negated)| Expression::IsNull { expr, negated }),
+                        (boxed(), any::<bool>(), boxed(), boxed()).prop_map(
+                            |(expr, negated, low, high)| Expression::Between {
+                                expr,
+                                negated,
+                                low,
+                                high
+                            }
+                        ),
+                        (boxed(), any::<bool>(), vec(inner.clone(), 1..3)).prop_map(
+                            |(expr, negated, list)| Expression::InList {
+                                expr,
+                                negated,
+                                list
+                            }
+                        ),
+                        (
+                            boxed(),
+                            any::<bool>(),
+                            pattern_op,
+                            boxed(),
+                            option::of(boxed())
+                        )
+                            .prop_map(
+                                |(expr, negated, op, pattern, escape)| {
+                                    Expression::Pattern {
+                                        expr,
+                                        negated,
+                                        op,
+                                        pattern,
+                                        escape,
+                                    }
+                                }
+                            ),
+                        (
+                            option::of(boxed()),
+                            vec(
+                                (inner.clone(), inner.clone()).prop_map(|(condition, result)| {
+                                    WhenClause { condition, result }
+                                }),
+                                1..3
+                            ),
+                            option::of(boxed())
+                        )
+                            .prop_map(
+                                |(operand, when_clauses, else_result)| {
+                                    Expression::Case {
+                                        operand,
+                                        when_clauses,
+                                        else_result,
+                                    }
+                                }
+                            ),
+                        call.clone().prop_map(Expression::Function),
+                        (call, window_spec(inner.clone())).prop_map(|(call, window)| {
+                            Expression::Window(Box::new(WindowFunction { call, window }))
+                        }),
+                        simple_select().prop_map(|query| Expression::Subquery(Box::new(query))),
+                        simple_select().prop_map(|query| Expression::Exists(Box::new(query))),
+                        (boxed(), any::<bool>(), simple_select()).prop_map(
+                            |(expr, negated, subquery)| Expression::InSubquery {
+                                expr,
+                                negated,
+                                subquery: Box::new(subquery),
+                            }
+                        ),
+                    ]
+                })
+                .boxed()
+        }
+
+        fn order_by_item(expr: BoxedStrategy<Expression>) -> impl Strategy<Value = OrderByItem> {
+            (expr, any::<bool>()).prop_map(|(expr, descending)| OrderByItem { expr, descending })
+        }
+
+        fn table_reference() -> impl Strategy<Value = TableReference> {
+            let primary = prop_oneof![
+                (name(), option::of(name()))
+                    .prop_map(|(name, alias)| TableReference::Table { name, alias }),
+                (simple_select(), name()).prop_map(|(query, alias)| TableReference::Subquery {
+                    query: Box::new(query),
+                    alias,
+                }),
+            ]
+            .boxed();
+            let join = prop_oneof![
+                primary
+                    .clone()
+                    .prop_map(|right| (JoinKind::Cross, right, None)),
+                (
+                    prop_oneof![Just(JoinKind::Inner), Just(JoinKind::Left)],
+                    primary.clone(),
+                    expression()
+                )
+                    .prop_map(|(kind, right, on)| (kind, right, Some(on))),
+            ];
+            (primary, vec(join, 0..3)).prop_map(|(first, joins)| {
+                joins
+                    .into_iter()
+                    .fold(first, |left, (kind, right, on)| TableReference::Join {
+                        left: Box::new(left),
+                        kind,
+                        right: Box::new(right),
+                        on,
+                    })
+            })
+        }
+
+        fn select_statement() -> impl Strategy<Value = SelectStatement> {
+            let field = prop_oneof![
+                Just(SelectField::Wildcard),
+                (expression(), option::of(name()))
+                    .prop_map(|(expr, alias)| SelectField::Expression { expr, alias }),
+            ];
+            let distinct = option::of(prop_oneof![
+                Just(Distinct::Rows),
+                vec(expression(), 1..3).prop_map(Distinct::On),
+            ]);
+            (
+                distinct,
+                vec(field, 1..4),
+                table_reference(),
+                option::of(expression()),
+                vec(expression(), 0..3),
+            )
+                .prop_map(|(distinct, fields, from, where_clause, group_by)| {
+                    SelectStatement {
+                        distinct,
+                        fields,
+                        from,
+                        where_clause,
+                        group_by,
+                    }
+                })
+        }
+
+        fn compound_select() -> impl Strategy<Value = CompoundSelect> {
+            let op = prop_oneof![
+                Just(SetOperator::Union),
+                Just(SetOperator::UnionAll),
+                Just(SetOperator::Intersect),
+                Just(SetOperator::Except),
+            ];
+            let with = option::of(
+                (
+                    any::<bool>(),
+                    vec(
+                        (name(), vec(name(), 0..3), simple_select()).prop_map(
+                            |(name, columns, query)| CommonTableExpression {
+                                name,
+                                columns,
+                                query,
+                            },
+                        ),
+                        1..3,
+                    ),
+                )
+                    .prop_map(|(recursive, ctes)| WithClause { recursive, ctes }),
+            );
+            (
+                with,
+                select_statement(),
+                vec(
+                    (op, select_statement()).prop_map(|(op, select)| CompoundTerm { op, select }),
+                    0..3,
+                ),
+                vec(order_by_item(expression()), 0..3),
+                option::of((expression(), option::of(expression()))),
+            )
+                .prop_map(|(with, first, compounds, order_by, limit)| {
+                    let (limit, offset) = match limit {
+                        Some((limit, offset)) => (Some(limit), offset),
+                        None => (None, None),
+                    };
+                    CompoundSelect {
+                        with,
+                        first,
+                        compounds,
+                        order_by,
+                        limit,
+                        offset,
+                    }
+                })
+        }
+
+        fn query() -> impl Strategy<Value = SqlQuery> {
+            let type_info = prop_oneof![Just(SqlTypeInfo::String), Just(SqlTypeInfo::Int)];
+            prop_oneof![
+                compound_select().prop_map(SqlQuery::Select),
+                (
+                    name(),
+                    vec(
+                        (name(), type_info)
+                            .prop_map(|(name, type_info)| Column { name, type_info }),
+                        1..4
+                    )
+                )
+                    .prop_map(|(table, columns)| SqlQuery::Create(
+                        CreateStatement { table, columns }
+                    )),
+                (name(), name(), name()).prop_map(|(name, table, column)| {
+                    SqlQuery::CreateIndex(CreateIndexStatement {
+                        name,
+                        table,
+                        column,
+                    })
+                }),
+                (name(), vec(expression(), 1..4)).prop_map(|(table, values)| {
+                    SqlQuery::Insert(InsertStatement { table, values })
+                }),
+                (any::<bool>(), compound_select()).prop_map(|(analyze, query)| {
+                    SqlQuery::Explain(ExplainStatement { analyze, query })
+                }),
+                option::of(name()).prop_map(|table| SqlQuery::Analyze(AnalyzeStatement { table })),
+                (
+                    name(),
+                    prop_oneof![Just(CopyDirection::From), Just(CopyDirection::To)],
+                    "[a-z./' ]{0,12}",
+                    any::<bool>(),
+                    prop_oneof![Just(','), Just(';'), Just('\t'), Just('\'')],
+                )
+                    .prop_map(|(table, direction, path, header, delimiter)| {
+                        SqlQuery::Copy(CopyStatement {
+                            table,
+                            direction,
+                            path,
+                            options: CopyOptions { header, delimiter },
+                        })
+                    }),
+            ]
+        }
+
+        proptest! {
+            /// Printing a statement gives sql that parses back to the same
+            /// statement
+            #[test]
+            fn test_round_trip(query in query()) {
+                let sql = query.to_string();
+                let parsed = SqlQuery::parse_statement(&sql);
+                prop_assert_eq!(parsed, Ok(query), "{}", sql);
+            }
+        }
+    }
 }
@@ -0,0 +1,67 @@
+use std::fmt;
+
+use nom::{
+    combinator::{map, opt},
+    error::context,
+    sequence::preceded,
+    Parser,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    lexer::{Ident, Keyword},
+    parse::{identifier, keyword, Parse, ParseResult, Tokens},
+};
+
+/// `ANALYZE [<table>]`, collecting the statistics the planner estimates
+/// costs with, for one table or for all of them
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzeStatement {
+    pub table: Option<String>,
+}
+
+impl fmt::Display for AnalyzeStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ANALYZE")?;
+        if let Some(table) = &self.table {
+            write!(f, " {}", Ident(table))?;
+        }
+        Ok(())
+    }
+}
+
+impl Parse for AnalyzeStatement {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let mut parser = map(
+            context(
+                "Analyze",
+                preceded(
+                    keyword(Keyword::Analyze),
+                    opt(context("Table Name", identifier)),
+                ),
+            ),
+            |table| Self { table },
+        );
+
+        parser.parse(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::Parse;
+
+    use super::AnalyzeStatement;
+
+    #[test]
+    fn test_analyze() {
+        let (_, analyze) = AnalyzeStatement::parse_from_raw("ANALYZE").unwrap();
+        assert_eq!(analyze.table, None);
+
+        let (remaining, analyze) = AnalyzeStatement::parse_from_raw("analyze users;").unwrap();
+        assert_eq!(analyze.table.as_deref(), Some("users"));
+        assert_eq!(remaining, ";");
+
+        assert!(AnalyzeStatement::parse_from_raw("analyzed").is_err());
+    }
+}
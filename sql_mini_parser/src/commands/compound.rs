@@ -0,0 +1,253 @@
+use std::fmt;
+
+use nom::{
+    branch::alt,
+    combinator::{map, opt, value},
+    error::context,
+    multi::many0,
+    sequence::preceded,
+    Parser,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::WithClause,
+    display::fmt_list,
+    expr::Expression,
+    lexer::Keyword,
+    parse::{comma_sep, keyword, Parse, ParseResult, Tokens},
+};
+
+use super::SelectStatement;
+
+/// How the rows of two selects are combined
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum SetOperator {
+    /// Rows of either side, without duplicates
+    Union,
+    /// Rows of either side, keeping duplicates
+    UnionAll,
+    /// Rows found on both sides
+    Intersect,
+    /// Rows of the left side that are not on the right side
+    Except,
+}
+
+impl fmt::Display for SetOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Union => write!(f, "UNION"),
+            Self::UnionAll => write!(f, "UNION ALL"),
+            Self::Intersect => write!(f, "INTERSECT"),
+            Self::Except => write!(f, "EXCEPT"),
+        }
+    }
+}
+
+// parses "UNION [ALL] | INTERSECT | EXCEPT"
+impl Parse for SetOperator {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Set Operator",
+            alt((
+                map(
+                    preceded(keyword(Keyword::Union), opt(keyword(Keyword::All))),
+                    |all| match all {
+                        Some(_) => Self::UnionAll,
+                        None => Self::Union,
+                    },
+                ),
+                value(Self::Intersect, keyword(Keyword::Intersect)),
+                value(Self::Except, keyword(Keyword::Except)),
+            )),
+        )
+        .parse(input)
+    }
+}
+
+/// A select combined with the rows before it
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct CompoundTerm {
+    pub op: SetOperator,
+    pub select: SelectStatement,
+}
+
+/// `<expr> [ASC | DESC]`
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct OrderByItem {
+    pub expr: Expression,
+    pub descending: bool,
+}
+
+impl fmt::Display for OrderByItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+        if self.descending {
+            write!(f, " DESC")?;
+        }
+        Ok(())
+    }
+}
+
+impl Parse for OrderByItem {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Order By Item",
+            map(
+                (
+                    Expression::parse,
+                    opt(alt((
+                        value(false, keyword(Keyword::Asc)),
+                        value(true, keyword(Keyword::Desc)),
+                    ))),
+                ),
+                |(expr, descending)| Self {
+                    expr,
+                    descending: descending.unwrap_or_default(),
+                },
+            ),
+        )
+        .parse(input)
+    }
+}
+
+/// A complete query, one or more selects joined by set operators
+///
+/// `ORDER BY`, `LIMIT` and `OFFSET` apply to the combined rows
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct CompoundSelect {
+    pub with: Option<WithClause>,
+    pub first: SelectStatement,
+    pub compounds: Vec<CompoundTerm>,
+    pub order_by: Vec<OrderByItem>,
+    pub limit: Option<Expression>,
+    pub offset: Option<Expression>,
+}
+
+impl From<SelectStatement> for CompoundSelect {
+    fn from(first: SelectStatement) -> Self {
+        Self {
+            first,
+            ..Default::default()
+        }
+    }
+}
+
+impl CompoundSelect {
+    /// Every select of the compound, in order
+    pub fn selects(&self) -> impl Iterator<Item = &SelectStatement> {
+        std::iter::once(&self.first).chain(self.compounds.iter().map(|term| &term.select))
+    }
+}
+
+impl fmt::Display for CompoundSelect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(with) = &self.with {
+            write!(f, "{with} ")?;
+        }
+        write!(f, "{}", self.first)?;
+        for term in &self.compounds {
+            write!(f, " {} {}", term.op, term.select)?;
+        }
+        if !self.order_by.is_empty() {
+            write!(f, " ORDER BY ")?;
+            fmt_list(f, &self.order_by)?;
+        }
+        if let Some(limit) = &self.limit {
+            write!(f, " LIMIT {limit}")?;
+        }
+        if let Some(offset) = &self.offset {
+            write!(f, " OFFSET {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Parse for CompoundSelect {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let (remaining_input, (with, first, compounds, order_by, (limit, offset))) = context(
+            "Select Query",
+            (
+                opt(WithClause::parse),
+                SelectStatement::parse,
+                many0(map(
+                    (SetOperator::parse, SelectStatement::parse),
+                    |(op, select)| CompoundTerm { op, select },
+                )),
+                opt(preceded(
+                    (keyword(Keyword::Order), keyword(Keyword::By)),
+                    context("Order By", comma_sep(OrderByItem::parse)),
+                )),
+                map(
+                    opt((
+                        preceded(keyword(Keyword::Limit), context("Limit", Expression::parse)),
+                        opt(preceded(
+                            keyword(Keyword::Offset),
+                            context("Offset", Expression::parse),
+                        )),
+                    )),
+                    |limit| match limit {
+                        Some((limit, offset)) => (Some(limit), offset),
+                        None => (None, None),
+                    },
+                ),
+            ),
+        )
+        .parse(input)?;
+
+        Ok((
+            remaining_input,
+            Self {
+                with,
+                first,
+                compounds,
+                order_by: order_by.unwrap_or_default(),
+                limit,
+                offset,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expr::Expression, parse::Parse, value::Value};
+
+    use super::{CompoundSelect, SetOperator};
+
+    #[test]
+    fn test_compound() {
+        let sql = "SELECT a FROM x UNION ALL SELECT b FROM y EXCEPT SELECT c FROM z \
+                   ORDER BY a DESC, 2 LIMIT 10 OFFSET 5";
+
+        let (remaining, query) = CompoundSelect::parse_from_raw(sql).unwrap();
+
+        assert!(remaining.is_empty());
+        assert_eq!(
+            query
+                .compounds
+                .iter()
+                .map(|term| term.op)
+                .collect::<Vec<_>>(),
+            vec![SetOperator::UnionAll, SetOperator::Except]
+        );
+        assert_eq!(query.order_by.len(), 2);
+        assert!(query.order_by[0].descending);
+        assert_eq!(
+            query.limit,
+            Some(Expression::Literal(Value::Number(10.into())))
+        );
+        assert_eq!(
+            query.to_string(),
+            sql.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+    }
+
+    #[test]
+    fn test_order_by_does_not_bind_to_table_alias() {
+        let (_, query) =
+            CompoundSelect::parse_from_raw("SELECT a FROM x ORDER BY a LIMIT 1").unwrap();
+        assert_eq!(query.first.from.name(), Some("x"));
+        assert_eq!(query.order_by.len(), 1);
+    }
+}
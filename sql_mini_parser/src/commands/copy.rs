@@ -0,0 +1,185 @@
+use std::fmt;
+
+use nom::{
+    branch::alt,
+    combinator::{map, opt, value, verify},
+    error::context,
+    sequence::{delimited, preceded},
+    Parser,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    lexer::{unquote, Ident, Keyword, Symbol, TokenKind},
+    parse::{comma_sep, identifier, keyword, symbol, token, Parse, ParseResult, Tokens},
+    value::Value,
+};
+
+/// Whether a copy reads a file into a table or writes a table to a file
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum CopyDirection {
+    #[default]
+    From,
+    To,
+}
+
+/// How the rows of the file are written
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct CopyOptions {
+    /// The first line names the columns instead of holding a row
+    pub header: bool,
+    /// The ascii character between the values of a row
+    pub delimiter: char,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            header: false,
+            delimiter: ',',
+        }
+    }
+}
+
+/// One of the options in `WITH (...)`
+#[derive(Clone, Copy)]
+enum CopyOption {
+    Header(bool),
+    Delimiter(char),
+}
+
+/// `COPY <table> FROM|TO '<file>' [WITH (HEADER [TRUE|FALSE], DELIMITER '<c>')]`,
+/// moving rows between a table and a CSV file
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct CopyStatement {
+    pub table: String,
+    pub direction: CopyDirection,
+    pub path: String,
+    pub options: CopyOptions,
+}
+
+impl fmt::Display for CopyStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            CopyDirection::From => Keyword::From,
+            CopyDirection::To => Keyword::To,
+        };
+        let path = Value::from(self.path.as_str()).to_sql();
+        write!(f, "COPY {} {direction} {path}", Ident(&self.table))?;
+        let CopyOptions { header, delimiter } = self.options;
+        let delimiter = Value::from(delimiter.to_string()).to_sql();
+        write!(f, " WITH (HEADER {header}, DELIMITER {delimiter})")
+    }
+}
+
+fn string_literal(input: Tokens<'_>) -> ParseResult<'_, String> {
+    token(|token| (token.kind == TokenKind::String).then(|| unquote(token.text))).parse(input)
+}
+
+fn copy_option(input: Tokens<'_>) -> ParseResult<'_, CopyOption> {
+    let boolean = alt((
+        value(true, keyword(Keyword::True)),
+        value(false, keyword(Keyword::False)),
+    ));
+    let delimiter = verify(string_literal, |text: &str| {
+        text.len() == 1 && text.is_ascii()
+    });
+    alt((
+        map(preceded(keyword(Keyword::Header), opt(boolean)), |header| {
+            CopyOption::Header(header.unwrap_or(true))
+        }),
+        map(
+            preceded(keyword(Keyword::Delimiter), context("Delimiter", delimiter)),
+            |text| CopyOption::Delimiter(text.chars().next().unwrap_or(',')),
+        ),
+    ))
+    .parse(input)
+}
+
+fn copy_options(input: Tokens<'_>) -> ParseResult<'_, CopyOptions> {
+    map(
+        delimited(
+            (keyword(Keyword::With), symbol(Symbol::LeftParen)),
+            comma_sep(copy_option),
+            symbol(Symbol::RightParen),
+        ),
+        |options| {
+            options
+                .into_iter()
+                .fold(CopyOptions::default(), |mut options, option| {
+                    match option {
+                        CopyOption::Header(header) => options.header = header,
+                        CopyOption::Delimiter(delimiter) => options.delimiter = delimiter,
+                    }
+                    options
+                })
+        },
+    )
+    .parse(input)
+}
+
+impl Parse for CopyStatement {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let mut parser = map(
+            context(
+                "Copy",
+                (
+                    preceded(keyword(Keyword::Copy), context("Table Name", identifier)),
+                    alt((
+                        value(CopyDirection::From, keyword(Keyword::From)),
+                        value(CopyDirection::To, keyword(Keyword::To)),
+                    )),
+                    context("File Name", string_literal),
+                    opt(copy_options),
+                ),
+            ),
+            |(table, direction, path, options)| Self {
+                table,
+                direction,
+                path,
+                options: options.unwrap_or_default(),
+            },
+        );
+
+        parser.parse(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::SqlQuery, parse::Parse};
+
+    use super::{CopyDirection, CopyOptions, CopyStatement};
+
+    #[test]
+    fn test_copy() {
+        let (remaining, copy) = CopyStatement::parse_from_raw(
+            "COPY people FROM 'data/people''s.csv' WITH (HEADER, DELIMITER ';');",
+        )
+        .unwrap();
+        assert_eq!(remaining, ";");
+        assert_eq!(
+            copy,
+            CopyStatement {
+                table: "people".into(),
+                direction: CopyDirection::From,
+                path: "data/people's.csv".into(),
+                options: CopyOptions {
+                    header: true,
+                    delimiter: ';'
+                },
+            }
+        );
+        assert_eq!(
+            copy.to_string(),
+            "COPY people FROM 'data/people''s.csv' WITH (HEADER true, DELIMITER ';')"
+        );
+
+        let (_, copy) = CopyStatement::parse_from_raw("copy people to 'out.csv'").unwrap();
+        assert_eq!(copy.direction, CopyDirection::To);
+        assert_eq!(copy.options, CopyOptions::default());
+
+        assert!(CopyStatement::parse_from_raw("copy people from out.csv").is_err());
+        assert!(SqlQuery::parse_statement("copy people to 'a' with (delimiter ';;')").is_err());
+    }
+}
@@ -1,33 +1,39 @@
+use std::fmt;
+
 use derive_more::Display;
 use nom::{
     branch::alt,
-    bytes::complete::{tag, tag_no_case},
-    character::complete::multispace1,
     combinator::map,
     error::context,
-    sequence::{preceded, separated_pair},
+    sequence::{delimited, pair, preceded},
     Parser,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::parse::{comma_sep, identifier, Parse, ParseResult, RawSpan};
+use crate::{
+    display::fmt_list,
+    lexer::{Ident, Keyword, Symbol},
+    parse::{comma_sep, identifier, keyword, symbol, Parse, ParseResult, Tokens},
+};
 
 /// A column's type
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize, Display, Copy)]
 pub enum SqlTypeInfo {
+    #[display("STRING")]
     String,
+    #[display("INT")]
     Int,
 }
 
 // parses "string | int"
-impl<'a> Parse<'a> for SqlTypeInfo {
-    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+impl Parse for SqlTypeInfo {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
         let mut parser = context(
             "Column Type",
             // alt will try each passed parser and return what ever succeeds
             alt((
-                map(tag_no_case("string"), |_| Self::String),
-                map(tag_no_case("int"), |_| Self::Int),
+                map(keyword(Keyword::String), |_| Self::String),
+                map(keyword(Keyword::Int), |_| Self::Int),
             )),
         );
 
@@ -42,17 +48,19 @@ pub struct Column {
     pub type_info: SqlTypeInfo,
 }
 
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", Ident(&self.name), self.type_info)
+    }
+}
+
 // parses "<colName> <colTyle>"
-impl<'a> Parse<'a> for Column {
-    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+impl Parse for Column {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
         context(
             "Create Column",
             map(
-                separated_pair(
-                    context("Column Name", identifier),
-                    multispace1,
-                    SqlTypeInfo::parse,
-                ),
+                pair(context("Column Name", identifier), SqlTypeInfo::parse),
                 |(name, type_info)| Self { name, type_info },
             ),
         )
@@ -67,13 +75,22 @@ pub struct CreateStatement {
     pub columns: Vec<Column>,
 }
 
+impl fmt::Display for CreateStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CREATE TABLE {} (", Ident(&self.table))?;
+        fmt_list(f, &self.columns)?;
+        write!(f, ")")
+    }
+}
+
 //parses a comma seperated list of column and definitions contained in parens
-fn column_definitions(input: RawSpan<'_>) -> ParseResult<'_, Vec<Column>> {
+fn column_definitions(input: Tokens<'_>) -> ParseResult<'_, Vec<Column>> {
     let mut parser = context(
         "Column Definitions",
-        map(
-            (tag("("), comma_sep(Column::parse), tag(")")),
-            |(_, cols, _)| cols,
+        delimited(
+            symbol(Symbol::LeftParen),
+            comma_sep(Column::parse),
+            symbol(Symbol::RightParen),
         ),
     );
 
@@ -81,23 +98,17 @@ fn column_definitions(input: RawSpan<'_>) -> ParseResult<'_, Vec<Column>> {
 }
 
 //Parses "CREATE TABLE <table name> <column defs>"
-impl<'a> Parse<'a> for CreateStatement {
-    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+impl Parse for CreateStatement {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
         let mut parser = map(
             context(
                 "Create Table",
-                separated_pair(
+                pair(
                     // table name
                     preceded(
-                        (
-                            tag_no_case("create"),
-                            multispace1,
-                            tag_no_case("table"),
-                            multispace1,
-                        ),
+                        (keyword(Keyword::Create), keyword(Keyword::Table)),
                         context("Table Name", identifier),
                     ),
-                    multispace1,
                     // column defs
                     column_definitions,
                 ),
@@ -109,11 +120,62 @@ impl<'a> Parse<'a> for CreateStatement {
     }
 }
 
+/// An index on a column of a table, used to find rows by value without
+/// reading the whole table
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct CreateIndexStatement {
+    pub name: String,
+    pub table: String,
+    pub column: String,
+}
+
+impl fmt::Display for CreateIndexStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CREATE INDEX {} ON {} ({})",
+            Ident(&self.name),
+            Ident(&self.table),
+            Ident(&self.column)
+        )
+    }
+}
+
+//parses "CREATE INDEX <index name> ON <table name> (<column name>)"
+impl Parse for CreateIndexStatement {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let mut parser = map(
+            context(
+                "Create Index",
+                (
+                    preceded(
+                        (keyword(Keyword::Create), keyword(Keyword::Index)),
+                        context("Index Name", identifier),
+                    ),
+                    preceded(keyword(Keyword::On), context("Table Name", identifier)),
+                    delimited(
+                        symbol(Symbol::LeftParen),
+                        context("Column Name", identifier),
+                        symbol(Symbol::RightParen),
+                    ),
+                ),
+            ),
+            |(name, table, column)| Self {
+                name,
+                table,
+                column,
+            },
+        );
+
+        parser.parse(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::parse::Parse;
 
-    use super::{Column, CreateStatement, SqlTypeInfo};
+    use super::{Column, CreateIndexStatement, CreateStatement, SqlTypeInfo};
 
     #[test]
     fn test_create() {
@@ -144,4 +206,19 @@ mod tests {
             expeted
         )
     }
+
+    #[test]
+    fn test_create_index() {
+        let (remaining, index) =
+            CreateIndexStatement::parse_from_raw("CREATE INDEX by_name ON users ( name )").unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(
+            index,
+            CreateIndexStatement {
+                name: "by_name".into(),
+                table: "users".into(),
+                column: "name".into(),
+            }
+        );
+    }
 }
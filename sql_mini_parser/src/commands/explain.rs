@@ -0,0 +1,79 @@
+use std::fmt;
+
+use nom::{
+    combinator::{map, opt},
+    error::context,
+    sequence::preceded,
+    Parser,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    lexer::Keyword,
+    parse::{keyword, Parse, ParseResult, Tokens},
+};
+
+use super::CompoundSelect;
+
+/// `EXPLAIN [ANALYZE] <query>`, describing how a query runs instead of
+/// returning its rows
+///
+/// With `ANALYZE` the query also runs, to measure every step of it
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct ExplainStatement {
+    pub analyze: bool,
+    pub query: CompoundSelect,
+}
+
+impl fmt::Display for ExplainStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EXPLAIN ")?;
+        if self.analyze {
+            write!(f, "ANALYZE ")?;
+        }
+        write!(f, "{}", self.query)
+    }
+}
+
+impl Parse for ExplainStatement {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let mut parser = map(
+            context(
+                "Explain",
+                preceded(
+                    keyword(Keyword::Explain),
+                    (opt(keyword(Keyword::Analyze)), CompoundSelect::parse),
+                ),
+            ),
+            |(analyze, query)| Self {
+                analyze: analyze.is_some(),
+                query,
+            },
+        );
+
+        parser.parse(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::Parse;
+
+    use super::ExplainStatement;
+
+    #[test]
+    fn test_explain() {
+        let (_, explain) = ExplainStatement::parse_from_raw("EXPLAIN select a from t").unwrap();
+        assert!(!explain.analyze);
+        assert_eq!(explain.to_string(), "EXPLAIN SELECT a FROM t");
+
+        let (_, explain) =
+            ExplainStatement::parse_from_raw("explain analyze select a from t where a > 1")
+                .unwrap();
+        assert!(explain.analyze);
+        assert_eq!(explain.query.to_string(), "SELECT a FROM t WHERE a > 1");
+
+        // ANALYZE still needs a query to run
+        assert!(ExplainStatement::parse_from_raw("explain analyze").is_err());
+    }
+}
@@ -1,30 +1,38 @@
-use nom::{
-    bytes::complete::tag_no_case, character::complete::multispace1, error::context,
-    sequence::preceded, Parser,
-};
+use std::fmt;
+
+use nom::{error::context, Parser};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    parse::{comma_sep, identifier, Parse},
-    value::Value,
+    display::fmt_list,
+    expr::Expression,
+    lexer::{Ident, Keyword},
+    parse::{comma_sep, identifier, keyword, Parse, ParseResult, Tokens},
 };
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct InsertStatement {
     pub table: String,
-    pub values: Vec<Value>,
+    pub values: Vec<Expression>,
+}
+
+impl fmt::Display for InsertStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "INSERT INTO {} VALUES ", Ident(&self.table))?;
+        fmt_list(f, &self.values)
+    }
 }
 
-impl<'a> Parse<'a> for InsertStatement {
-    fn parse(input: crate::parse::RawSpan<'a>) -> crate::parse::ParseResult<'a, Self> {
+impl Parse for InsertStatement {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
         let (remaining_input, (_, _, table, _, values)) = context(
             "Insert Statement",
             (
-                tag_no_case("insert"),
-                preceded(multispace1, tag_no_case("into")),
-                preceded(multispace1, context("Table Name", identifier)),
-                preceded(multispace1, tag_no_case("values")),
-                preceded(multispace1, context("Values", comma_sep(Value::parse))),
+                keyword(Keyword::Insert),
+                keyword(Keyword::Into),
+                context("Table Name", identifier),
+                keyword(Keyword::Values),
+                context("Values", comma_sep(Expression::parse)),
             ),
         )
         .parse(input)?;
@@ -35,7 +43,11 @@ impl<'a> Parse<'a> for InsertStatement {
 
 #[cfg(test)]
 mod tests {
-    use crate::{parse::Parse, value::Value};
+    use crate::{
+        expr::{Expression, Parameter},
+        parse::Parse,
+        value::Value,
+    };
 
     use super::InsertStatement;
 
@@ -44,8 +56,8 @@ mod tests {
         let expected = InsertStatement {
             table: String::from("users"),
             values: vec![
-                Value::String(String::from("john")),
-                Value::String(String::from("jane")),
+                Expression::Literal(Value::String(String::from("john"))),
+                Expression::Literal(Value::String(String::from("jane"))),
             ],
         };
 
@@ -54,4 +66,19 @@ mod tests {
 
         assert_eq!(command, expected);
     }
+
+    #[test]
+    fn test_insert_parameters() {
+        let (_, command) =
+            InsertStatement::parse_from_raw("insert into users values ?, ?3, :name").unwrap();
+
+        assert_eq!(
+            command.values,
+            vec![
+                Expression::Parameter(Parameter::Next),
+                Expression::Parameter(Parameter::Numbered(3)),
+                Expression::Parameter(Parameter::Named("name".into())),
+            ]
+        );
+    }
 }
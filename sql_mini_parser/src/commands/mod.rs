@@ -1,7 +1,15 @@
+mod analyze;
+mod compound;
+mod copy;
 mod create;
+mod explain;
 mod insert;
 mod select;
 
-pub use create::{Column, CreateStatement, SqlTypeInfo};
+pub use analyze::AnalyzeStatement;
+pub use compound::{CompoundSelect, CompoundTerm, OrderByItem, SetOperator};
+pub use copy::{CopyDirection, CopyOptions, CopyStatement};
+pub use create::{Column, CreateIndexStatement, CreateStatement, SqlTypeInfo};
+pub use explain::ExplainStatement;
 pub use insert::InsertStatement;
-pub use select::SelectStatement;
+pub use select::{Distinct, JoinKind, SelectField, SelectStatement, TableReference};
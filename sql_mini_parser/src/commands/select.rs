@@ -1,51 +1,496 @@
-use nom::{bytes::complete::tag_no_case, character::complete::multispace1, error::context, Parser};
+use std::fmt;
+
+use nom::{
+    branch::alt,
+    combinator::{map, opt, value},
+    error::context,
+    multi::many0,
+    sequence::{preceded, terminated},
+    Parser,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::parse::{comma_sep, identifier, Parse};
+use crate::{
+    commands::CompoundSelect,
+    display::fmt_list,
+    expr::{subquery, Expression},
+    lexer::{Ident, Keyword, Symbol},
+    parse::{comma_sep, identifier, keyword, symbol, Parse, ParseResult, Tokens},
+};
+
+/// A single entry in the select list
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum SelectField {
+    /// `*`, every column of the table
+    Wildcard,
+    Expression {
+        expr: Expression,
+        alias: Option<String>,
+    },
+}
+
+impl SelectField {
+    /// A plain, unaliased column reference
+    pub fn column(name: impl Into<String>) -> Self {
+        Self::Expression {
+            expr: Expression::column(name),
+            alias: None,
+        }
+    }
+}
+
+// parses "* | <expr> [AS <alias>]"
+impl Parse for SelectField {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Select Field",
+            alt((
+                value(Self::Wildcard, symbol(Symbol::Star)),
+                map(
+                    (
+                        Expression::parse,
+                        opt(preceded(keyword(Keyword::As), context("Alias", identifier))),
+                    ),
+                    |(expr, alias)| Self::Expression { expr, alias },
+                ),
+            )),
+        )
+        .parse(input)
+    }
+}
+
+impl fmt::Display for SelectField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wildcard => write!(f, "*"),
+            Self::Expression { expr, alias: None } => write!(f, "{expr}"),
+            Self::Expression {
+                expr,
+                alias: Some(alias),
+            } => write!(f, "{expr} AS {}", Ident(alias)),
+        }
+    }
+}
+
+/// How the rows of the two sides of a join are paired
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum JoinKind {
+    /// `[INNER] JOIN ... ON`, every pair of rows matching the condition
+    Inner,
+    /// `LEFT [OUTER] JOIN ... ON`, like an inner join but also keeping left
+    /// rows without a match, padded with NULLs
+    Left,
+    /// `CROSS JOIN` or `,`, every pair of rows
+    Cross,
+}
+
+impl fmt::Display for JoinKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inner => write!(f, "JOIN"),
+            Self::Left => write!(f, "LEFT JOIN"),
+            Self::Cross => write!(f, "CROSS JOIN"),
+        }
+    }
+}
+
+/// What a select reads its rows from
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum TableReference {
+    Table {
+        name: String,
+        alias: Option<String>,
+    },
+    /// A derived table, `(SELECT ...) AS alias`
+    Subquery {
+        query: Box<CompoundSelect>,
+        alias: String,
+    },
+    /// `<left> <kind> <right> [ON <condition>]`
+    Join {
+        left: Box<TableReference>,
+        kind: JoinKind,
+        right: Box<TableReference>,
+        on: Option<Expression>,
+    },
+}
+
+impl Default for TableReference {
+    fn default() -> Self {
+        Self::table(String::new())
+    }
+}
+
+impl TableReference {
+    /// An unaliased table
+    pub fn table(name: impl Into<String>) -> Self {
+        Self::Table {
+            name: name.into(),
+            alias: None,
+        }
+    }
+
+    /// The name columns of this table can be qualified with, joins have none
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Table {
+                alias: Some(alias), ..
+            }
+            | Self::Subquery { alias, .. } => Some(alias),
+            Self::Table { name, alias: None } => Some(name),
+            Self::Join { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for TableReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Table { name, alias: None } => write!(f, "{}", Ident(name)),
+            Self::Table {
+                name,
+                alias: Some(alias),
+            } => write!(f, "{} AS {}", Ident(name), Ident(alias)),
+            Self::Subquery { query, alias } => write!(f, "({query}) AS {}", Ident(alias)),
+            Self::Join {
+                left,
+                kind,
+                right,
+                on,
+            } => {
+                write!(f, "{left} {kind} {right}")?;
+                if let Some(on) = on {
+                    write!(f, " ON {on}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// parses "<table> [[AS] <alias>] | (<select>) [AS] <alias>"
+fn table_primary(input: Tokens<'_>) -> ParseResult<'_, TableReference> {
+    let alias = || preceded(opt(keyword(Keyword::As)), context("Alias", identifier));
+
+    alt((
+        map(
+            (
+                subquery,
+                preceded(opt(keyword(Keyword::As)), context("Alias", identifier)),
+            ),
+            |(query, alias)| TableReference::Subquery {
+                query: Box::new(query),
+                alias,
+            },
+        ),
+        map((identifier, opt(alias())), |(name, alias)| {
+            TableReference::Table { name, alias }
+        }),
+    ))
+    .parse(input)
+}
+
+// parses ", <table> | CROSS JOIN <table> | [INNER | LEFT [OUTER]] JOIN <table> ON <expr>"
+fn join_clause(
+    input: Tokens<'_>,
+) -> ParseResult<'_, (JoinKind, TableReference, Option<Expression>)> {
+    let kind = alt((
+        value(
+            JoinKind::Inner,
+            (opt(keyword(Keyword::Inner)), keyword(Keyword::Join)),
+        ),
+        value(
+            JoinKind::Left,
+            (
+                keyword(Keyword::Left),
+                opt(keyword(Keyword::Outer)),
+                keyword(Keyword::Join),
+            ),
+        ),
+    ));
+
+    context(
+        "Join",
+        alt((
+            map(preceded(symbol(Symbol::Comma), table_primary), |right| {
+                (JoinKind::Cross, right, None)
+            }),
+            map(
+                preceded(
+                    (keyword(Keyword::Cross), keyword(Keyword::Join)),
+                    table_primary,
+                ),
+                |right| (JoinKind::Cross, right, None),
+            ),
+            map(
+                (
+                    kind,
+                    table_primary,
+                    preceded(
+                        keyword(Keyword::On),
+                        context("Join Condition", Expression::parse),
+                    ),
+                ),
+                |(kind, right, on)| (kind, right, Some(on)),
+            ),
+        )),
+    )
+    .parse(input)
+}
+
+// parses "<table> [<join> ...]", joins nesting to the left
+impl Parse for TableReference {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Table Reference",
+            map((table_primary, many0(join_clause)), |(first, joins)| {
+                joins
+                    .into_iter()
+                    .fold(first, |left, (kind, right, on)| Self::Join {
+                        left: Box::new(left),
+                        kind,
+                        right: Box::new(right),
+                        on,
+                    })
+            }),
+        )
+        .parse(input)
+    }
+}
+
+/// Which rows `SELECT DISTINCT` treats as duplicates
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Distinct {
+    /// `DISTINCT`, rows with the same values in every column
+    Rows,
+    /// `DISTINCT ON (<expr>, ...)`, keeps the first row of each set of rows
+    /// with the same values for the expressions
+    On(Vec<Expression>),
+}
+
+impl fmt::Display for Distinct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rows => write!(f, "DISTINCT"),
+            Self::On(exprs) => {
+                write!(f, "DISTINCT ON (")?;
+                fmt_list(f, exprs)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+// parses "DISTINCT [ON (<expr>, ...)]"
+impl Parse for Distinct {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Distinct",
+            alt((
+                map(
+                    preceded(
+                        (
+                            keyword(Keyword::Distinct),
+                            keyword(Keyword::On),
+                            symbol(Symbol::LeftParen),
+                        ),
+                        terminated(comma_sep(Expression::parse), symbol(Symbol::RightParen)),
+                    ),
+                    Self::On,
+                ),
+                value(Self::Rows, keyword(Keyword::Distinct)),
+            )),
+        )
+        .parse(input)
+    }
+}
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct SelectStatement {
-    pub table: String,
-    pub fields: Vec<String>,
+    pub distinct: Option<Distinct>,
+    pub fields: Vec<SelectField>,
+    pub from: TableReference,
+    pub where_clause: Option<Expression>,
+    pub group_by: Vec<Expression>,
 }
 
-//TODO: impl display
+impl fmt::Display for SelectStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SELECT ")?;
+        if let Some(distinct) = &self.distinct {
+            write!(f, "{distinct} ")?;
+        }
+        fmt_list(f, &self.fields)?;
+        write!(f, " FROM {}", self.from)?;
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {where_clause}")?;
+        }
+        if !self.group_by.is_empty() {
+            write!(f, " GROUP BY ")?;
+            fmt_list(f, &self.group_by)?;
+        }
+        Ok(())
+    }
+}
 
-impl<'a> Parse<'a> for SelectStatement {
-    fn parse(input: crate::parse::RawSpan<'a>) -> crate::parse::ParseResult<'a, Self> {
-        let (remaining_input, (_, _, fields, _, _, _, table)) = context(
+impl Parse for SelectStatement {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let (remaining_input, (_, distinct, fields, from, where_clause, group_by)) = context(
             "Select Statement",
             (
-                tag_no_case("select"),
-                multispace1,
-                context("Select Columns", comma_sep(identifier)),
-                multispace1,
-                tag_no_case("from"),
-                multispace1,
-                context("From Table", identifier),
+                keyword(Keyword::Select),
+                opt(Distinct::parse),
+                context("Select Columns", comma_sep(SelectField::parse)),
+                preceded(
+                    keyword(Keyword::From),
+                    context("From Table", TableReference::parse),
+                ),
+                opt(preceded(
+                    keyword(Keyword::Where),
+                    context("Where Clause", Expression::parse),
+                )),
+                opt(preceded(
+                    (keyword(Keyword::Group), keyword(Keyword::By)),
+                    context("Group By", comma_sep(Expression::parse)),
+                )),
             ),
         )
         .parse(input)?;
 
-        Ok((remaining_input, SelectStatement { fields, table }))
+        Ok((
+            remaining_input,
+            SelectStatement {
+                distinct,
+                fields,
+                from,
+                where_clause,
+                group_by: group_by.unwrap_or_default(),
+            },
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parse::Parse;
+    use crate::{
+        expr::{BinaryOperator, Expression, FunctionArgs, FunctionCall},
+        parse::Parse,
+        value::Value,
+    };
 
-    use super::SelectStatement;
+    use super::{Distinct, JoinKind, SelectField, SelectStatement, TableReference};
 
     #[test]
     fn test_select() {
         let expected = SelectStatement {
-            table: String::from("users"),
-            fields: vec![String::from("name"), String::from("id")],
+            from: TableReference::table("users"),
+            fields: vec![SelectField::column("name"), SelectField::column("id")],
+            ..Default::default()
         };
 
         let value = SelectStatement::parse_from_raw("SELECT name, id FROM users");
 
         assert_eq!(value.unwrap().1, expected);
     }
+
+    #[test]
+    fn test_select_where_group_by() {
+        let expected = SelectStatement {
+            from: TableReference::table("users"),
+            fields: vec![
+                SelectField::column("name"),
+                SelectField::Expression {
+                    expr: Expression::Function(FunctionCall {
+                        name: "count".into(),
+                        args: FunctionArgs::Wildcard,
+                    }),
+                    alias: Some("total".into()),
+                },
+            ],
+            where_clause: Some(Expression::binary(
+                Expression::column("name"),
+                BinaryOperator::NotEq,
+                Expression::Literal(Value::String("root".into())),
+            )),
+            group_by: vec![Expression::column("name")],
+            ..Default::default()
+        };
+
+        let value = SelectStatement::parse_from_raw(
+            "SELECT name, count(*) AS total FROM users WHERE name <> 'root' GROUP BY name",
+        );
+
+        assert_eq!(value.unwrap().1, expected);
+    }
+
+    #[test]
+    fn test_select_subqueries() {
+        let sql = "SELECT t.a FROM (SELECT a FROM foo AS f WHERE EXISTS (SELECT 1 FROM baz)) AS t \
+                   WHERE t.a IN (SELECT b FROM bar) AND t.a > (SELECT max(c) FROM baz)";
+
+        let (remaining, select) = SelectStatement::parse_from_raw(sql).unwrap();
+
+        assert!(remaining.is_empty());
+        assert!(matches!(
+            select.from,
+            TableReference::Subquery { ref alias, .. } if alias == "t"
+        ));
+        assert_eq!(
+            select.to_string(),
+            sql.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+    }
+
+    #[test]
+    fn test_distinct() {
+        let (_, select) =
+            SelectStatement::parse_from_raw("SELECT DISTINCT name FROM users").unwrap();
+        assert_eq!(select.distinct, Some(Distinct::Rows));
+        assert_eq!(select.fields, vec![SelectField::column("name")]);
+
+        let sql = "SELECT DISTINCT ON (dept, lower(name)) name FROM users";
+        let (_, select) = SelectStatement::parse_from_raw(sql).unwrap();
+        assert!(matches!(select.distinct, Some(Distinct::On(ref exprs)) if exprs.len() == 2));
+        assert_eq!(select.to_string(), sql);
+
+        // a column merely starting with the keyword is not DISTINCT
+        let (_, select) = SelectStatement::parse_from_raw("SELECT distinctive FROM users").unwrap();
+        assert_eq!(select.distinct, None);
+    }
+
+    #[test]
+    fn test_joins() {
+        let sql =
+            "SELECT * FROM a JOIN b AS x ON a.id = x.id LEFT JOIN c ON c.id = a.id CROSS JOIN d";
+        let (remaining, select) = SelectStatement::parse_from_raw(sql).unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(select.to_string(), sql);
+
+        // joins nest to the left
+        let TableReference::Join {
+            left, kind, right, ..
+        } = &select.from
+        else {
+            panic!("expected a join, got {:?}", select.from);
+        };
+        assert_eq!(*kind, JoinKind::Cross);
+        assert_eq!(right.name(), Some("d"));
+        assert!(matches!(
+            left.as_ref(),
+            TableReference::Join {
+                kind: JoinKind::Left,
+                ..
+            }
+        ));
+
+        let (_, select) = SelectStatement::parse_from_raw(
+            "SELECT * FROM a, b INNER JOIN c ON b.x = c.x WHERE a.x = 1",
+        )
+        .unwrap();
+        assert_eq!(
+            select.to_string(),
+            "SELECT * FROM a CROSS JOIN b JOIN c ON b.x = c.x WHERE a.x = 1"
+        );
+        assert_eq!(select.from.name(), None);
+    }
 }
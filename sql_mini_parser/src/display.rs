@@ -0,0 +1,15 @@
+//! Helpers for `Display` impls, shared with the execution crate's plan
+//! display and not part of the API
+
+use std::fmt;
+
+/// Write `items` separated by commas
+pub fn fmt_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    Ok(())
+}
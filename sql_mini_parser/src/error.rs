@@ -0,0 +1,66 @@
+use std::fmt;
+
+use crate::parse::Tokens;
+
+/// Where parsing a statement stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line of the input, counting from 1
+    pub line: u32,
+    /// Column in characters, counting from 1
+    pub column: usize,
+    /// The start of the input that could not be parsed
+    pub near: String,
+}
+
+impl ParseError {
+    /// How much of the remaining input to show
+    const NEAR_CHARS: usize = 20;
+
+    /// An error at byte `offset` of `source`
+    pub(crate) fn at(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let rest = source[offset..].trim_end();
+        let mut near: String = rest.chars().take(Self::NEAR_CHARS).collect();
+        if near.len() < rest.len() {
+            near.push_str("...");
+        }
+        Self {
+            line: before.matches('\n').count() as u32 + 1,
+            column: before[line_start..].chars().count() + 1,
+            near,
+        }
+    }
+}
+
+impl<'a> From<nom::Err<nom::error::Error<Tokens<'a>>>> for ParseError {
+    fn from(err: nom::Err<nom::error::Error<Tokens<'a>>>) -> Self {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => {
+                Self::at(err.input.source(), err.input.offset())
+            }
+            // the parsers only work on complete input
+            nom::Err::Incomplete(_) => Self {
+                line: 1,
+                column: 1,
+                near: String::new(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.near.as_str() {
+            "" => write!(f, "Unexpected end of input"),
+            near => write!(
+                f,
+                "Syntax error at line {}, column {} near \"{near}\"",
+                self.line, self.column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,1015 @@
+use std::fmt;
+
+use derive_more::Display;
+use nom::{
+    branch::alt,
+    combinator::{map, opt, peek, success, value},
+    error::context,
+    multi::{fold_many0, many1},
+    sequence::{delimited, preceded, terminated},
+    Parser,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::CompoundSelect,
+    lexer::{Ident, Keyword, Symbol, TokenKind},
+    parse::{
+        any_identifier, comma_sep, identifier, keyword, symbol, token, Parse, ParseResult, Tokens,
+    },
+    value::{literal, Value},
+    window::{WindowFunction, WindowSpec},
+};
+
+/// Operators that take two operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display)]
+pub enum BinaryOperator {
+    #[display("OR")]
+    Or,
+    #[display("AND")]
+    And,
+    #[display("=")]
+    Eq,
+    #[display("<>")]
+    NotEq,
+    #[display("<")]
+    Lt,
+    #[display("<=")]
+    LtEq,
+    #[display(">")]
+    Gt,
+    #[display(">=")]
+    GtEq,
+    #[display("+")]
+    Plus,
+    #[display("-")]
+    Minus,
+    #[display("*")]
+    Multiply,
+    #[display("/")]
+    Divide,
+    #[display("%")]
+    Modulo,
+    #[display("||")]
+    Concat,
+}
+
+impl BinaryOperator {
+    /// Binding power of the operator, higher binds tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::Eq | Self::NotEq | Self::Lt | Self::LtEq | Self::Gt | Self::GtEq => 4,
+            Self::Plus | Self::Minus | Self::Concat => 5,
+            Self::Multiply | Self::Divide | Self::Modulo => 6,
+        }
+    }
+}
+
+/// Operators that take a single operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display)]
+pub enum UnaryOperator {
+    #[display("NOT ")]
+    Not,
+    #[display("-")]
+    Minus,
+}
+
+impl UnaryOperator {
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Not => 3,
+            Self::Minus => 7,
+        }
+    }
+}
+
+/// Pattern matching operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display)]
+pub enum PatternOperator {
+    /// Case sensitive, `%` matches any sequence and `_` any single character
+    #[display("LIKE")]
+    Like,
+    /// [`PatternOperator::Like`] ignoring case
+    #[display("ILIKE")]
+    ILike,
+    /// Case sensitive unix style globbing with `*`, `?` and `[...]`
+    #[display("GLOB")]
+    Glob,
+}
+
+/// A `WHEN <condition> THEN <result>` branch of a CASE expression
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WhenClause {
+    pub condition: Expression,
+    pub result: Expression,
+}
+
+/// A possibly table qualified reference to a column, `name` or `table.name`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ColumnRef {
+    pub table: Option<String>,
+    pub name: String,
+}
+
+impl ColumnRef {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            table: None,
+            name: name.into(),
+        }
+    }
+
+    pub fn qualified(table: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            table: Some(table.into()),
+            name: name.into(),
+        }
+    }
+}
+
+impl fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.table {
+            Some(table) => write!(f, "{}.{}", Ident(table), Ident(&self.name)),
+            None => write!(f, "{}", Ident(&self.name)),
+        }
+    }
+}
+
+/// The arguments passed to a function call
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FunctionArgs {
+    /// `count(*)`
+    Wildcard,
+    List(Vec<Expression>),
+}
+
+/// A call to a scalar or aggregate function, which one is decided at execution
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    pub args: FunctionArgs,
+}
+
+/// A placeholder for a value bound when running a prepared statement
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Parameter {
+    /// `?`, numbered one past the largest number before it
+    Next,
+    /// `?<n>`, numbered from 1
+    Numbered(usize),
+    /// `:<name>`, every use of a name is the same parameter
+    Named(String),
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Next => write!(f, "?"),
+            Self::Numbered(n) => write!(f, "?{n}"),
+            Self::Named(name) => write!(f, ":{name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Expression {
+    Literal(Value),
+    Parameter(Parameter),
+    Column(ColumnRef),
+    Function(FunctionCall),
+    Unary {
+        op: UnaryOperator,
+        expr: Box<Expression>,
+    },
+    Binary {
+        left: Box<Expression>,
+        op: BinaryOperator,
+        right: Box<Expression>,
+    },
+    IsNull {
+        expr: Box<Expression>,
+        negated: bool,
+    },
+    /// `CASE [<operand>] WHEN ... THEN ... [ELSE ...] END`
+    ///
+    /// With an operand each condition is compared to it, otherwise each
+    /// condition is evaluated as a boolean
+    Case {
+        operand: Option<Box<Expression>>,
+        when_clauses: Vec<WhenClause>,
+        else_result: Option<Box<Expression>>,
+    },
+    Between {
+        expr: Box<Expression>,
+        negated: bool,
+        low: Box<Expression>,
+        high: Box<Expression>,
+    },
+    InList {
+        expr: Box<Expression>,
+        negated: bool,
+        list: Vec<Expression>,
+    },
+    Pattern {
+        expr: Box<Expression>,
+        negated: bool,
+        op: PatternOperator,
+        pattern: Box<Expression>,
+        escape: Option<Box<Expression>>,
+    },
+    /// `(SELECT ...)` producing a single value
+    Subquery(Box<CompoundSelect>),
+    InSubquery {
+        expr: Box<Expression>,
+        negated: bool,
+        subquery: Box<CompoundSelect>,
+    },
+    /// `EXISTS (SELECT ...)`
+    Exists(Box<CompoundSelect>),
+    /// `<call> OVER (...)`
+    Window(Box<WindowFunction>),
+}
+
+impl Expression {
+    pub fn binary(left: Expression, op: BinaryOperator, right: Expression) -> Self {
+        Self::Binary {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    /// An unqualified column reference
+    pub fn column(name: impl Into<String>) -> Self {
+        Self::Column(ColumnRef::new(name))
+    }
+
+    pub fn unary(op: UnaryOperator, expr: Expression) -> Self {
+        Self::Unary {
+            op,
+            expr: Box::new(expr),
+        }
+    }
+
+    /// The direct sub expressions of this expression
+    ///
+    /// Subqueries are their own scope so their expressions are not included
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Self::Literal(_)
+            | Self::Parameter(_)
+            | Self::Column(_)
+            | Self::Subquery(_)
+            | Self::Exists(_) => vec![],
+            Self::InSubquery { expr, .. } => vec![expr],
+            Self::Function(FunctionCall { args, .. }) => match args {
+                FunctionArgs::Wildcard => vec![],
+                FunctionArgs::List(args) => args.iter().collect(),
+            },
+            Self::Unary { expr, .. } | Self::IsNull { expr, .. } => vec![expr],
+            Self::Binary { left, right, .. } => vec![left, right],
+            Self::Case {
+                operand,
+                when_clauses,
+                else_result,
+            } => operand
+                .iter()
+                .map(|e| e.as_ref())
+                .chain(
+                    when_clauses
+                        .iter()
+                        .flat_map(|when| [&when.condition, &when.result]),
+                )
+                .chain(else_result.iter().map(|e| e.as_ref()))
+                .collect(),
+            Self::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+            Self::InList { expr, list, .. } => std::iter::once(expr.as_ref()).chain(list).collect(),
+            Self::Pattern {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => [expr.as_ref(), pattern.as_ref()]
+                .into_iter()
+                .chain(escape.iter().map(|e| e.as_ref()))
+                .collect(),
+            Self::Window(window) => {
+                let args = match &window.call.args {
+                    FunctionArgs::Wildcard => &[][..],
+                    FunctionArgs::List(args) => args,
+                };
+                args.iter()
+                    .chain(&window.window.partition_by)
+                    .chain(window.window.order_by.iter().map(|item| &item.expr))
+                    .collect()
+            }
+        }
+    }
+
+    /// Mutable access to the direct sub expressions, in the same order as
+    /// [`Expression::children`]
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Self::Literal(_)
+            | Self::Parameter(_)
+            | Self::Column(_)
+            | Self::Subquery(_)
+            | Self::Exists(_) => vec![],
+            Self::InSubquery { expr, .. } => vec![expr],
+            Self::Function(FunctionCall { args, .. }) => match args {
+                FunctionArgs::Wildcard => vec![],
+                FunctionArgs::List(args) => args.iter_mut().collect(),
+            },
+            Self::Unary { expr, .. } | Self::IsNull { expr, .. } => vec![expr],
+            Self::Binary { left, right, .. } => vec![left, right],
+            Self::Case {
+                operand,
+                when_clauses,
+                else_result,
+            } => operand
+                .iter_mut()
+                .map(|e| e.as_mut())
+                .chain(
+                    when_clauses
+                        .iter_mut()
+                        .flat_map(|when| [&mut when.condition, &mut when.result]),
+                )
+                .chain(else_result.iter_mut().map(|e| e.as_mut()))
+                .collect(),
+            Self::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+            Self::InList { expr, list, .. } => std::iter::once(expr.as_mut()).chain(list).collect(),
+            Self::Pattern {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => [expr.as_mut(), pattern.as_mut()]
+                .into_iter()
+                .chain(escape.iter_mut().map(|e| e.as_mut()))
+                .collect(),
+            Self::Window(window) => {
+                let window = window.as_mut();
+                let args = match &mut window.call.args {
+                    FunctionArgs::Wildcard => &mut [][..],
+                    FunctionArgs::List(args) => args,
+                };
+                args.iter_mut()
+                    .chain(&mut window.window.partition_by)
+                    .chain(window.window.order_by.iter_mut().map(|item| &mut item.expr))
+                    .collect()
+            }
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Binary { op, .. } => op.precedence(),
+            Self::Unary { op, .. } => op.precedence(),
+            Self::IsNull { .. }
+            | Self::Between { .. }
+            | Self::InList { .. }
+            | Self::InSubquery { .. }
+            | Self::Pattern { .. } => 4,
+            _ => u8::MAX,
+        }
+    }
+
+    fn fmt_not(f: &mut fmt::Formatter<'_>, negated: bool) -> fmt::Result {
+        if negated {
+            write!(f, " NOT")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write `expr`, wrapping it in parens if it binds looser than `min`
+    fn fmt_operand(f: &mut fmt::Formatter<'_>, expr: &Expression, min: u8) -> fmt::Result {
+        if expr.precedence() < min {
+            write!(f, "({expr})")
+        } else {
+            write!(f, "{expr}")
+        }
+    }
+}
+
+impl fmt::Display for FunctionCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.args {
+            FunctionArgs::Wildcard => write!(f, "{}(*)", Ident(&self.name)),
+            FunctionArgs::List(args) => {
+                write!(f, "{}(", Ident(&self.name))?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Literal(v) => write!(f, "{}", v.to_sql()),
+            Self::Parameter(parameter) => write!(f, "{parameter}"),
+            Self::Column(column) => write!(f, "{column}"),
+            Self::Function(call) => write!(f, "{call}"),
+            Self::Unary { op, expr } => {
+                write!(f, "{op}")?;
+                // `--` would start a comment
+                if *op == UnaryOperator::Minus && expr.to_string().starts_with('-') {
+                    write!(f, " ")?;
+                }
+                Self::fmt_operand(f, expr, op.precedence())
+            }
+            Self::Binary { left, op, right } => {
+                // all binary operators are left associative, except
+                // comparisons which do not chain at all
+                let left_min = match op {
+                    BinaryOperator::Eq
+                    | BinaryOperator::NotEq
+                    | BinaryOperator::Lt
+                    | BinaryOperator::LtEq
+                    | BinaryOperator::Gt
+                    | BinaryOperator::GtEq => op.precedence() + 1,
+                    _ => op.precedence(),
+                };
+                Self::fmt_operand(f, left, left_min)?;
+                write!(f, " {op} ")?;
+                Self::fmt_operand(f, right, op.precedence() + 1)
+            }
+            Self::IsNull { expr, negated } => {
+                Self::fmt_operand(f, expr, self.precedence() + 1)?;
+                write!(f, " IS {}NULL", if *negated { "NOT " } else { "" })
+            }
+            Self::Case {
+                operand,
+                when_clauses,
+                else_result,
+            } => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {operand}")?;
+                }
+                for when in when_clauses {
+                    write!(f, " WHEN {} THEN {}", when.condition, when.result)?;
+                }
+                if let Some(else_result) = else_result {
+                    write!(f, " ELSE {else_result}")?;
+                }
+                write!(f, " END")
+            }
+            Self::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let min = self.precedence() + 1;
+                Self::fmt_operand(f, expr, min)?;
+                Self::fmt_not(f, *negated)?;
+                write!(f, " BETWEEN ")?;
+                Self::fmt_operand(f, low, min)?;
+                write!(f, " AND ")?;
+                Self::fmt_operand(f, high, min)
+            }
+            Self::InList {
+                expr,
+                negated,
+                list,
+            } => {
+                Self::fmt_operand(f, expr, self.precedence() + 1)?;
+                Self::fmt_not(f, *negated)?;
+                write!(f, " IN (")?;
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Pattern {
+                expr,
+                negated,
+                op,
+                pattern,
+                escape,
+            } => {
+                let min = self.precedence() + 1;
+                Self::fmt_operand(f, expr, min)?;
+                Self::fmt_not(f, *negated)?;
+                write!(f, " {op} ")?;
+                Self::fmt_operand(f, pattern, min)?;
+                if let Some(escape) = escape {
+                    write!(f, " ESCAPE ")?;
+                    Self::fmt_operand(f, escape, min)?;
+                }
+                Ok(())
+            }
+            Self::Subquery(subquery) => write!(f, "({subquery})"),
+            Self::InSubquery {
+                expr,
+                negated,
+                subquery,
+            } => {
+                Self::fmt_operand(f, expr, self.precedence() + 1)?;
+                Self::fmt_not(f, *negated)?;
+                write!(f, " IN ({subquery})")
+            }
+            Self::Exists(subquery) => write!(f, "EXISTS ({subquery})"),
+            Self::Window(window) => write!(f, "{window}"),
+        }
+    }
+}
+
+/// Parse a left associative chain of `operand (op operand)*`
+fn left_assoc<'a, O, P>(operand: P, op: O, input: Tokens<'a>) -> ParseResult<'a, Expression>
+where
+    P: Fn(Tokens<'a>) -> ParseResult<'a, Expression> + Copy,
+    O: nom::Parser<Tokens<'a>, Output = BinaryOperator, Error = nom::error::Error<Tokens<'a>>>,
+{
+    let (input, first) = operand(input)?;
+    fold_many0(
+        (op, operand),
+        move || first.clone(),
+        |left, (op, right)| Expression::binary(left, op, right),
+    )
+    .parse(input)
+}
+
+fn or_expr(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    left_assoc(
+        and_expr,
+        value(BinaryOperator::Or, keyword(Keyword::Or)),
+        input,
+    )
+}
+
+fn and_expr(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    left_assoc(
+        not_expr,
+        value(BinaryOperator::And, keyword(Keyword::And)),
+        input,
+    )
+}
+
+fn not_expr(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    alt((
+        map(preceded(keyword(Keyword::Not), not_expr), |expr| {
+            Expression::unary(UnaryOperator::Not, expr)
+        }),
+        comparison,
+    ))
+    .parse(input)
+}
+
+fn comparison_operator(input: Tokens<'_>) -> ParseResult<'_, BinaryOperator> {
+    alt((
+        value(BinaryOperator::LtEq, symbol(Symbol::LessThanOrEqual)),
+        value(BinaryOperator::GtEq, symbol(Symbol::GreaterThanOrEqual)),
+        value(BinaryOperator::NotEq, symbol(Symbol::NotEqual)),
+        value(BinaryOperator::NotEq, symbol(Symbol::NotEqual)),
+        value(BinaryOperator::Eq, symbol(Symbol::Equal)),
+        value(BinaryOperator::Lt, symbol(Symbol::LessThan)),
+        value(BinaryOperator::Gt, symbol(Symbol::GreaterThan)),
+    ))
+    .parse(input)
+}
+
+/// The part of a comparison following its left hand side
+enum ComparisonTail {
+    Binary(BinaryOperator, Expression),
+    IsNull(bool),
+    Between(bool, Expression, Expression),
+    InList(bool, Vec<Expression>),
+    InSubquery(bool, Box<CompoundSelect>),
+    Pattern(bool, PatternOperator, Expression, Option<Expression>),
+}
+
+fn pattern_operator(input: Tokens<'_>) -> ParseResult<'_, PatternOperator> {
+    alt((
+        value(PatternOperator::Like, keyword(Keyword::Like)),
+        value(PatternOperator::ILike, keyword(Keyword::Ilike)),
+        value(PatternOperator::Glob, keyword(Keyword::Glob)),
+    ))
+    .parse(input)
+}
+
+// parses the "[NOT] BETWEEN | IN | LIKE | ILIKE | GLOB" comparisons that can be negated
+fn negatable_tail(input: Tokens<'_>) -> ParseResult<'_, ComparisonTail> {
+    let (input, not) = opt(keyword(Keyword::Not)).parse(input)?;
+    let negated = not.is_some();
+
+    alt((
+        map(
+            (
+                keyword(Keyword::Between),
+                additive,
+                preceded(keyword(Keyword::And), additive),
+            ),
+            move |(_, low, high)| ComparisonTail::Between(negated, low, high),
+        ),
+        preceded(
+            (keyword(Keyword::In), symbol(Symbol::LeftParen)),
+            terminated(
+                alt((
+                    map(CompoundSelect::parse, move |subquery| {
+                        ComparisonTail::InSubquery(negated, Box::new(subquery))
+                    }),
+                    map(comma_sep(Expression::parse), move |list| {
+                        ComparisonTail::InList(negated, list)
+                    }),
+                )),
+                symbol(Symbol::RightParen),
+            ),
+        ),
+        map(
+            (
+                pattern_operator,
+                additive,
+                opt(preceded(keyword(Keyword::Escape), additive)),
+            ),
+            move |(op, pattern, escape)| ComparisonTail::Pattern(negated, op, pattern, escape),
+        ),
+    ))
+    .parse(input)
+}
+
+// parses "<additive> [(<op> <additive>) | IS [NOT] NULL | [NOT] BETWEEN ... | ...]"
+fn comparison(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    let (input, left) = additive(input)?;
+    let (input, tail) = opt(alt((
+        map((comparison_operator, additive), |(op, right)| {
+            ComparisonTail::Binary(op, right)
+        }),
+        map(
+            (
+                keyword(Keyword::Is),
+                opt(keyword(Keyword::Not)),
+                keyword(Keyword::Null),
+            ),
+            |(_, not, _)| ComparisonTail::IsNull(not.is_some()),
+        ),
+        negatable_tail,
+    )))
+    .parse(input)?;
+
+    let expr = Box::new(left);
+    let expr = match tail {
+        None => *expr,
+        Some(ComparisonTail::Binary(op, right)) => Expression::binary(*expr, op, right),
+        Some(ComparisonTail::IsNull(negated)) => Expression::IsNull { expr, negated },
+        Some(ComparisonTail::Between(negated, low, high)) => Expression::Between {
+            expr,
+            negated,
+            low: Box::new(low),
+            high: Box::new(high),
+        },
+        Some(ComparisonTail::InList(negated, list)) => Expression::InList {
+            expr,
+            negated,
+            list,
+        },
+        Some(ComparisonTail::InSubquery(negated, subquery)) => Expression::InSubquery {
+            expr,
+            negated,
+            subquery,
+        },
+        Some(ComparisonTail::Pattern(negated, op, pattern, escape)) => Expression::Pattern {
+            expr,
+            negated,
+            op,
+            pattern: Box::new(pattern),
+            escape: escape.map(Box::new),
+        },
+    };
+
+    Ok((input, expr))
+}
+
+fn additive(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    left_assoc(
+        multiplicative,
+        alt((
+            value(BinaryOperator::Concat, symbol(Symbol::Concat)),
+            value(BinaryOperator::Plus, symbol(Symbol::Plus)),
+            value(BinaryOperator::Minus, symbol(Symbol::Minus)),
+        )),
+        input,
+    )
+}
+
+fn multiplicative(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    left_assoc(
+        unary,
+        alt((
+            value(BinaryOperator::Multiply, symbol(Symbol::Star)),
+            value(BinaryOperator::Divide, symbol(Symbol::Slash)),
+            value(BinaryOperator::Modulo, symbol(Symbol::Percent)),
+        )),
+        input,
+    )
+}
+
+fn unary(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    alt((
+        map(preceded(symbol(Symbol::Minus), unary), |expr| {
+            Expression::unary(UnaryOperator::Minus, expr)
+        }),
+        primary,
+    ))
+    .parse(input)
+}
+
+fn function_args(input: Tokens<'_>) -> ParseResult<'_, FunctionArgs> {
+    delimited(
+        symbol(Symbol::LeftParen),
+        alt((
+            value(FunctionArgs::Wildcard, symbol(Symbol::Star)),
+            map(comma_sep(Expression::parse), FunctionArgs::List),
+            map(success(()), |_| FunctionArgs::List(vec![])),
+        )),
+        symbol(Symbol::RightParen),
+    )
+    .parse(input)
+}
+
+// parses "CASE [<operand>] (WHEN <expr> THEN <expr>)+ [ELSE <expr>] END"
+fn case(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    let when_clause = map(
+        (
+            preceded(keyword(Keyword::When), Expression::parse),
+            preceded(keyword(Keyword::Then), Expression::parse),
+        ),
+        |(condition, result)| WhenClause { condition, result },
+    );
+
+    map(
+        (
+            preceded(keyword(Keyword::Case), opt(Expression::parse)),
+            context("When Clauses", many1(when_clause)),
+            opt(preceded(keyword(Keyword::Else), Expression::parse)),
+            keyword(Keyword::End),
+        ),
+        |(operand, when_clauses, else_result, _)| Expression::Case {
+            operand: operand.map(Box::new),
+            when_clauses,
+            else_result: else_result.map(Box::new),
+        },
+    )
+    .parse(input)
+}
+
+/// Parse a parenthesized select query
+pub(crate) fn subquery(input: Tokens<'_>) -> ParseResult<'_, CompoundSelect> {
+    context(
+        "Subquery",
+        delimited(
+            (
+                symbol(Symbol::LeftParen),
+                peek(alt((keyword(Keyword::Select), keyword(Keyword::With)))),
+            ),
+            CompoundSelect::parse,
+            symbol(Symbol::RightParen),
+        ),
+    )
+    .parse(input)
+}
+
+// parses "? | ?<n> | :<name>"
+fn parameter(input: Tokens<'_>) -> ParseResult<'_, Parameter> {
+    // parameters are numbered from 1
+    token(|token| match token.kind {
+        TokenKind::Parameter => match token.text.split_at(1) {
+            (":", name) => Some(Parameter::Named(name.to_owned())),
+            (_, "") => Some(Parameter::Next),
+            (_, digits) => match digits.parse::<usize>() {
+                Ok(0) | Err(_) => None,
+                Ok(n) => Some(Parameter::Numbered(n)),
+            },
+        },
+        _ => None,
+    })
+    .parse(input)
+}
+
+// parses "<name> | <table>.<name>"
+fn column_ref(input: Tokens<'_>) -> ParseResult<'_, ColumnRef> {
+    map(
+        (
+            identifier,
+            opt(preceded(symbol(Symbol::Dot), any_identifier)),
+        ),
+        |(first, second)| match second {
+            Some(name) => ColumnRef::qualified(first, name),
+            None => ColumnRef::new(first),
+        },
+    )
+    .parse(input)
+}
+
+fn primary(input: Tokens<'_>) -> ParseResult<'_, Expression> {
+    alt((
+        map(subquery, |subquery| {
+            Expression::Subquery(Box::new(subquery))
+        }),
+        map(preceded(keyword(Keyword::Exists), subquery), |subquery| {
+            Expression::Exists(Box::new(subquery))
+        }),
+        delimited(
+            symbol(Symbol::LeftParen),
+            Expression::parse,
+            symbol(Symbol::RightParen),
+        ),
+        map(literal, Expression::Literal),
+        context("Parameter", map(parameter, Expression::Parameter)),
+        context("Case", case),
+        map(
+            (
+                identifier,
+                function_args,
+                opt(preceded(keyword(Keyword::Over), WindowSpec::parse)),
+            ),
+            |(name, args, window)| {
+                let call = FunctionCall { name, args };
+                match window {
+                    Some(window) => Expression::Window(Box::new(WindowFunction { call, window })),
+                    None => Expression::Function(call),
+                }
+            },
+        ),
+        map(column_ref, Expression::Column),
+    ))
+    .parse(input)
+}
+
+// Parses an expression, the tokens after it are left to the caller
+impl Parse for Expression {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        context("Expression", or_expr).parse(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use crate::{parse::Parse, value::Value};
+
+    use super::{
+        BinaryOperator, Expression, FunctionArgs, FunctionCall, Parameter, PatternOperator,
+        WhenClause,
+    };
+
+    fn number(n: &str) -> Expression {
+        Expression::Literal(Value::Number(BigDecimal::from_str(n).unwrap()))
+    }
+
+    #[test]
+    fn test_precedence() {
+        let expected = Expression::binary(
+            Expression::binary(
+                Expression::column("a"),
+                BinaryOperator::Eq,
+                Expression::binary(
+                    number("1"),
+                    BinaryOperator::Plus,
+                    Expression::binary(number("2"), BinaryOperator::Multiply, number("3")),
+                ),
+            ),
+            BinaryOperator::Or,
+            Expression::IsNull {
+                expr: Box::new(Expression::column("b")),
+                negated: true,
+            },
+        );
+
+        let (remaining, expr) =
+            Expression::parse_from_raw("a = 1 + 2 * 3 or b is not null from").unwrap();
+
+        assert_eq!(expr, expected);
+        assert_eq!(remaining.to_string(), "from");
+        assert_eq!(expr.to_string(), "a = 1 + 2 * 3 OR b IS NOT NULL");
+    }
+
+    #[test]
+    fn test_function_call() {
+        let expected = Expression::Function(FunctionCall {
+            name: "coalesce".into(),
+            args: FunctionArgs::List(vec![
+                Expression::Function(FunctionCall {
+                    name: "count".into(),
+                    args: FunctionArgs::Wildcard,
+                }),
+                Expression::Literal(Value::String("none".into())),
+            ]),
+        });
+
+        let (_, expr) = Expression::parse_from_raw("coalesce(count( * ), 'none')").unwrap();
+
+        assert_eq!(expr, expected);
+        assert_eq!(
+            Expression::parse_from_raw(&expr.to_string()).unwrap().1,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_case() {
+        let expected = Expression::Case {
+            operand: None,
+            when_clauses: vec![WhenClause {
+                condition: Expression::binary(
+                    Expression::column("a"),
+                    BinaryOperator::Gt,
+                    number("1"),
+                ),
+                result: Expression::Literal(Value::String("big".into())),
+            }],
+            else_result: Some(Box::new(Expression::Literal(Value::Null))),
+        };
+
+        let (_, expr) =
+            Expression::parse_from_raw("CASE WHEN a > 1 THEN 'big' ELSE NULL END").unwrap();
+
+        assert_eq!(expr, expected);
+        assert_eq!(expr.to_string(), "CASE WHEN a > 1 THEN 'big' ELSE NULL END");
+    }
+
+    #[test]
+    fn test_predicates() {
+        let expected = Expression::binary(
+            Expression::Between {
+                expr: Box::new(Expression::column("a")),
+                negated: false,
+                low: Box::new(number("1")),
+                high: Box::new(number("2")),
+            },
+            BinaryOperator::And,
+            Expression::binary(
+                Expression::InList {
+                    expr: Box::new(Expression::column("b")),
+                    negated: true,
+                    list: vec![number("3"), number("4")],
+                },
+                BinaryOperator::And,
+                Expression::Pattern {
+                    expr: Box::new(Expression::column("c")),
+                    negated: false,
+                    op: PatternOperator::ILike,
+                    pattern: Box::new(Expression::Literal(Value::String("a!%%".into()))),
+                    escape: Some(Box::new(Expression::Literal(Value::String("!".into())))),
+                },
+            ),
+        );
+
+        let (_, expr) = Expression::parse_from_raw(
+            "a between 1 and 2 and (b not in (3, 4) and c ilike 'a!%%' escape '!')",
+        )
+        .unwrap();
+
+        assert_eq!(expr, expected);
+        assert_eq!(
+            Expression::parse_from_raw(&expr.to_string()).unwrap().1,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parameters() {
+        let expected = Expression::binary(
+            Expression::binary(
+                Expression::column("a"),
+                BinaryOperator::Eq,
+                Expression::Parameter(Parameter::Next),
+            ),
+            BinaryOperator::Or,
+            Expression::binary(
+                Expression::Parameter(Parameter::Numbered(2)),
+                BinaryOperator::Lt,
+                Expression::Parameter(Parameter::Named("max".into())),
+            ),
+        );
+
+        let (_, expr) = Expression::parse_from_raw("a = ? or ?2 < :max").unwrap();
+        assert_eq!(expr, expected);
+        assert_eq!(
+            Expression::parse_from_raw(&expr.to_string()).unwrap().1,
+            expected
+        );
+        assert!(Expression::parse_from_raw("?0").is_err());
+    }
+}
@@ -0,0 +1,430 @@
+use std::fmt;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag, take_until, take_while, take_while1},
+    character::complete::{char, digit0, digit1, multispace1},
+    combinator::{map, not, opt, recognize},
+    multi::many0,
+    sequence::{pair, preceded, terminated},
+    IResult, Parser,
+};
+use nom_locate::LocatedSpan;
+
+use crate::error::ParseError;
+
+//Use nom_locate's LocatedSpan as a wrapper around a string input
+pub type RawSpan<'a> = LocatedSpan<&'a str>;
+
+type LexResult<'a, T> = IResult<RawSpan<'a>, T>;
+
+macro_rules! keywords {
+    ($($variant:ident => $word:literal,)*) => {
+        /// A word with a meaning in the grammar, matched case insensitively
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Keyword {
+            $($variant,)*
+        }
+
+        impl Keyword {
+            /// Every keyword, in alphabetical order
+            pub const ALL: &'static [Keyword] = &[$(Keyword::$variant,)*];
+
+            /// The keyword in lower case
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Keyword::$variant => $word,)*
+                }
+            }
+        }
+    };
+}
+
+keywords! {
+    All => "all",
+    Analyze => "analyze",
+    And => "and",
+    As => "as",
+    Asc => "asc",
+    Between => "between",
+    By => "by",
+    Case => "case",
+    Copy => "copy",
+    Create => "create",
+    Cross => "cross",
+    Current => "current",
+    Delimiter => "delimiter",
+    Desc => "desc",
+    Distinct => "distinct",
+    Else => "else",
+    End => "end",
+    Escape => "escape",
+    Except => "except",
+    Exists => "exists",
+    Explain => "explain",
+    False => "false",
+    Following => "following",
+    From => "from",
+    Glob => "glob",
+    Group => "group",
+    Header => "header",
+    Ilike => "ilike",
+    In => "in",
+    Index => "index",
+    Inner => "inner",
+    Insert => "insert",
+    Int => "int",
+    Intersect => "intersect",
+    Into => "into",
+    Is => "is",
+    Join => "join",
+    Left => "left",
+    Like => "like",
+    Limit => "limit",
+    Not => "not",
+    Null => "null",
+    Offset => "offset",
+    On => "on",
+    Or => "or",
+    Order => "order",
+    Outer => "outer",
+    Over => "over",
+    Partition => "partition",
+    Preceding => "preceding",
+    Recursive => "recursive",
+    Row => "row",
+    Rows => "rows",
+    Select => "select",
+    String => "string",
+    Table => "table",
+    Then => "then",
+    To => "to",
+    True => "true",
+    Unbounded => "unbounded",
+    Union => "union",
+    Values => "values",
+    When => "when",
+    Where => "where",
+    With => "with",
+}
+
+impl Keyword {
+    /// Find the keyword spelled by `word` in any case
+    pub fn from_word(word: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|keyword| keyword.as_str().eq_ignore_ascii_case(word))
+    }
+
+    /// Reserved keywords can not be used as an unquoted table, column,
+    /// function or alias name
+    pub fn is_reserved(self) -> bool {
+        !matches!(
+            self,
+            Self::Analyze
+                | Self::Asc
+                | Self::Copy
+                | Self::Create
+                | Self::Current
+                | Self::Delimiter
+                | Self::Desc
+                | Self::Explain
+                | Self::Following
+                | Self::Header
+                | Self::Index
+                | Self::Insert
+                | Self::Int
+                | Self::Into
+                | Self::Partition
+                | Self::Preceding
+                | Self::Row
+                | Self::Rows
+                | Self::String
+                | Self::Table
+                | Self::To
+                | Self::Unbounded
+                | Self::Values
+        )
+    }
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str().to_uppercase())
+    }
+}
+
+/// Operators and punctuation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    LeftParen,
+    RightParen,
+    Comma,
+    Dot,
+    Semicolon,
+    /// `*`, both multiplication and "every column"
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    /// `||`
+    Concat,
+    Equal,
+    /// `<>` or `!=`
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+/// What a token is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Keyword(Keyword),
+    /// A name that is not a keyword
+    Identifier,
+    /// A `"quoted"` name, which can be a keyword or hold any character,
+    /// the token text includes the quotes
+    QuotedIdentifier,
+    /// An unsigned number like `12` or `1.5`
+    Number,
+    /// A `'quoted'` string with quotes in it doubled, the token text
+    /// includes the quotes
+    String,
+    /// `?`, `?<n>` or `:<name>`
+    Parameter,
+    Symbol(Symbol),
+}
+
+/// A piece of sql with where it was found, whitespace and comments are not
+/// tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// The token as written
+    pub text: &'a str,
+    /// Byte offset into the input
+    pub offset: usize,
+    /// Line of the input, counting from 1
+    pub line: u32,
+    /// Column in characters, counting from 1
+    pub column: usize,
+}
+
+impl Token<'_> {
+    /// Byte offset just past the token
+    pub fn end(&self) -> usize {
+        self.offset + self.text.len()
+    }
+}
+
+/// Split `input` into tokens, failing on characters that can not start a
+/// token and on unterminated strings or comments
+pub fn tokenize(input: &str) -> Result<Vec<Token<'_>>, ParseError> {
+    let (rest, tokens) = many0(preceded(blank, token))
+        .parse(LocatedSpan::new(input))
+        .map_err(|_| ParseError::at(input, input.len()))?;
+    let (rest, _) = blank(rest).map_err(|_| ParseError::at(input, input.len()))?;
+    if !rest.fragment().is_empty() {
+        return Err(ParseError::at(input, rest.location_offset()));
+    }
+    Ok(tokens)
+}
+
+/// Parse a `-- line` or `/* block */` comment
+fn comment(i: RawSpan) -> LexResult<RawSpan> {
+    alt((
+        recognize(pair(tag("--"), take_while(|c: char| c != '\n'))),
+        recognize((tag("/*"), take_until("*/"), tag("*/"))),
+    ))
+    .parse(i)
+}
+
+/// Any whitespace and comments, possibly none
+fn blank(i: RawSpan) -> LexResult<RawSpan> {
+    recognize(many0(alt((multispace1, comment)))).parse(i)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn word(i: RawSpan) -> LexResult<TokenKind> {
+    map(
+        recognize(pair(
+            take_while1(|c: char| c.is_alphabetic() || c == '_'),
+            take_while(is_identifier_char),
+        )),
+        |word: RawSpan| match Keyword::from_word(word.fragment()) {
+            Some(keyword) => TokenKind::Keyword(keyword),
+            None => TokenKind::Identifier,
+        },
+    )
+    .parse(i)
+}
+
+fn number(i: RawSpan) -> LexResult<TokenKind> {
+    map(
+        recognize(pair(digit1, opt(pair(char('.'), digit1)))),
+        |_| TokenKind::Number,
+    )
+    .parse(i)
+}
+
+/// Text between `quote`s where a doubled quote stands for one
+fn quoted<'a>(
+    quote: &'static str,
+) -> impl Parser<RawSpan<'a>, Output = RawSpan<'a>, Error = nom::error::Error<RawSpan<'a>>> {
+    let doubled = if quote == "'" { "''" } else { "\"\"" };
+    recognize((
+        tag(quote),
+        many0(alt((is_not(quote), tag(doubled)))),
+        tag(quote),
+    ))
+}
+
+fn string(i: RawSpan) -> LexResult<TokenKind> {
+    map(quoted("'"), |_| TokenKind::String).parse(i)
+}
+
+fn quoted_identifier(i: RawSpan) -> LexResult<TokenKind> {
+    map(quoted("\""), |_| TokenKind::QuotedIdentifier).parse(i)
+}
+
+/// The text of a quoted token without its quotes and with doubled quotes
+/// made single
+pub(crate) fn unquote(text: &str) -> String {
+    let quote = &text[..1];
+    text[1..text.len() - 1].replace(&quote.repeat(2), quote)
+}
+
+/// Writes a name so it reads back as the same identifier, quoted when it is
+/// a keyword or is not a plain word
+pub(crate) struct Ident<'a>(pub &'a str);
+
+impl fmt::Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.0;
+        let plain = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(is_identifier_char)
+            && Keyword::from_word(name).is_none();
+        if plain {
+            write!(f, "{name}")
+        } else {
+            write!(f, "\"{}\"", name.replace('"', "\"\""))
+        }
+    }
+}
+
+fn parameter(i: RawSpan) -> LexResult<TokenKind> {
+    map(
+        alt((
+            recognize(pair(char('?'), digit0)),
+            recognize(pair(char(':'), take_while1(is_identifier_char))),
+        )),
+        |_| TokenKind::Parameter,
+    )
+    .parse(i)
+}
+
+fn symbol(i: RawSpan) -> LexResult<TokenKind> {
+    use Symbol::*;
+
+    let symbol = |text, symbol| map(tag(text), move |_| symbol);
+    map(
+        alt((
+            // longest first so `<=` is not read as `<` then `=`
+            alt((
+                symbol("||", Concat),
+                symbol("<>", NotEqual),
+                symbol("!=", NotEqual),
+                symbol("<=", LessThanOrEqual),
+                symbol(">=", GreaterThanOrEqual),
+            )),
+            alt((
+                symbol("(", LeftParen),
+                symbol(")", RightParen),
+                symbol(",", Comma),
+                symbol(".", Dot),
+                symbol(";", Semicolon),
+                symbol("*", Star),
+                symbol("+", Plus),
+                symbol("-", Minus),
+                // an unterminated `/*` comment is an error, not a division
+                map(terminated(tag("/"), not(char('*'))), |_| Slash),
+                symbol("%", Percent),
+                symbol("=", Equal),
+                symbol("<", LessThan),
+                symbol(">", GreaterThan),
+            )),
+        )),
+        TokenKind::Symbol,
+    )
+    .parse(i)
+}
+
+fn token(i: RawSpan) -> LexResult<Token> {
+    let (rest, (text, kind)) = nom::combinator::consumed(alt((
+        word,
+        number,
+        string,
+        quoted_identifier,
+        parameter,
+        symbol,
+    )))
+    .parse(i)?;
+    Ok((
+        rest,
+        Token {
+            kind,
+            text: text.fragment(),
+            offset: text.location_offset(),
+            line: text.location_line(),
+            column: text.get_utf8_column(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Keyword, Symbol, TokenKind};
+
+    #[test]
+    fn test_tokenize() {
+        let tokens =
+            tokenize("SELECT selectx, t.a<=1.5 -- note\n/* c */ || 'it' ?2 :name;").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(|t| (t.kind, t.text)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Keyword(Keyword::Select), "SELECT"),
+                (TokenKind::Identifier, "selectx"),
+                (TokenKind::Symbol(Symbol::Comma), ","),
+                (TokenKind::Identifier, "t"),
+                (TokenKind::Symbol(Symbol::Dot), "."),
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Symbol(Symbol::LessThanOrEqual), "<="),
+                (TokenKind::Number, "1.5"),
+                (TokenKind::Symbol(Symbol::Concat), "||"),
+                (TokenKind::String, "'it'"),
+                (TokenKind::Parameter, "?2"),
+                (TokenKind::Parameter, ":name"),
+                (TokenKind::Symbol(Symbol::Semicolon), ";"),
+            ]
+        );
+        let concat = tokens[8];
+        assert_eq!((concat.offset, concat.line, concat.column), (41, 2, 9));
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        let err = tokenize("select 1,\n  'open").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 3));
+        let err = tokenize("select a # b").unwrap_err();
+        assert_eq!((err.line, err.column, err.near.as_str()), (1, 10, "# b"));
+        assert!(tokenize("select /* open").is_err());
+    }
+}
@@ -1,4 +1,12 @@
 pub mod ast;
 pub mod commands;
+#[doc(hidden)]
+pub mod display;
+pub mod error;
+pub mod expr;
+pub mod lexer;
 pub mod parse;
+pub mod script;
 pub mod value;
+pub mod visit;
+pub mod window;
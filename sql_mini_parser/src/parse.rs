@@ -1,66 +1,275 @@
 use nom::{
-    bytes::complete::{tag, tag_no_case, take_while1},
-    character::complete::multispace0,
-    combinator::{map, peek},
+    combinator::{cut, map, peek},
+    error::{ErrorKind, ParseError as _},
     multi::separated_list1,
-    sequence::{delimited, pair},
-    IResult, Parser,
+    sequence::pair,
+    IResult, Needed, Parser,
 };
-use nom_locate::LocatedSpan;
 
-//Use nom_locate's LocatedSpan as a wrapper around a string input
-pub type RawSpan<'a> = LocatedSpan<&'a str>;
+use crate::{
+    error::ParseError,
+    lexer::{tokenize, unquote, Keyword, Symbol, Token, TokenKind},
+};
+
+/// The tokens of a statement, the input of every parser
+///
+/// The source is kept so errors can point into it
+#[derive(Debug, Clone, Copy)]
+pub struct Tokens<'a> {
+    tokens: &'a [Token<'a>],
+    source: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(tokens: &'a [Token<'a>], source: &'a str) -> Self {
+        Self { tokens, source }
+    }
+
+    pub fn first(&self) -> Option<&'a Token<'a>> {
+        self.tokens.first()
+    }
+
+    pub fn last(&self) -> Option<&'a Token<'a>> {
+        self.tokens.last()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The source the tokens were read from
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Byte offset of the first token, the end of the source when there are
+    /// none left
+    pub fn offset(&self) -> usize {
+        self.first().map_or(self.source.len(), |token| token.offset)
+    }
+}
+
+impl<'a> nom::Input for Tokens<'a> {
+    type Item = &'a Token<'a>;
+    type Iter = std::slice::Iter<'a, Token<'a>>;
+    type IterIndices = std::iter::Enumerate<Self::Iter>;
+
+    fn input_len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    fn take(&self, index: usize) -> Self {
+        Self::new(&self.tokens[..index], self.source)
+    }
+
+    fn take_from(&self, index: usize) -> Self {
+        Self::new(&self.tokens[index..], self.source)
+    }
+
+    fn take_split(&self, index: usize) -> (Self, Self) {
+        (self.take_from(index), self.take(index))
+    }
+
+    fn position<P>(&self, predicate: P) -> Option<usize>
+    where
+        P: Fn(Self::Item) -> bool,
+    {
+        self.tokens.iter().position(predicate)
+    }
 
-//The result for all of our parsers, they will have our span type as input and can have any output
+    fn iter_elements(&self) -> Self::Iter {
+        self.tokens.iter()
+    }
+
+    fn iter_indices(&self) -> Self::IterIndices {
+        self.tokens.iter().enumerate()
+    }
+
+    fn slice_index(&self, count: usize) -> Result<usize, Needed> {
+        match self.tokens.len() {
+            len if len >= count => Ok(count),
+            len => Err(Needed::new(count - len)),
+        }
+    }
+}
+
+//The result for all of our parsers, they will have our token type as input and can have any output
 // this will use a default error type but we will change that latter
-pub type ParseResult<'a, T> = IResult<RawSpan<'a>, T>;
+pub type ParseResult<'a, T> = IResult<Tokens<'a>, T>;
+
+/// Match the next token with `f`, failing when it returns `None`
+pub(crate) fn token<'a, O>(
+    f: impl Fn(&'a Token<'a>) -> Option<O>,
+) -> impl Fn(Tokens<'a>) -> ParseResult<'a, O> {
+    move |input: Tokens<'a>| match input.first().and_then(&f) {
+        Some(output) => Ok((nom::Input::take_from(&input, 1), output)),
+        None => Err(nom::Err::Error(nom::error::Error::from_error_kind(
+            input,
+            ErrorKind::Tag,
+        ))),
+    }
+}
+
+/// Match a keyword
+pub(crate) fn keyword<'a>(keyword: Keyword) -> impl Fn(Tokens<'a>) -> ParseResult<'a, Keyword> {
+    token(move |token| (token.kind == TokenKind::Keyword(keyword)).then_some(keyword))
+}
+
+/// Match an operator or punctuation
+pub(crate) fn symbol<'a>(symbol: Symbol) -> impl Fn(Tokens<'a>) -> ParseResult<'a, Symbol> {
+    token(move |token| (token.kind == TokenKind::Symbol(symbol)).then_some(symbol))
+}
 
-/// Parse a unquoted sql identifier
-pub(crate) fn identifier(i: RawSpan) -> ParseResult<String> {
-    map(take_while1(|c: char| c.is_alphanumeric()), |s: RawSpan| {
-        s.fragment().to_string()
+/// Parse a sql identifier, quoted or not, non reserved keywords like `index`
+/// or `rows` can be used as unquoted names too
+pub(crate) fn identifier(i: Tokens) -> ParseResult<String> {
+    token(|token| match token.kind {
+        TokenKind::Identifier => Some(token.text.to_owned()),
+        TokenKind::QuotedIdentifier => Some(unquote(token.text)),
+        TokenKind::Keyword(keyword) if !keyword.is_reserved() => Some(token.text.to_owned()),
+        _ => None,
     })
     .parse(i)
 }
 
-/// Implement the parse function to more easily convert a span into a sql
+/// Parse any word as a name, where no keyword can follow like the column
+/// of `<table>.<column>`
+pub(crate) fn any_identifier(i: Tokens) -> ParseResult<String> {
+    token(|token| match token.kind {
+        TokenKind::Identifier | TokenKind::Keyword(_) => Some(token.text.to_owned()),
+        TokenKind::QuotedIdentifier => Some(unquote(token.text)),
+        _ => None,
+    })
+    .parse(i)
+}
+
+/// Implement the parse function to more easily convert tokens into a sql
 /// command
-pub trait Parse<'a>: Sized {
-    /// Parse the given span into self
-    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self>;
+pub trait Parse: Sized {
+    /// Parse the given tokens into self
+    fn parse(input: Tokens<'_>) -> ParseResult<'_, Self>;
 
-    /// Helper method for tests to convert a str into a raw span and parse
-    fn parse_from_raw(input: &'a str) -> ParseResult<'a, Self> {
-        let i = LocatedSpan::new(input);
-        Self::parse(i)
+    /// Tokenize and parse a str, returning the input after the last token
+    /// parsed
+    fn parse_from_raw(input: &str) -> Result<(&str, Self), ParseError> {
+        let tokens = tokenize(input)?;
+        let (rest, parsed) = Self::parse(Tokens::new(&tokens, input))?;
+        Ok((&input[rest.offset()..], parsed))
     }
 }
 
-/// Check if the input has the passed in tag
-/// if so run the parser supplied (with the peeked tag still expected)
+/// Check if the input starts with what `peek_parser` matches
+/// if so run the parser supplied (with the peeked tokens still expected)
 /// and cut on error
 ///
-/// This is useful on alts so we stop on errors
-pub(crate) fn peek_then_cut<'a, T, O, E, F>(
-    peek_tag: T,
+/// This is useful on alts so we stop on errors, reporting where the chosen
+/// branch failed instead of where the last branch did
+pub(crate) fn peek_then_cut<'a, P, O, F>(
+    peek_parser: P,
     f: F,
-) -> impl nom::Parser<RawSpan<'a>, Output = O, Error = E>
+) -> impl nom::Parser<Tokens<'a>, Output = O, Error = nom::error::Error<Tokens<'a>>>
 where
-    T: nom::Input + Clone,
-    F: nom::Parser<RawSpan<'a>, Output = O, Error = E>,
-    E: nom::error::ParseError<RawSpan<'a>>,
-    LocatedSpan<&'a str>: nom::Compare<T>,
+    P: nom::Parser<Tokens<'a>, Error = nom::error::Error<Tokens<'a>>>,
+    F: nom::Parser<Tokens<'a>, Output = O, Error = nom::error::Error<Tokens<'a>>>,
 {
-    map(pair(peek(tag_no_case(peek_tag)), f), |(_, f_res)| f_res)
+    map(pair(peek(peek_parser), cut(f)), |(_, f_res)| f_res)
 }
 
-pub(crate) fn comma_sep<'a, O, E, F>(
+pub(crate) fn comma_sep<'a, O, F>(
     f: F,
-) -> impl nom::Parser<RawSpan<'a>, Output = Vec<O>, Error = E>
+) -> impl nom::Parser<Tokens<'a>, Output = Vec<O>, Error = nom::error::Error<Tokens<'a>>>
 where
-    F: nom::Parser<RawSpan<'a>, Error = E, Output = O>,
-    E: nom::error::ParseError<RawSpan<'a>>,
+    F: nom::Parser<Tokens<'a>, Error = nom::error::Error<Tokens<'a>>, Output = O>,
 {
-    let separator = delimited(multispace0, tag(","), multispace0);
-    separated_list1(separator, f)
+    separated_list1(symbol(Symbol::Comma), f)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::SqlQuery, error::ParseError};
+
+    use super::Parse;
+
+    /// Every statement parses the same with comments in place of whitespace
+    #[test]
+    fn test_comments() {
+        let statements = [
+            (
+                "with recent as (select id from orders where id > 10) \
+                 select distinct c.name, count(*) over (partition by c.id order by o.id) \
+                 from customers c left join recent o on c.id = o.id \
+                 where c.name not like 'a%' and c.id between 1 and 9 \
+                 group by c.name union all select name, 1 from other \
+                 order by 1 desc limit 5 offset 2;",
+                "with/* cte */recent as ( -- recent orders\n select id from orders where id > 10 )\n\
+                 select /* no dupes */ distinct c.name , count(*) over ( partition by c.id order by o.id )\n\
+                 from customers c -- the customers\n left join recent o on c.id = o.id\n\
+                 where c.name not like 'a%'/**/and c.id between 1 and 9 -- range\n\
+                 group by c.name union all select name, 1 from other\n\
+                 order by 1 desc limit 5 /* page */ offset 2 -- done\n;",
+            ),
+            (
+                "create table users (id int, name string);",
+                "create -- a table\n table users ( id int , /* the name */ name string ) ;",
+            ),
+            (
+                "create index users_name on users (name);",
+                "create/**/index users_name -- lookups\n on users (name);",
+            ),
+            (
+                "insert into users values 1 - 2, 'ann';",
+                "insert into users -- one row\n values 1 - /* minus */ 2, 'ann';",
+            ),
+            (
+                "explain analyze select id from users;",
+                "explain /* with timings */ analyze\n-- the query\nselect id from users;",
+            ),
+            ("analyze users;", "analyze -- refresh stats\n users;"),
+        ];
+
+        for (plain, commented) in statements {
+            let (_, expected) = SqlQuery::parse_from_raw(plain).unwrap();
+            let (rest, query) = SqlQuery::parse_from_raw(commented).unwrap();
+            assert_eq!(query, expected, "{commented}");
+            assert!(rest.is_empty(), "{commented}");
+        }
+    }
+
+    #[test]
+    fn test_keywords() {
+        // keywords are whole tokens, never the prefix of a name
+        assert!(SqlQuery::parse_statement("selectx a from t").is_err());
+        assert!(SqlQuery::parse_statement("select a from orders_t").is_ok());
+        // reserved keywords are never names, the others can be
+        assert!(SqlQuery::parse_statement("select rows, index from values").is_ok());
+        assert!(SqlQuery::parse_statement("create table order (a int)").is_err());
+        assert!(SqlQuery::parse_statement("select a from t as where").is_err());
+        // unless quoted
+        let query =
+            SqlQuery::parse_statement("create table \"order\" (\"a \"\"b\"\"\" int)").unwrap();
+        assert_eq!(
+            query.to_string(),
+            "CREATE TABLE \"order\" (\"a \"\"b\"\"\" INT)"
+        );
+    }
+
+    #[test]
+    fn test_error_position() {
+        assert_eq!(
+            SqlQuery::parse_statement("select a\nfrom t\nwhere a = 1 b"),
+            Err(ParseError {
+                line: 3,
+                column: 13,
+                near: "b".into()
+            })
+        );
+        assert_eq!(
+            SqlQuery::parse_statement("select a from"),
+            Err(ParseError {
+                line: 1,
+                column: 14,
+                near: String::new()
+            })
+        );
+    }
 }
@@ -0,0 +1,171 @@
+use crate::{
+    ast::SqlQuery,
+    error::ParseError,
+    lexer::{tokenize, Symbol, Token, TokenKind},
+    parse::Tokens,
+};
+
+/// Where a statement is in a script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset just past the semicolon, or the last character when the
+    /// statement ends the script without one
+    pub end: usize,
+    /// Line of the first character, counting from 1
+    pub line: u32,
+    /// Column of the first character in characters, counting from 1
+    pub column: usize,
+}
+
+impl Span {
+    /// The text of the statement in `script`
+    pub fn slice<'a>(&self, script: &'a str) -> &'a str {
+        &script[self.start..self.end]
+    }
+}
+
+/// A statement of a script and where it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub query: SqlQuery,
+    pub span: Span,
+}
+
+/// Parse every statement of a script
+///
+/// Statements end with a semicolon, which the last one may leave out, and
+/// `-- line` and `/* block */` comments can go before and after each
+pub fn parse_script(input: &str) -> Result<Vec<Statement>, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut rest = Tokens::new(&tokens, input);
+    let mut statements = Vec::new();
+    while let Some((remaining, statement)) = next_statement(rest)? {
+        statements.push(statement);
+        rest = remaining;
+    }
+    Ok(statements)
+}
+
+/// Whether `input` holds whole statements, each ending with a semicolon, the
+/// point where a prompt should stop asking for more
+///
+/// A statement the parser stops at the end of is cut short, one it rejects
+/// earlier is complete once a semicolon follows, so the error can be shown.
+/// Input with no statement is complete, and so is input that can not be
+/// tokenized for a reason more text can not fix
+pub fn is_complete(input: &str) -> bool {
+    let tokens = match tokenize(input) {
+        Ok(tokens) => tokens,
+        // an unterminated string, quoted name or comment
+        Err(err) => return !(err.near.starts_with(['\'', '"']) || err.near.starts_with("/*")),
+    };
+    let is_semicolon = |token: &Token<'_>| token.kind == TokenKind::Symbol(Symbol::Semicolon);
+    let mut rest = Tokens::new(&tokens, input);
+    while !rest.is_empty() {
+        let stopped_at = match SqlQuery::statement(rest) {
+            Ok((after, _)) if after.first().is_some_and(is_semicolon) => {
+                rest = nom::Input::take_from(&after, 1);
+                continue;
+            }
+            Ok((after, _)) => after,
+            Err(nom::Err::Error(err) | nom::Err::Failure(err)) => err.input,
+            Err(nom::Err::Incomplete(_)) => return false,
+        };
+        return nom::Input::iter_elements(&stopped_at).any(is_semicolon);
+    }
+    true
+}
+
+/// Parse the statement at the start of `input`, `None` when no tokens are
+/// left
+pub(crate) fn next_statement(
+    input: Tokens<'_>,
+) -> Result<Option<(Tokens<'_>, Statement)>, ParseError> {
+    let Some(start) = input.first() else {
+        return Ok(None);
+    };
+    let (after, query) = SqlQuery::statement(input)?;
+    let (rest, end) = match after.first() {
+        Some(token) if token.kind == TokenKind::Symbol(Symbol::Semicolon) => {
+            (nom::Input::take_from(&after, 1), token.end())
+        }
+        Some(_) => return Err(ParseError::at(input.source(), after.offset())),
+        None => (after, input.last().map_or(start.end(), Token::end)),
+    };
+    let span = Span {
+        start: start.offset,
+        end,
+        line: start.line,
+        column: start.column,
+    };
+    Ok(Some((rest, Statement { query, span })))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::SqlQuery, error::ParseError};
+
+    use super::{is_complete, parse_script, Span};
+
+    #[test]
+    fn test_parse_script() {
+        let script = "-- people\n\
+                      create table t (a int); /* two\n rows */ insert into t values 1;\n\
+                      \n  select a from t -- no semicolon\n";
+        let statements = parse_script(script).unwrap();
+
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[1].query, SqlQuery::Insert(_)));
+        assert_eq!(
+            statements[2].span,
+            Span {
+                start: 77,
+                end: 92,
+                line: 5,
+                column: 3
+            }
+        );
+        assert_eq!(statements[1].span.slice(script), "insert into t values 1;");
+        assert_eq!(statements[2].span.slice(script), "select a from t");
+        assert!(parse_script(" -- nothing\n/* at all */")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_script_errors() {
+        assert_eq!(
+            parse_script("select a from t;\nselect a from t select a from t;"),
+            Err(ParseError {
+                line: 2,
+                column: 17,
+                near: "select a from t;".into()
+            })
+        );
+        assert!(parse_script("select a from t; /* unterminated").is_err());
+
+        assert!(SqlQuery::parse_statement(" select a from t -- trailing").is_ok());
+        assert!(SqlQuery::parse_statement("select a from t; select a from t;").is_err());
+        assert!(SqlQuery::parse_statement("-- only a comment").is_err());
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete("select a\nfrom t;"));
+        assert!(is_complete("select 1; -- done"));
+        assert!(is_complete("  -- nothing yet"));
+        assert!(!is_complete("create table t (\n  a int"));
+        assert!(!is_complete("select 1 -- ;"));
+        assert!(!is_complete("select 'a;"));
+        assert!(!is_complete("select \"a;"));
+        assert!(!is_complete("select 1 /* ; */"));
+        assert!(!is_complete("select 1; /* open ;"));
+        assert!(is_complete("select a # b"));
+        assert!(!is_complete("select a from t where"));
+        assert!(!is_complete("select a from t; select b from t"));
+        assert!(is_complete("select (a from t;"));
+        assert!(is_complete("select a from t select b from t;"));
+    }
+}
@@ -2,62 +2,121 @@ use std::str::FromStr;
 
 use bigdecimal::BigDecimal;
 use derive_more::Display;
-use nom::{
-    branch::alt,
-    bytes::complete::{tag, take_until, take_while},
-    character::complete::multispace0,
-    error::context,
-    sequence::{preceded, terminated},
-    Parser,
-};
+use nom::{branch::alt, combinator::map, error::context, Parser};
 use serde::{Deserialize, Serialize};
 
-use crate::parse::{peek_then_cut, Parse, ParseResult, RawSpan};
+use crate::{
+    lexer::{unquote, Keyword, TokenKind},
+    parse::{keyword, token, Parse, ParseResult, Tokens},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Display)]
 pub enum Value {
     Number(BigDecimal),
     String(String),
+    #[display("{}", if *_0 { "TRUE" } else { "FALSE" })]
+    Bool(bool),
+    #[display("NULL")]
+    Null,
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// The value as a sql literal, unlike `to_string` strings are quoted
+    /// with the quotes in them doubled
+    pub fn to_sql(&self) -> String {
+        match self {
+            Value::String(s) => format!("'{}'", s.replace('\'', "''")),
+            Value::Number(n) => n.to_plain_string(),
+            v => v.to_string(),
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n.into())
+    }
+}
+
+impl From<BigDecimal> for Value {
+    fn from(n: BigDecimal) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+/// `None` is NULL
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
 }
 
 ///Parse a single quoted string value
-fn parse_string_value(input: RawSpan<'_>) -> ParseResult<'_, Value> {
-    let (remaining_input, (_, str_value, _)) = context(
+fn parse_string_value(input: Tokens<'_>) -> ParseResult<'_, Value> {
+    context(
         "String Literal",
-        (
-            tag("'"),
-            take_until("'").map(|s: RawSpan| Value::String(s.fragment().to_string())),
-            tag("'"), // take_until does not consume the ending quote
-        ),
+        token(|token| {
+            (token.kind == TokenKind::String).then(|| Value::String(unquote(token.text)))
+        }),
     )
-    .parse(input)?;
+    .parse(input)
+}
 
-    Ok((remaining_input, str_value))
+/// Parse a numeric literal, optionally with a fractional part
+fn parse_number_value(input: Tokens<'_>) -> ParseResult<'_, Value> {
+    context(
+        "Number Literal",
+        token(|token| match token.kind {
+            TokenKind::Number => BigDecimal::from_str(token.text).ok().map(Value::Number),
+            _ => None,
+        }),
+    )
+    .parse(input)
 }
 
-/// Parse a numeric literal
-fn parse_number_value(input: RawSpan<'_>) -> ParseResult<'_, Value> {
-    let (reamining_input, digits) =
-        context("Number Literal", take_while(|c: char| c.is_numeric())).parse(input)?;
+/// Parse the NULL, TRUE and FALSE keywords
+fn parse_keyword_value(input: Tokens<'_>) -> ParseResult<'_, Value> {
+    context(
+        "Keyword Literal",
+        alt((
+            map(keyword(Keyword::Null), |_| Value::Null),
+            map(keyword(Keyword::True), |_| Value::Bool(true)),
+            map(keyword(Keyword::False), |_| Value::Bool(false)),
+        )),
+    )
+    .parse(input)
+}
 
-    Ok((
-        reamining_input,
-        Value::Number(BigDecimal::from_str(&digits).unwrap()),
-    ))
+/// Parse a literal value
+pub(crate) fn literal(input: Tokens<'_>) -> ParseResult<'_, Value> {
+    alt((parse_string_value, parse_number_value, parse_keyword_value)).parse(input)
 }
 
-impl<'a> Parse<'a> for Value {
-    fn parse(input: crate::parse::RawSpan<'a>) -> crate::parse::ParseResult<'a, Self> {
-        let mut parser = context(
-            "Value",
-            preceded(
-                multispace0,
-                terminated(
-                    alt((peek_then_cut("'", parse_string_value), parse_number_value)),
-                    multispace0,
-                ),
-            ),
-        );
+impl Parse for Value {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let mut parser = context("Value", literal);
 
         parser.parse(input)
     }
@@ -96,4 +155,19 @@ mod tests {
 
         assert_eq!(remaining_input.to_string(), expected_remaining);
     }
+
+    #[test]
+    fn test_string_escapes() {
+        let (_, value) = Value::parse_from_raw("'it''s'").unwrap();
+        assert_eq!(value, Value::String("it's".into()));
+        assert_eq!(value.to_sql(), "'it''s'");
+        assert_eq!(value.to_string(), "it's");
+    }
+
+    #[test]
+    fn test_keywords() {
+        assert_eq!(Value::parse_from_raw("NULL").unwrap().1, Value::Null);
+        assert_eq!(Value::parse_from_raw("true").unwrap().1, Value::Bool(true));
+        assert!(Value::parse_from_raw("nullable").is_err());
+    }
 }
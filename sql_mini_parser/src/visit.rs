@@ -0,0 +1,554 @@
+//! Walking every node of a parsed statement
+//!
+//! Each `visit_*` method of [`Visitor`] and [`VisitorMut`] defaults to the
+//! matching `walk_*` function, which visits the children of the node. An
+//! implementation overrides only the nodes it cares about and calls the
+//! `walk_*` function from its override to keep going into the children.
+
+use crate::{
+    ast::{CommonTableExpression, SqlQuery},
+    commands::{
+        AnalyzeStatement, Column, CompoundSelect, CopyStatement, CreateIndexStatement,
+        CreateStatement, Distinct, ExplainStatement, InsertStatement, OrderByItem, SelectField,
+        SelectStatement, TableReference,
+    },
+    expr::{ColumnRef, Expression, FunctionArgs, FunctionCall, Parameter},
+    value::Value,
+    window::WindowSpec,
+};
+
+/// Read only traversal of a statement
+pub trait Visitor {
+    fn visit_query(&mut self, query: &SqlQuery) {
+        walk_query(self, query);
+    }
+
+    fn visit_compound_select(&mut self, select: &CompoundSelect) {
+        walk_compound_select(self, select);
+    }
+
+    fn visit_cte(&mut self, cte: &CommonTableExpression) {
+        walk_cte(self, cte);
+    }
+
+    fn visit_select(&mut self, select: &SelectStatement) {
+        walk_select(self, select);
+    }
+
+    fn visit_select_field(&mut self, field: &SelectField) {
+        walk_select_field(self, field);
+    }
+
+    fn visit_table_reference(&mut self, table: &TableReference) {
+        walk_table_reference(self, table);
+    }
+
+    /// A table read or changed by the statement, including a CTE named in a
+    /// `FROM` clause
+    fn visit_table_name(&mut self, _name: &str) {}
+
+    fn visit_order_by_item(&mut self, item: &OrderByItem) {
+        walk_order_by_item(self, item);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+
+    fn visit_column_ref(&mut self, _column: &ColumnRef) {}
+
+    fn visit_literal(&mut self, _value: &Value) {}
+
+    fn visit_parameter(&mut self, _parameter: &Parameter) {}
+
+    fn visit_function_call(&mut self, call: &FunctionCall) {
+        walk_function_call(self, call);
+    }
+
+    fn visit_window_spec(&mut self, window: &WindowSpec) {
+        walk_window_spec(self, window);
+    }
+
+    fn visit_create(&mut self, create: &CreateStatement) {
+        walk_create(self, create);
+    }
+
+    fn visit_column_definition(&mut self, _column: &Column) {}
+
+    fn visit_create_index(&mut self, index: &CreateIndexStatement) {
+        self.visit_table_name(&index.table);
+    }
+
+    fn visit_insert(&mut self, insert: &InsertStatement) {
+        walk_insert(self, insert);
+    }
+
+    fn visit_explain(&mut self, explain: &ExplainStatement) {
+        self.visit_compound_select(&explain.query);
+    }
+
+    fn visit_analyze(&mut self, analyze: &AnalyzeStatement) {
+        if let Some(table) = &analyze.table {
+            self.visit_table_name(table);
+        }
+    }
+
+    fn visit_copy(&mut self, copy: &CopyStatement) {
+        self.visit_table_name(&copy.table);
+    }
+}
+
+pub fn walk_query<V: Visitor + ?Sized>(visitor: &mut V, query: &SqlQuery) {
+    match query {
+        SqlQuery::Select(select) => visitor.visit_compound_select(select),
+        SqlQuery::Create(create) => visitor.visit_create(create),
+        SqlQuery::CreateIndex(index) => visitor.visit_create_index(index),
+        SqlQuery::Insert(insert) => visitor.visit_insert(insert),
+        SqlQuery::Explain(explain) => visitor.visit_explain(explain),
+        SqlQuery::Analyze(analyze) => visitor.visit_analyze(analyze),
+        SqlQuery::Copy(copy) => visitor.visit_copy(copy),
+    }
+}
+
+pub fn walk_compound_select<V: Visitor + ?Sized>(visitor: &mut V, select: &CompoundSelect) {
+    if let Some(with) = &select.with {
+        for cte in &with.ctes {
+            visitor.visit_cte(cte);
+        }
+    }
+    for select in select.selects() {
+        visitor.visit_select(select);
+    }
+    for item in &select.order_by {
+        visitor.visit_order_by_item(item);
+    }
+    for expr in select.limit.iter().chain(&select.offset) {
+        visitor.visit_expression(expr);
+    }
+}
+
+pub fn walk_cte<V: Visitor + ?Sized>(visitor: &mut V, cte: &CommonTableExpression) {
+    visitor.visit_compound_select(&cte.query);
+}
+
+pub fn walk_select<V: Visitor + ?Sized>(visitor: &mut V, select: &SelectStatement) {
+    if let Some(Distinct::On(exprs)) = &select.distinct {
+        for expr in exprs {
+            visitor.visit_expression(expr);
+        }
+    }
+    for field in &select.fields {
+        visitor.visit_select_field(field);
+    }
+    visitor.visit_table_reference(&select.from);
+    for expr in select.where_clause.iter().chain(&select.group_by) {
+        visitor.visit_expression(expr);
+    }
+}
+
+pub fn walk_select_field<V: Visitor + ?Sized>(visitor: &mut V, field: &SelectField) {
+    if let SelectField::Expression { expr, .. } = field {
+        visitor.visit_expression(expr);
+    }
+}
+
+pub fn walk_table_reference<V: Visitor + ?Sized>(visitor: &mut V, table: &TableReference) {
+    match table {
+        TableReference::Table { name, .. } => visitor.visit_table_name(name),
+        TableReference::Subquery { query, .. } => visitor.visit_compound_select(query),
+        TableReference::Join {
+            left, right, on, ..
+        } => {
+            visitor.visit_table_reference(left);
+            visitor.visit_table_reference(right);
+            if let Some(on) = on {
+                visitor.visit_expression(on);
+            }
+        }
+    }
+}
+
+pub fn walk_order_by_item<V: Visitor + ?Sized>(visitor: &mut V, item: &OrderByItem) {
+    visitor.visit_expression(&item.expr);
+}
+
+/// Visits the sub expressions in the order of [`Expression::children`] and
+/// the subqueries
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::Literal(value) => visitor.visit_literal(value),
+        Expression::Parameter(parameter) => visitor.visit_parameter(parameter),
+        Expression::Column(column) => visitor.visit_column_ref(column),
+        Expression::Function(call) => visitor.visit_function_call(call),
+        Expression::Subquery(query) | Expression::Exists(query) => {
+            visitor.visit_compound_select(query)
+        }
+        Expression::InSubquery { expr, subquery, .. } => {
+            visitor.visit_expression(expr);
+            visitor.visit_compound_select(subquery);
+        }
+        Expression::Window(window) => {
+            visitor.visit_function_call(&window.call);
+            visitor.visit_window_spec(&window.window);
+        }
+        expr => {
+            for child in expr.children() {
+                visitor.visit_expression(child);
+            }
+        }
+    }
+}
+
+pub fn walk_function_call<V: Visitor + ?Sized>(visitor: &mut V, call: &FunctionCall) {
+    if let FunctionArgs::List(args) = &call.args {
+        for arg in args {
+            visitor.visit_expression(arg);
+        }
+    }
+}
+
+pub fn walk_window_spec<V: Visitor + ?Sized>(visitor: &mut V, window: &WindowSpec) {
+    for expr in &window.partition_by {
+        visitor.visit_expression(expr);
+    }
+    for item in &window.order_by {
+        visitor.visit_order_by_item(item);
+    }
+}
+
+pub fn walk_create<V: Visitor + ?Sized>(visitor: &mut V, create: &CreateStatement) {
+    visitor.visit_table_name(&create.table);
+    for column in &create.columns {
+        visitor.visit_column_definition(column);
+    }
+}
+
+pub fn walk_insert<V: Visitor + ?Sized>(visitor: &mut V, insert: &InsertStatement) {
+    visitor.visit_table_name(&insert.table);
+    for expr in &insert.values {
+        visitor.visit_expression(expr);
+    }
+}
+
+/// Traversal of a statement that can change it in place, see [`Visitor`]
+pub trait VisitorMut {
+    fn visit_query_mut(&mut self, query: &mut SqlQuery) {
+        walk_query_mut(self, query);
+    }
+
+    fn visit_compound_select_mut(&mut self, select: &mut CompoundSelect) {
+        walk_compound_select_mut(self, select);
+    }
+
+    fn visit_cte_mut(&mut self, cte: &mut CommonTableExpression) {
+        walk_cte_mut(self, cte);
+    }
+
+    fn visit_select_mut(&mut self, select: &mut SelectStatement) {
+        walk_select_mut(self, select);
+    }
+
+    fn visit_select_field_mut(&mut self, field: &mut SelectField) {
+        walk_select_field_mut(self, field);
+    }
+
+    fn visit_table_reference_mut(&mut self, table: &mut TableReference) {
+        walk_table_reference_mut(self, table);
+    }
+
+    /// See [`Visitor::visit_table_name`]
+    fn visit_table_name_mut(&mut self, _name: &mut String) {}
+
+    fn visit_order_by_item_mut(&mut self, item: &mut OrderByItem) {
+        walk_order_by_item_mut(self, item);
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr);
+    }
+
+    fn visit_column_ref_mut(&mut self, _column: &mut ColumnRef) {}
+
+    fn visit_literal_mut(&mut self, _value: &mut Value) {}
+
+    fn visit_parameter_mut(&mut self, _parameter: &mut Parameter) {}
+
+    fn visit_function_call_mut(&mut self, call: &mut FunctionCall) {
+        walk_function_call_mut(self, call);
+    }
+
+    fn visit_window_spec_mut(&mut self, window: &mut WindowSpec) {
+        walk_window_spec_mut(self, window);
+    }
+
+    fn visit_create_mut(&mut self, create: &mut CreateStatement) {
+        walk_create_mut(self, create);
+    }
+
+    fn visit_column_definition_mut(&mut self, _column: &mut Column) {}
+
+    fn visit_create_index_mut(&mut self, index: &mut CreateIndexStatement) {
+        self.visit_table_name_mut(&mut index.table);
+    }
+
+    fn visit_insert_mut(&mut self, insert: &mut InsertStatement) {
+        walk_insert_mut(self, insert);
+    }
+
+    fn visit_explain_mut(&mut self, explain: &mut ExplainStatement) {
+        self.visit_compound_select_mut(&mut explain.query);
+    }
+
+    fn visit_analyze_mut(&mut self, analyze: &mut AnalyzeStatement) {
+        if let Some(table) = &mut analyze.table {
+            self.visit_table_name_mut(table);
+        }
+    }
+
+    fn visit_copy_mut(&mut self, copy: &mut CopyStatement) {
+        self.visit_table_name_mut(&mut copy.table);
+    }
+}
+
+pub fn walk_query_mut<V: VisitorMut + ?Sized>(visitor: &mut V, query: &mut SqlQuery) {
+    match query {
+        SqlQuery::Select(select) => visitor.visit_compound_select_mut(select),
+        SqlQuery::Create(create) => visitor.visit_create_mut(create),
+        SqlQuery::CreateIndex(index) => visitor.visit_create_index_mut(index),
+        SqlQuery::Insert(insert) => visitor.visit_insert_mut(insert),
+        SqlQuery::Explain(explain) => visitor.visit_explain_mut(explain),
+        SqlQuery::Analyze(analyze) => visitor.visit_analyze_mut(analyze),
+        SqlQuery::Copy(copy) => visitor.visit_copy_mut(copy),
+    }
+}
+
+pub fn walk_compound_select_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    select: &mut CompoundSelect,
+) {
+    if let Some(with) = &mut select.with {
+        for cte in &mut with.ctes {
+            visitor.visit_cte_mut(cte);
+        }
+    }
+    visitor.visit_select_mut(&mut select.first);
+    for term in &mut select.compounds {
+        visitor.visit_select_mut(&mut term.select);
+    }
+    for item in &mut select.order_by {
+        visitor.visit_order_by_item_mut(item);
+    }
+    for expr in select.limit.iter_mut().chain(&mut select.offset) {
+        visitor.visit_expression_mut(expr);
+    }
+}
+
+pub fn walk_cte_mut<V: VisitorMut + ?Sized>(visitor: &mut V, cte: &mut CommonTableExpression) {
+    visitor.visit_compound_select_mut(&mut cte.query);
+}
+
+pub fn walk_select_mut<V: VisitorMut + ?Sized>(visitor: &mut V, select: &mut SelectStatement) {
+    if let Some(Distinct::On(exprs)) = &mut select.distinct {
+        for expr in exprs {
+            visitor.visit_expression_mut(expr);
+        }
+    }
+    for field in &mut select.fields {
+        visitor.visit_select_field_mut(field);
+    }
+    visitor.visit_table_reference_mut(&mut select.from);
+    for expr in select.where_clause.iter_mut().chain(&mut select.group_by) {
+        visitor.visit_expression_mut(expr);
+    }
+}
+
+pub fn walk_select_field_mut<V: VisitorMut + ?Sized>(visitor: &mut V, field: &mut SelectField) {
+    if let SelectField::Expression { expr, .. } = field {
+        visitor.visit_expression_mut(expr);
+    }
+}
+
+pub fn walk_table_reference_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    table: &mut TableReference,
+) {
+    match table {
+        TableReference::Table { name, .. } => visitor.visit_table_name_mut(name),
+        TableReference::Subquery { query, .. } => visitor.visit_compound_select_mut(query),
+        TableReference::Join {
+            left, right, on, ..
+        } => {
+            visitor.visit_table_reference_mut(left);
+            visitor.visit_table_reference_mut(right);
+            if let Some(on) = on {
+                visitor.visit_expression_mut(on);
+            }
+        }
+    }
+}
+
+pub fn walk_order_by_item_mut<V: VisitorMut + ?Sized>(visitor: &mut V, item: &mut OrderByItem) {
+    visitor.visit_expression_mut(&mut item.expr);
+}
+
+/// See [`walk_expression`]
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::Literal(value) => visitor.visit_literal_mut(value),
+        Expression::Parameter(parameter) => visitor.visit_parameter_mut(parameter),
+        Expression::Column(column) => visitor.visit_column_ref_mut(column),
+        Expression::Function(call) => visitor.visit_function_call_mut(call),
+        Expression::Subquery(query) | Expression::Exists(query) => {
+            visitor.visit_compound_select_mut(query)
+        }
+        Expression::InSubquery { expr, subquery, .. } => {
+            visitor.visit_expression_mut(expr);
+            visitor.visit_compound_select_mut(subquery);
+        }
+        Expression::Window(window) => {
+            visitor.visit_function_call_mut(&mut window.call);
+            visitor.visit_window_spec_mut(&mut window.window);
+        }
+        expr => {
+            for child in expr.children_mut() {
+                visitor.visit_expression_mut(child);
+            }
+        }
+    }
+}
+
+pub fn walk_function_call_mut<V: VisitorMut + ?Sized>(visitor: &mut V, call: &mut FunctionCall) {
+    if let FunctionArgs::List(args) = &mut call.args {
+        for arg in args {
+            visitor.visit_expression_mut(arg);
+        }
+    }
+}
+
+pub fn walk_window_spec_mut<V: VisitorMut + ?Sized>(visitor: &mut V, window: &mut WindowSpec) {
+    for expr in &mut window.partition_by {
+        visitor.visit_expression_mut(expr);
+    }
+    for item in &mut window.order_by {
+        visitor.visit_order_by_item_mut(item);
+    }
+}
+
+pub fn walk_create_mut<V: VisitorMut + ?Sized>(visitor: &mut V, create: &mut CreateStatement) {
+    visitor.visit_table_name_mut(&mut create.table);
+    for column in &mut create.columns {
+        visitor.visit_column_definition_mut(column);
+    }
+}
+
+pub fn walk_insert_mut<V: VisitorMut + ?Sized>(visitor: &mut V, insert: &mut InsertStatement) {
+    visitor.visit_table_name_mut(&mut insert.table);
+    for expr in &mut insert.values {
+        visitor.visit_expression_mut(expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::SqlQuery,
+        commands::SelectStatement,
+        expr::{BinaryOperator, ColumnRef, Expression, Parameter},
+    };
+
+    use super::{walk_select_mut, Visitor, VisitorMut};
+
+    #[derive(Default)]
+    struct Access {
+        tables: Vec<String>,
+        columns: Vec<String>,
+        parameters: usize,
+    }
+
+    impl Visitor for Access {
+        fn visit_table_name(&mut self, name: &str) {
+            self.tables.push(name.to_owned());
+        }
+
+        fn visit_column_ref(&mut self, column: &ColumnRef) {
+            self.columns.push(column.to_string());
+        }
+
+        fn visit_parameter(&mut self, _parameter: &Parameter) {
+            self.parameters += 1;
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let query = SqlQuery::parse_statement(
+            "with recent as (select id from orders where at > ?) \
+             select c.name, count(*) over (partition by c.region order by c.id) \
+             from customers c join recent r on c.id = r.id \
+             where exists (select 1 from bans where bans.id = c.id) \
+             and c.tier in (select tier from tiers) \
+             order by c.name limit :n",
+        )
+        .unwrap();
+        let mut access = Access::default();
+        access.visit_query(&query);
+
+        assert_eq!(
+            access.tables,
+            ["orders", "customers", "recent", "bans", "tiers"]
+        );
+        assert_eq!(
+            access.columns,
+            [
+                "id", "at", "c.name", "c.region", "c.id", "c.id", "r.id", "bans.id", "c.id",
+                "c.tier", "tier", "c.name"
+            ]
+        );
+        assert_eq!(access.parameters, 2);
+    }
+
+    /// Renames tables and restricts every select to one tenant
+    struct Tenant;
+
+    impl VisitorMut for Tenant {
+        fn visit_table_name_mut(&mut self, name: &mut String) {
+            if name == "users" {
+                *name = "accounts".to_owned();
+            }
+        }
+
+        fn visit_select_mut(&mut self, select: &mut SelectStatement) {
+            walk_select_mut(self, select);
+            let filter = Expression::binary(
+                Expression::column("tenant"),
+                BinaryOperator::Eq,
+                Expression::Literal(7.into()),
+            );
+            select.where_clause = Some(match select.where_clause.take() {
+                Some(condition) => Expression::binary(condition, BinaryOperator::And, filter),
+                None => filter,
+            });
+        }
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut query = SqlQuery::parse_statement(
+            "select name from users where id in (select id from admins where a or b)",
+        )
+        .unwrap();
+        Tenant.visit_query_mut(&mut query);
+        assert_eq!(
+            query.to_string(),
+            "SELECT name FROM accounts WHERE id IN (SELECT id FROM admins \
+             WHERE (a OR b) AND tenant = 7) AND tenant = 7"
+        );
+
+        let mut insert = SqlQuery::parse_statement("insert into users values 1").unwrap();
+        Tenant.visit_query_mut(&mut insert);
+        let SqlQuery::Insert(insert) = insert else {
+            unreachable!()
+        };
+        assert_eq!(insert.table, "accounts");
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use nom::{
+    branch::alt,
+    combinator::{map, opt, value},
+    error::context,
+    sequence::{preceded, separated_pair, terminated},
+    Parser,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    commands::OrderByItem,
+    display::fmt_list,
+    expr::{Expression, FunctionCall},
+    lexer::{Keyword, Symbol, TokenKind},
+    parse::{comma_sep, keyword, symbol, token, Parse, ParseResult, Tokens},
+};
+
+/// One end of a window frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    /// `<n> PRECEDING`, n rows before the current one
+    Preceding(u64),
+    CurrentRow,
+    /// `<n> FOLLOWING`, n rows after the current one
+    Following(u64),
+    UnboundedFollowing,
+}
+
+impl fmt::Display for FrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            Self::Preceding(n) => write!(f, "{n} PRECEDING"),
+            Self::CurrentRow => write!(f, "CURRENT ROW"),
+            Self::Following(n) => write!(f, "{n} FOLLOWING"),
+            Self::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+// parses "UNBOUNDED PRECEDING | <n> PRECEDING | CURRENT ROW | <n> FOLLOWING | UNBOUNDED FOLLOWING"
+impl Parse for FrameBound {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let offset = || {
+            token(|token| match token.kind {
+                TokenKind::Number => token.text.parse::<u64>().ok(),
+                _ => None,
+            })
+        };
+
+        context(
+            "Frame Bound",
+            alt((
+                value(
+                    Self::UnboundedPreceding,
+                    (keyword(Keyword::Unbounded), keyword(Keyword::Preceding)),
+                ),
+                value(
+                    Self::UnboundedFollowing,
+                    (keyword(Keyword::Unbounded), keyword(Keyword::Following)),
+                ),
+                value(
+                    Self::CurrentRow,
+                    (keyword(Keyword::Current), keyword(Keyword::Row)),
+                ),
+                map(
+                    terminated(offset(), keyword(Keyword::Preceding)),
+                    Self::Preceding,
+                ),
+                map(
+                    terminated(offset(), keyword(Keyword::Following)),
+                    Self::Following,
+                ),
+            )),
+        )
+        .parse(input)
+    }
+}
+
+/// `ROWS BETWEEN <start> AND <end>`, the rows of the partition an aggregate
+/// or `FIRST_VALUE` looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WindowFrame {
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+impl fmt::Display for WindowFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ROWS BETWEEN {} AND {}", self.start, self.end)
+    }
+}
+
+// parses "ROWS BETWEEN <bound> AND <bound> | ROWS <bound>", the short form
+// ending at the current row
+impl Parse for WindowFrame {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        context(
+            "Window Frame",
+            preceded(
+                keyword(Keyword::Rows),
+                alt((
+                    map(
+                        preceded(
+                            keyword(Keyword::Between),
+                            separated_pair(
+                                FrameBound::parse,
+                                keyword(Keyword::And),
+                                FrameBound::parse,
+                            ),
+                        ),
+                        |(start, end)| Self { start, end },
+                    ),
+                    map(FrameBound::parse, |start| Self {
+                        start,
+                        end: FrameBound::CurrentRow,
+                    }),
+                )),
+            ),
+        )
+        .parse(input)
+    }
+}
+
+/// `([PARTITION BY <expr>, ...] [ORDER BY <item>, ...] [<frame>])`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WindowSpec {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<OrderByItem>,
+    /// Without a frame the window is the whole partition, or with an
+    /// ORDER BY everything up to the last row sorting equal to the current one
+    pub frame: Option<WindowFrame>,
+}
+
+impl fmt::Display for WindowSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        let mut separator = "";
+        if !self.partition_by.is_empty() {
+            write!(f, "PARTITION BY ")?;
+            fmt_list(f, &self.partition_by)?;
+            separator = " ";
+        }
+        if !self.order_by.is_empty() {
+            write!(f, "{separator}ORDER BY ")?;
+            fmt_list(f, &self.order_by)?;
+            separator = " ";
+        }
+        if let Some(frame) = &self.frame {
+            write!(f, "{separator}{frame}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Parse for WindowSpec {
+    fn parse<'a>(input: Tokens<'a>) -> ParseResult<'a, Self> {
+        let by = |word| (keyword(word), keyword(Keyword::By));
+
+        let (remaining_input, (_, partition_by, order_by, frame, _)) = context(
+            "Window",
+            (
+                symbol(Symbol::LeftParen),
+                opt(preceded(
+                    by(Keyword::Partition),
+                    comma_sep(Expression::parse),
+                )),
+                opt(preceded(by(Keyword::Order), comma_sep(OrderByItem::parse))),
+                opt(WindowFrame::parse),
+                symbol(Symbol::RightParen),
+            ),
+        )
+        .parse(input)?;
+
+        Ok((
+            remaining_input,
+            Self {
+                partition_by: partition_by.unwrap_or_default(),
+                order_by: order_by.unwrap_or_default(),
+                frame,
+            },
+        ))
+    }
+}
+
+/// `<call> OVER (<window>)`, a function computed over a set of rows related
+/// to the current one without collapsing them
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WindowFunction {
+    pub call: FunctionCall,
+    pub window: WindowSpec,
+}
+
+impl fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} OVER {}", self.call, self.window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        expr::{Expression, FunctionArgs},
+        parse::Parse,
+    };
+
+    use super::{FrameBound, WindowFrame, WindowSpec};
+
+    #[test]
+    fn test_window_spec() {
+        let sql =
+            "(PARTITION BY dept ORDER BY salary DESC ROWS BETWEEN 2 PRECEDING AND CURRENT ROW)";
+        let (remaining, spec) = WindowSpec::parse_from_raw(sql).unwrap();
+
+        assert!(remaining.is_empty());
+        assert_eq!(spec.partition_by, vec![Expression::column("dept")]);
+        assert!(spec.order_by[0].descending);
+        assert_eq!(
+            spec.frame,
+            Some(WindowFrame {
+                start: FrameBound::Preceding(2),
+                end: FrameBound::CurrentRow,
+            })
+        );
+        assert_eq!(spec.to_string(), sql);
+
+        let (_, spec) = WindowSpec::parse_from_raw("( )").unwrap();
+        assert_eq!(spec, WindowSpec::default());
+
+        // the short form of a frame ends at the current row
+        let (_, spec) = WindowSpec::parse_from_raw("(ROWS UNBOUNDED PRECEDING)").unwrap();
+        assert_eq!(
+            spec.frame,
+            Some(WindowFrame {
+                start: FrameBound::UnboundedPreceding,
+                end: FrameBound::CurrentRow,
+            })
+        );
+    }
+
+    #[test]
+    fn test_window_function() {
+        let (_, expr) = Expression::parse_from_raw("rank() OVER (ORDER BY score) + 1").unwrap();
+
+        let Expression::Binary { left, .. } = &expr else {
+            panic!("expected a binary expression, got {expr:?}");
+        };
+        let Expression::Window(window) = left.as_ref() else {
+            panic!("expected a window function, got {left:?}");
+        };
+        assert_eq!(window.call.name, "rank");
+        assert_eq!(window.call.args, FunctionArgs::List(vec![]));
+        assert_eq!(expr.to_string(), "rank() OVER (ORDER BY score) + 1");
+    }
+}
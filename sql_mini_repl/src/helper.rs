@@ -0,0 +1,211 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use rustyline::{
+    completion::Completer,
+    highlight::{CmdKind, Highlighter},
+    hint::Hinter,
+    validate::Validator,
+    Cmd, ConditionalEventHandler, Context, Event, EventContext, Helper, Movement, RepeatCount,
+    Result,
+};
+use sql_mini_execution::Execution;
+use sql_mini_parser::lexer::{tokenize, Keyword, TokenKind};
+
+const KEYWORD_STYLE: &str = "\x1b[1;34m";
+const STRING_STYLE: &str = "\x1b[32m";
+const NUMBER_STYLE: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Completion and highlighting for the prompt
+#[derive(Debug, Default)]
+pub struct SqlHelper {
+    /// Table name to column names, as of the last [`SqlHelper::refresh`]
+    tables: BTreeMap<String, Vec<String>>,
+}
+
+impl SqlHelper {
+    /// Pick up the tables and columns of `exec`, call after every statement
+    /// that may change them
+    pub fn refresh(&mut self, exec: &Execution) {
+        self.tables = exec
+            .table_names()
+            .into_iter()
+            .filter_map(|table| exec.describe(table))
+            .map(|create| {
+                let columns = create.columns.into_iter().map(|c| c.name).collect();
+                (create.table, columns)
+            })
+            .collect();
+    }
+
+    /// Every word that can complete `prefix`, `table` is the name before a
+    /// `.` if there is one
+    fn candidates(&self, prefix: &str, table: Option<&str>) -> Vec<String> {
+        let matches = |word: &str| {
+            word.len() >= prefix.len()
+                && word.is_char_boundary(prefix.len())
+                && word[..prefix.len()].eq_ignore_ascii_case(prefix)
+        };
+        if let Some(table) = table {
+            let columns: BTreeSet<_> = match self.tables.get(table) {
+                Some(columns) => columns.iter().collect(),
+                // an alias, or a table not created yet
+                None => self.tables.values().flatten().collect(),
+            };
+            return columns
+                .into_iter()
+                .filter(|column| matches(column))
+                .cloned()
+                .collect();
+        }
+
+        let upper = prefix.starts_with(|c: char| c.is_uppercase());
+        let keywords = Keyword::ALL
+            .iter()
+            .map(|keyword| {
+                if upper {
+                    keyword.to_string()
+                } else {
+                    keyword.as_str().to_owned()
+                }
+            })
+            .filter(|keyword| matches(keyword));
+        let names: BTreeSet<_> = self
+            .tables
+            .iter()
+            .flat_map(|(table, columns)| [table].into_iter().chain(columns))
+            .filter(|name| matches(name))
+            .cloned()
+            .collect();
+        keywords.chain(names).collect()
+    }
+}
+
+impl Completer for SqlHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Result<(usize, Vec<String>)> {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+        let before = &line[..pos];
+        let start = before.rfind(|c: char| !is_word_char(c)).map_or(0, |i| {
+            i + before[i..].chars().next().map_or(1, char::len_utf8)
+        });
+        let table = before[..start].strip_suffix('.').map(|qualifier| {
+            let table_start = qualifier
+                .rfind(|c: char| !is_word_char(c))
+                .map_or(0, |i| i + 1);
+            &qualifier[table_start..]
+        });
+        Ok((start, self.candidates(&before[start..], table)))
+    }
+}
+
+impl Highlighter for SqlHelper {
+    /// Colors keywords, strings and numbers, input that can not be tokenized
+    /// yet, like an open string, is left as it is
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let Ok(tokens) = tokenize(line) else {
+            return Cow::Borrowed(line);
+        };
+        let mut highlighted = String::with_capacity(line.len());
+        let mut written = 0;
+        for token in tokens {
+            let style = match token.kind {
+                TokenKind::Keyword(_) => KEYWORD_STYLE,
+                TokenKind::String => STRING_STYLE,
+                TokenKind::Number => NUMBER_STYLE,
+                _ => continue,
+            };
+            highlighted.push_str(&line[written..token.offset]);
+            highlighted.push_str(style);
+            highlighted.push_str(token.text);
+            highlighted.push_str(RESET);
+            written = token.end();
+        }
+        highlighted.push_str(&line[written..]);
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        kind != CmdKind::MoveCursor
+    }
+}
+
+/// Every line is accepted, the prompt gathers the lines of a statement so it
+/// can show a continuation prompt
+impl Validator for SqlHelper {}
+
+impl Hinter for SqlHelper {
+    type Hint = String;
+}
+
+/// Ctrl-C clears the line being typed, and only interrupts the prompt on an
+/// empty one
+pub struct ClearLine;
+
+impl ConditionalEventHandler for ClearLine {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        (!ctx.line().is_empty()).then_some(Cmd::Kill(Movement::WholeBuffer))
+    }
+}
+
+impl Helper for SqlHelper {}
+
+#[cfg(test)]
+mod tests {
+    use rustyline::{
+        completion::Completer, highlight::Highlighter, history::DefaultHistory, Context,
+    };
+    use sql_mini_execution::Execution;
+    use sql_mini_parser::ast::SqlQuery;
+
+    use super::SqlHelper;
+
+    fn helper() -> SqlHelper {
+        let mut exec = Execution::new();
+        let create = "create table people (name string, nickname string, age int);";
+        exec.run(SqlQuery::parse_statement(create).unwrap())
+            .unwrap();
+        let mut helper = SqlHelper::default();
+        helper.refresh(&exec);
+        helper
+    }
+
+    #[test]
+    fn test_complete() {
+        let helper = helper();
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        let complete = |line: &str| helper.complete(line, line.len(), &ctx).unwrap();
+
+        assert_eq!(complete("SEL"), (0, vec!["SELECT".to_owned()]));
+        assert_eq!(
+            complete("select n"),
+            (
+                7,
+                vec![
+                    "not".into(),
+                    "null".into(),
+                    "name".into(),
+                    "nickname".into()
+                ]
+            )
+        );
+        assert_eq!(complete("select * from pe"), (14, vec!["people".into()]));
+        assert_eq!(complete("select people.a"), (14, vec!["age".into()]));
+        assert_eq!(complete("select p.ni"), (9, vec!["nickname".into()]));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let helper = helper();
+        assert_eq!(
+            helper.highlight("select 'a', 1 from t", 0),
+            "\x1b[1;34mselect\x1b[0m \x1b[32m'a'\x1b[0m, \x1b[33m1\x1b[0m \x1b[1;34mfrom\x1b[0m t"
+        );
+        assert_eq!(helper.highlight("select 'open", 0), "select 'open");
+    }
+}
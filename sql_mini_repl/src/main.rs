@@ -1,74 +1,328 @@
-use rustyline::{error::ReadlineError, DefaultEditor, Result};
-use sql_mini_execution::{ExecResponse, Execution};
-use sql_mini_parser::{ast::SqlQuery, parse::Parse};
-use tabled::builder::Builder;
-
-const HISTORY_FILE: &str = "history.txt";
-fn main() -> Result<()> {
-    let mut rl = DefaultEditor::new()?;
-    if rl.load_history(HISTORY_FILE).is_err() {
-        println!("No previous history.");
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, IsTerminal},
+    mem,
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::Parser;
+use helper::{ClearLine, SqlHelper};
+use meta::MetaCommand;
+use output::Mode;
+use rustyline::{error::ReadlineError, history::DefaultHistory, Editor, EventHandler, KeyEvent};
+use shell::Shell;
+use sql_mini_execution::connection::Connection;
+use sql_mini_parser::script::is_complete;
+
+mod helper;
+mod meta;
+mod output;
+mod shell;
+
+/// Run sql against a minisql database, at a prompt or from a script
+///
+/// Without SQL or --file the statements are read from standard input when it
+/// is not a terminal, and from a prompt when it is
+#[derive(Debug, Parser)]
+#[command(name = "minisql", version)]
+struct Cli {
+    /// The database file, created when missing, without it the database only
+    /// lives in memory
+    database: Option<PathBuf>,
+
+    /// Statements to run
+    sql: Option<String>,
+
+    /// Run the statements in FILE
+    #[arg(short, long, value_name = "FILE", conflicts_with = "sql")]
+    file: Option<PathBuf>,
+
+    /// Stop at the first statement or command that fails
+    #[arg(long)]
+    bail: bool,
+
+    /// How to write results: table, box, csv, tsv, json, jsonl, line or
+    /// markdown
+    #[arg(short, long, default_value_t = Mode::Table)]
+    mode: Mode,
+
+    /// Where to keep the prompt history [default: minisql/history.txt in the
+    /// user's data directory]
+    #[arg(long, value_name = "FILE", env = "MINISQL_HISTORY")]
+    history: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let conn = match &cli.database {
+        Some(path) => match Connection::open(path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Could not open {}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Connection::open_in_memory(),
+    };
+    let mut shell = Shell::new(conn);
+    shell.format.mode = cli.mode;
+
+    let succeeded = if let Some(sql) = &cli.sql {
+        shell.run_script(sql, 1, cli.bail)
+    } else if let Some(path) = &cli.file {
+        match File::open(path) {
+            Ok(file) => run_lines(&mut shell, BufReader::new(file), cli.bail),
+            Err(e) => {
+                eprintln!("Could not read {}: {e}", path.display());
+                false
+            }
+        }
+    } else if !io::stdin().is_terminal() {
+        run_lines(&mut shell, io::stdin().lock(), cli.bail)
+    } else {
+        let history = cli
+            .history
+            .or_else(|| dirs::data_dir().map(|dir| dir.join("minisql").join("history.txt")));
+        prompt(&mut shell, history)
+    };
+    // the database is written once at the end rather than after every
+    // statement, which would make long scripts slow
+    if shell.save() && succeeded {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
     }
+}
 
-    let mut exec = Execution::new();
-    loop {
-        let readline = rl.readline(">> ");
-        match readline {
-            Ok(line) => {
-                let _ = rl.add_history_entry(line.as_str());
-                match SqlQuery::parse_from_raw(line.as_ref()) {
-                    Ok((_, query)) => {
-                        let res = exec.run(query);
-                        match res {
-                            Ok(exec_res) => display_response(exec_res),
-                            Err(e) => {
-                                eprintln!("{e:?}");
-                            }
-                        }
-                    }
-                    //TODO: better errors
-                    Err(e) => eprintln!("{e:?}"),
+/// Run the statements and commands of a script as they are read, returns
+/// whether all of them worked
+fn run_lines(shell: &mut Shell, input: impl BufRead, bail: bool) -> bool {
+    let mut succeeded = true;
+    // the lines of a statement that has no terminating semicolon yet, and
+    // the line it starts on
+    let mut pending = String::new();
+    let mut first_line = 1;
+    for (line, number) in input.lines().zip(1..) {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Could not read line {number}: {e}");
+                return false;
+            }
+        };
+        let ran = match MetaCommand::parse(&line).filter(|_| pending.is_empty()) {
+            Some(Ok(MetaCommand::Quit)) => return succeeded,
+            Some(Ok(command)) => shell.run_command(command, bail),
+            Some(Err(e)) => {
+                eprintln!("Error at line {number}: {e}");
+                false
+            }
+            None => {
+                if pending.is_empty() {
+                    first_line = number;
+                } else {
+                    pending.push('\n');
                 }
+                pending.push_str(&line);
+                !is_complete(&pending)
+                    || shell.run_script(&mem::take(&mut pending), first_line, bail)
             }
-            Err(ReadlineError::Interrupted) => {
-                println!("CTRL-C");
-                break;
+        };
+        succeeded &= ran;
+        if !ran && bail {
+            return false;
+        }
+    }
+    // the last statement needs no semicolon
+    succeeded & shell.run_script(&pending, first_line, bail)
+}
+
+/// What the prompt does after a line is read
+#[derive(Debug, PartialEq, Eq)]
+enum Step {
+    /// Read another line, the statement may not be complete yet
+    Read,
+    /// A statement or command ran, keep it in the history
+    Ran(String),
+    Quit,
+}
+
+/// Run what a line completes, `pending` holds the lines of a statement that
+/// has no terminating semicolon yet
+fn prompt_step(shell: &mut Shell, pending: &mut String, line: rustyline::Result<String>) -> Step {
+    match line {
+        // commands are only recognized at the start of a statement
+        Ok(line) => match MetaCommand::parse(&line).filter(|_| pending.is_empty()) {
+            Some(Ok(MetaCommand::Quit)) => Step::Quit,
+            Some(Ok(command)) => {
+                shell.run_command(command, false);
+                Step::Ran(line)
             }
-            Err(ReadlineError::Eof) => {
-                println!("CTRL-D");
-                break;
+            Some(Err(e)) => {
+                eprintln!("{e}");
+                Step::Ran(line)
             }
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;
+            None => {
+                if !pending.is_empty() {
+                    pending.push('\n');
+                }
+                pending.push_str(&line);
+                if !is_complete(pending) {
+                    return Step::Read;
+                }
+                let script = mem::take(pending);
+                if script.trim().is_empty() {
+                    return Step::Read;
+                }
+                shell.run_script(&script, 1, false);
+                Step::Ran(script)
             }
+        },
+        // drop the statement being typed, like a shell
+        Err(ReadlineError::Interrupted) if !pending.is_empty() => {
+            pending.clear();
+            Step::Read
+        }
+        Err(ReadlineError::Interrupted) => {
+            println!("CTRL-C");
+            Step::Quit
+        }
+        Err(ReadlineError::Eof) => {
+            // run what is left, the last statement needs no semicolon
+            shell.run_script(&mem::take(pending), 1, false);
+            println!("CTRL-D");
+            Step::Quit
+        }
+        Err(err) => {
+            println!("Error: {:?}", err);
+            Step::Quit
         }
     }
-
-    let _ = rl.save_history(HISTORY_FILE);
-    Ok(())
 }
 
-fn display_response(response: ExecResponse) {
-    match response {
-        ExecResponse::Select(table_iter) => {
-            let mut builder = Builder::default();
-            let column_names: Vec<String> = table_iter
-                .columns
-                .iter()
-                .map(|col| col.name.clone())
-                .collect();
-
-            builder.push_record(column_names.iter());
-
-            table_iter.for_each(|row| {
-                builder.push_record(column_names.iter().filter_map(|col_name| {
-                    row.get(&col_name).map(|col_val| col_val.to_string()).ok()
-                }))
-            });
-
-            println!("{}", builder.build());
+/// Read statements and commands from a prompt until the user quits
+fn prompt(shell: &mut Shell, history: Option<PathBuf>) -> bool {
+    let mut rl = match Editor::<SqlHelper, DefaultHistory>::new() {
+        Ok(rl) => rl,
+        Err(e) => {
+            eprintln!("Could not start the prompt: {e}");
+            return false;
+        }
+    };
+    rl.set_helper(Some(SqlHelper::default()));
+    rl.bind_sequence(
+        KeyEvent::ctrl('C'),
+        EventHandler::Conditional(Box::new(ClearLine)),
+    );
+    if let Some(path) = &history {
+        if rl.load_history(path).is_err() {
+            println!("No previous history.");
+        }
+    }
+
+    let mut pending = String::new();
+    loop {
+        if let Some(helper) = rl.helper_mut() {
+            helper.refresh(shell.conn.execution());
+        }
+        let prompt = if pending.is_empty() { ">> " } else { "...> " };
+        let line = rl.readline(prompt);
+        match prompt_step(shell, &mut pending, line) {
+            Step::Read => continue,
+            Step::Ran(input) => {
+                // a statement over several lines is one entry
+                let _ = rl.add_history_entry(input);
+            }
+            Step::Quit => break,
         }
-        _ => println!("{response}"),
+        shell.save();
+    }
+
+    if let Some(path) = &history {
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| rl.save_history(path).map_err(io::Error::other));
+        if let Err(e) = saved {
+            eprintln!("Could not save the history to {}: {e}", path.display());
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use rustyline::error::ReadlineError;
+    use sql_mini_execution::connection::Connection;
+
+    use crate::{output::Mode, prompt_step, run_lines, shell::Shell, Step};
+
+    #[test]
+    fn test_run_lines() {
+        let script = "create table t (a int);\n\
+                      insert into t values 1;\n\
+                      insert into t values 'x', 2;\n\
+                      .mode jsonl\n\
+                      insert into t\n  values 3";
+        let count = |shell: &mut Shell| {
+            let rows = shell.conn.query("select a from t;", &[]).unwrap();
+            rows.count()
+        };
+
+        let mut shell = Shell::new(Connection::open_in_memory());
+        assert!(!run_lines(&mut shell, script.as_bytes(), false));
+        assert_eq!(count(&mut shell), 2);
+        assert_eq!(shell.format.mode, Mode::Jsonl);
+
+        let mut shell = Shell::new(Connection::open_in_memory());
+        assert!(!run_lines(&mut shell, script.as_bytes(), true));
+        assert_eq!(count(&mut shell), 1);
+
+        // a failed statement does not stop the rest of its line without bail
+        let script = "select * from nope; create table t (a int);\n\
+                      insert into t values 1;";
+        let mut shell = Shell::new(Connection::open_in_memory());
+        assert!(!run_lines(&mut shell, script.as_bytes(), false));
+        assert_eq!(count(&mut shell), 1);
+
+        let mut shell = Shell::new(Connection::open_in_memory());
+        assert!(!run_lines(&mut shell, script.as_bytes(), true));
+        assert!(shell.conn.execution().table_names().is_empty());
+
+        let mut shell = Shell::new(Connection::open_in_memory());
+        let script = "create table t (a int);\n.quit\nselect nope;";
+        assert!(run_lines(&mut shell, script.as_bytes(), true));
+    }
+
+    #[test]
+    fn test_prompt_step() {
+        let mut shell = Shell::new(Connection::open_in_memory());
+        let mut pending = String::new();
+        let mut step =
+            |shell: &mut Shell, line: &str| prompt_step(shell, &mut pending, Ok(line.to_owned()));
+        assert_eq!(step(&mut shell, "create table t ("), Step::Read);
+        assert_eq!(
+            step(&mut shell, "a int);"),
+            Step::Ran("create table t (\na int);".into())
+        );
+        assert_eq!(step(&mut shell, ".quit"), Step::Quit);
+
+        // ctrl-c drops the statement being typed and the prompt goes on
+        let mut pending = String::new();
+        let step = prompt_step(&mut shell, &mut pending, Ok("insert into t".into()));
+        assert_eq!(step, Step::Read);
+        let step = prompt_step(&mut shell, &mut pending, Err(ReadlineError::Interrupted));
+        assert_eq!(step, Step::Read);
+        assert!(pending.is_empty());
+        let step = prompt_step(&mut shell, &mut pending, Ok("values 1;".into()));
+        assert_eq!(step, Step::Ran("values 1;".into()));
+        assert_eq!(
+            shell.conn.query("select a from t;", &[]).unwrap().count(),
+            0
+        );
+
+        let step = prompt_step(&mut shell, &mut pending, Err(ReadlineError::Interrupted));
+        assert_eq!(step, Step::Quit);
     }
 }
@@ -0,0 +1,157 @@
+use crate::output::Mode;
+
+/// A `.command` typed at the prompt instead of sql
+#[derive(Debug, PartialEq, Eq)]
+pub enum MetaCommand {
+    /// List the tables
+    Tables,
+    /// Print the statements creating one table, or every table
+    Schema(Option<String>),
+    /// Run the statements of a file
+    Read(String),
+    /// Set how results are written, or show it
+    Mode(Option<Mode>),
+    /// Write the column names or not
+    Headers(bool),
+    /// How nulls are written
+    NullValue(String),
+    /// Write results to a file, or back to standard output
+    Output(Option<String>),
+    /// Append the rows of a CSV file to a table
+    Import {
+        path: String,
+        table: String,
+        /// The first line names the columns and is skipped
+        header: bool,
+    },
+    Help,
+    Quit,
+}
+
+pub const HELP: &str = "\
+.headers on|off   Show or hide column names, markdown tables always show
+                  them
+.help             Show this message
+.import [--header] FILE TABLE
+                  Append the rows of the CSV file FILE to TABLE, TSV in
+                  tsv mode, --header skips the first line
+.mode [MODE]      Set the output mode, one of table, box, csv, tsv, json,
+                  jsonl, line or markdown, or show the current one
+.nullvalue TEXT   Write nulls as TEXT
+.output [FILE]    Write results to FILE, or to the screen without FILE
+.quit             Exit, also .exit
+.read FILE        Run the statements in FILE
+.schema [TABLE]   Show the CREATE statements of TABLE or of every table
+.tables           List the tables";
+
+impl MetaCommand {
+    /// `None` if `line` is sql rather than a command, an error message if it
+    /// is a command that is unknown or has the wrong arguments
+    pub fn parse(line: &str) -> Option<Result<Self, String>> {
+        let line = line.trim();
+        let rest = line.strip_prefix('.')?;
+        let (name, arg) = match rest.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, Some(arg.trim()).filter(|arg| !arg.is_empty())),
+            None => (rest, None),
+        };
+        let command = match (name, arg) {
+            ("tables", None) => Ok(Self::Tables),
+            ("schema", table) => Ok(Self::Schema(table.map(str::to_owned))),
+            ("read", Some(path)) => Ok(Self::Read(path.to_owned())),
+            ("read", None) => Err("Usage: .read FILE".to_owned()),
+            ("mode", mode) => mode.map(str::parse).transpose().map(Self::Mode),
+            ("headers", Some("on")) => Ok(Self::Headers(true)),
+            ("headers", Some("off")) => Ok(Self::Headers(false)),
+            ("headers", _) => Err("Usage: .headers on|off".to_owned()),
+            ("nullvalue", Some(text)) => Ok(Self::NullValue(text.to_owned())),
+            ("nullvalue", None) => Err("Usage: .nullvalue TEXT".to_owned()),
+            ("output", path) => Ok(Self::Output(path.map(str::to_owned))),
+            ("import", Some(args)) => Self::import(args),
+            ("import", None) => Err(IMPORT_USAGE.to_owned()),
+            ("help", None) => Ok(Self::Help),
+            ("quit" | "exit", None) => Ok(Self::Quit),
+            ("tables" | "help" | "quit" | "exit", Some(_)) => {
+                Err(format!(".{name} takes no arguments"))
+            }
+            _ => Err(format!(
+                "Unknown command {line}, enter .help for the list of commands"
+            )),
+        };
+        Some(command)
+    }
+
+    fn import(args: &str) -> Result<Self, String> {
+        let (header, args) = match args.strip_prefix("--header") {
+            Some(rest) if rest.starts_with(char::is_whitespace) => (true, rest),
+            _ => (false, args),
+        };
+        match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [path, table] => Ok(Self::Import {
+                path: path.to_owned(),
+                table: table.to_owned(),
+                header,
+            }),
+            _ => Err(IMPORT_USAGE.to_owned()),
+        }
+    }
+}
+
+const IMPORT_USAGE: &str = "Usage: .import [--header] FILE TABLE";
+
+#[cfg(test)]
+mod tests {
+    use crate::output::Mode;
+
+    use super::MetaCommand;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(MetaCommand::parse("select 1;"), None);
+        assert_eq!(
+            MetaCommand::parse(" .tables "),
+            Some(Ok(MetaCommand::Tables))
+        );
+        assert_eq!(
+            MetaCommand::parse(".schema people"),
+            Some(Ok(MetaCommand::Schema(Some("people".to_owned()))))
+        );
+        assert_eq!(
+            MetaCommand::parse(".schema"),
+            Some(Ok(MetaCommand::Schema(None)))
+        );
+        assert_eq!(
+            MetaCommand::parse(".read  init.sql"),
+            Some(Ok(MetaCommand::Read("init.sql".to_owned())))
+        );
+        assert_eq!(MetaCommand::parse(".exit"), Some(Ok(MetaCommand::Quit)));
+        assert_eq!(
+            MetaCommand::parse(".mode json"),
+            Some(Ok(MetaCommand::Mode(Some(Mode::Json))))
+        );
+        assert_eq!(
+            MetaCommand::parse(".headers off"),
+            Some(Ok(MetaCommand::Headers(false)))
+        );
+        assert_eq!(
+            MetaCommand::parse(".output"),
+            Some(Ok(MetaCommand::Output(None)))
+        );
+        assert_eq!(
+            MetaCommand::parse(".import --header people.csv people"),
+            Some(Ok(MetaCommand::Import {
+                path: "people.csv".to_owned(),
+                table: "people".to_owned(),
+                header: true
+            }))
+        );
+        assert!(matches!(
+            MetaCommand::parse(".import people.csv"),
+            Some(Err(_))
+        ));
+        assert!(matches!(MetaCommand::parse(".mode yaml"), Some(Err(_))));
+        assert!(matches!(MetaCommand::parse(".headers yes"), Some(Err(_))));
+        assert!(matches!(MetaCommand::parse(".read"), Some(Err(_))));
+        assert!(matches!(MetaCommand::parse(".tables x"), Some(Err(_))));
+        assert!(matches!(MetaCommand::parse(".drop"), Some(Err(_))));
+    }
+}
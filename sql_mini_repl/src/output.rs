@@ -0,0 +1,332 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use sql_mini_execution::result::ResultSet;
+use sql_mini_parser::value::Value;
+use tabled::{builder::Builder, settings::Style, Table};
+
+/// How results are written, chosen with `.mode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// An ascii table
+    #[default]
+    Table,
+    /// A table drawn with box characters
+    Box,
+    Csv,
+    /// Tab separated values
+    Tsv,
+    /// One array of objects
+    Json,
+    /// One object per line
+    Jsonl,
+    /// One `column = value` line per column, rows separated by a blank line
+    Line,
+    Markdown,
+}
+
+impl Mode {
+    const ALL: [Mode; 8] = [
+        Mode::Table,
+        Mode::Box,
+        Mode::Csv,
+        Mode::Tsv,
+        Mode::Json,
+        Mode::Jsonl,
+        Mode::Line,
+        Mode::Markdown,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Table => "table",
+            Mode::Box => "box",
+            Mode::Csv => "csv",
+            Mode::Tsv => "tsv",
+            Mode::Json => "json",
+            Mode::Jsonl => "jsonl",
+            Mode::Line => "line",
+            Mode::Markdown => "markdown",
+        }
+    }
+
+    /// Whether the mode is meant for people rather than other programs,
+    /// which should only get the rows
+    pub fn is_for_people(self) -> bool {
+        matches!(self, Mode::Table | Mode::Box)
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|mode| mode.name()).collect();
+                format!("Unknown mode {s}, use one of {}", names.join(", "))
+            })
+    }
+}
+
+/// The settings for writing results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Format {
+    pub mode: Mode,
+    /// Write the column names, ignored by the json and line modes which
+    /// always name each value and by markdown, whose tables need a header
+    pub headers: bool,
+    /// How a null is written, except in json where it is `null`
+    pub null_value: String,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            headers: true,
+            null_value: Value::Null.to_string(),
+        }
+    }
+}
+
+impl Format {
+    pub fn write(&self, out: &mut dyn Write, result: &ResultSet) -> io::Result<()> {
+        match self.mode {
+            Mode::Table => writeln!(
+                out,
+                "{}",
+                self.table(result, self.headers).with(Style::ascii())
+            ),
+            Mode::Box => writeln!(
+                out,
+                "{}",
+                self.table(result, self.headers).with(Style::sharp())
+            ),
+            Mode::Markdown => writeln!(out, "{}", self.table(result, true).with(Style::markdown())),
+            Mode::Csv => self.write_separated(out, result, b','),
+            Mode::Tsv => self.write_separated(out, result, b'\t'),
+            Mode::Json => {
+                write!(out, "[")?;
+                for (i, row) in result.rows().iter().enumerate() {
+                    if i > 0 {
+                        writeln!(out, ",")?;
+                    }
+                    write!(out, "{}", json_object(&result.columns, row.values()))?;
+                }
+                writeln!(out, "]")
+            }
+            Mode::Jsonl => {
+                for row in result.rows() {
+                    writeln!(out, "{}", json_object(&result.columns, row.values()))?;
+                }
+                Ok(())
+            }
+            Mode::Line => {
+                let width = result.columns.iter().map(|c| c.chars().count()).max();
+                for (i, row) in result.rows().iter().enumerate() {
+                    if i > 0 {
+                        writeln!(out)?;
+                    }
+                    for (column, value) in result.columns.iter().zip(row.values()) {
+                        let width = width.unwrap_or_default();
+                        writeln!(out, "{column:>width$} = {}", self.text(value))?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn text(&self, value: &Value) -> String {
+        let text = match value {
+            Value::Null => self.null_value.clone(),
+            value => value.to_string(),
+        };
+        self.escape(text)
+    }
+
+    /// Keep a name or value from breaking out of the cell it is written in
+    fn escape(&self, text: String) -> String {
+        match self.mode {
+            // a pipe would end the cell and a newline the row
+            Mode::Markdown => text.replace('|', "\\|").replace('\n', "<br>"),
+            // tsv has no quoting, so the separators are written as escapes
+            Mode::Tsv => text
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+            _ => text,
+        }
+    }
+
+    fn table(&self, result: &ResultSet, headers: bool) -> Table {
+        let mut builder = Builder::default();
+        if headers {
+            builder.push_record(result.columns.iter().map(|c| self.escape(c.clone())));
+        }
+        for row in result.rows() {
+            builder.push_record(row.values().iter().map(|value| self.text(value)));
+        }
+        builder.build()
+    }
+
+    fn write_separated(
+        &self,
+        out: &mut dyn Write,
+        result: &ResultSet,
+        delimiter: u8,
+    ) -> io::Result<()> {
+        let quote_style = match self.mode {
+            Mode::Tsv => csv::QuoteStyle::Never,
+            _ => csv::QuoteStyle::Necessary,
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote_style(quote_style)
+            .from_writer(out);
+        if self.headers {
+            writer.write_record(result.columns.iter().map(|c| self.escape(c.clone())))?;
+        }
+        for row in result.rows() {
+            writer.write_record(row.values().iter().map(|value| self.text(value)))?;
+        }
+        writer.flush()
+    }
+}
+
+/// A json object with a key per column, in the order of the columns
+fn json_object(columns: &[String], values: &[Value]) -> String {
+    let members: Vec<_> = columns
+        .iter()
+        .zip(values)
+        .map(|(column, value)| {
+            format!(
+                "{}:{}",
+                serde_json::Value::from(column.as_str()),
+                json_value(value)
+            )
+        })
+        .collect();
+    format!("{{{}}}", members.join(","))
+}
+
+fn json_value(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        // a number json can not hold is kept as its digits
+        Value::Number(n) => serde_json::Number::from_str(&n.to_string())
+            .map_or_else(|_| n.to_string().into(), serde_json::Value::Number),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use sql_mini_execution::{result::ResultSet, row::Row};
+    use sql_mini_parser::{expr::ColumnRef, value::Value};
+
+    use super::{Format, Mode};
+
+    fn result() -> ResultSet {
+        let columns: Rc<[String]> = Rc::from(["name".to_owned(), "age".to_owned()]);
+        let refs: Rc<[ColumnRef]> = columns.iter().map(ColumnRef::new).collect();
+        let rows = vec![
+            Row::new(refs.clone(), vec!["ann, \"a\"".into(), 30.into()]),
+            Row::new(refs, vec!["bob".into(), Value::Null]),
+        ];
+        ResultSet::new(columns, rows)
+    }
+
+    fn write(format: &Format) -> String {
+        let mut out = Vec::new();
+        format.write(&mut out, &result()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_modes() {
+        let mut format = Format {
+            mode: Mode::Csv,
+            ..Format::default()
+        };
+        assert_eq!(
+            write(&format),
+            "name,age\n\"ann, \"\"a\"\"\",30\nbob,NULL\n"
+        );
+
+        format.mode = Mode::Tsv;
+        format.headers = false;
+        format.null_value = String::new();
+        assert_eq!(write(&format), "ann, \"a\"\t30\nbob\t\n");
+
+        format.mode = Mode::Json;
+        assert_eq!(
+            write(&format),
+            "[{\"name\":\"ann, \\\"a\\\"\",\"age\":30},\n{\"name\":\"bob\",\"age\":null}]\n"
+        );
+
+        format.mode = Mode::Jsonl;
+        assert_eq!(
+            write(&format).lines().nth(1),
+            Some("{\"name\":\"bob\",\"age\":null}")
+        );
+
+        format.mode = Mode::Line;
+        format.null_value = "-".into();
+        assert_eq!(
+            write(&format),
+            "name = ann, \"a\"\n age = 30\n\nname = bob\n age = -\n"
+        );
+
+        // a markdown table always has a header row
+        format.mode = Mode::Markdown;
+        assert_eq!(
+            write(&format),
+            "| name     | age |\n|----------|-----|\n| ann, \"a\" | 30  |\n| bob      | -   |\n"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        let columns: Rc<[String]> = Rc::from(["a|b".to_owned()]);
+        let refs: Rc<[ColumnRef]> = columns.iter().map(ColumnRef::new).collect();
+        let rows = vec![Row::new(refs, vec!["x|y\nz\t\\".into()])];
+        let result = ResultSet::new(columns, rows);
+        let write = |mode| {
+            let format = Format {
+                mode,
+                ..Format::default()
+            };
+            let mut out = Vec::new();
+            format.write(&mut out, &result).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            write(Mode::Markdown),
+            "| a\\|b        |\n|-------------|\n| x\\|y<br>z\t\\ |\n"
+        );
+        assert_eq!(write(Mode::Tsv), "a|b\nx|y\\nz\\t\\\\\n");
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!("BOX".parse(), Ok(Mode::Box));
+        assert!("yaml".parse::<Mode>().unwrap_err().contains("markdown"));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+};
+
+use sql_mini_execution::{connection::Connection, ExecResponse};
+use sql_mini_parser::{
+    ast::SqlQuery,
+    commands::{CopyDirection, CopyOptions, CopyStatement},
+    script::parse_script,
+};
+
+use crate::{
+    meta::{MetaCommand, HELP},
+    output::{Format, Mode},
+};
+
+/// The database and the output settings of a session
+pub struct Shell {
+    pub conn: Connection,
+    pub format: Format,
+    /// Standard output, or the file given to `.output`
+    out: Box<dyn Write>,
+}
+
+impl Shell {
+    pub fn new(conn: Connection) -> Self {
+        Self {
+            conn,
+            format: Format::default(),
+            out: Box::new(io::stdout()),
+        }
+    }
+
+    /// Run a `.command`, returns whether it worked, `bail` is passed on to
+    /// [`Shell::run_script`] by `.read`
+    pub fn run_command(&mut self, command: MetaCommand, bail: bool) -> bool {
+        match self.try_run_command(command, bail) {
+            Ok(ran) => ran,
+            Err(e) => {
+                eprintln!("{e}");
+                false
+            }
+        }
+    }
+
+    fn try_run_command(&mut self, command: MetaCommand, bail: bool) -> Result<bool, String> {
+        let write_error = |e: io::Error| format!("Could not write the output: {e}");
+        let mut ran = true;
+        match command {
+            MetaCommand::Tables => {
+                let names = self.conn.execution().table_names();
+                if !names.is_empty() {
+                    writeln!(self.out, "{}", names.join("  ")).map_err(write_error)?;
+                }
+            }
+            MetaCommand::Schema(Some(table)) => match self.conn.execution().describe(&table) {
+                Some(create) => writeln!(self.out, "{create};").map_err(write_error)?,
+                None => return Err(format!("No such table: {table}")),
+            },
+            MetaCommand::Schema(None) => {
+                let exec = self.conn.execution();
+                for table in exec.table_names() {
+                    if let Some(create) = exec.describe(table) {
+                        writeln!(self.out, "{create};").map_err(write_error)?;
+                    }
+                }
+            }
+            MetaCommand::Read(path) => {
+                let script =
+                    fs::read_to_string(&path).map_err(|e| format!("Could not read {path}: {e}"))?;
+                ran = self.run_script(&script, 1, bail);
+            }
+            MetaCommand::Mode(Some(mode)) => self.format.mode = mode,
+            MetaCommand::Mode(None) => {
+                writeln!(self.out, "{}", self.format.mode).map_err(write_error)?
+            }
+            MetaCommand::Headers(headers) => self.format.headers = headers,
+            MetaCommand::NullValue(text) => self.format.null_value = text,
+            MetaCommand::Output(path) => {
+                self.out.flush().map_err(write_error)?;
+                self.out = match path {
+                    Some(path) => {
+                        let file = File::create(&path)
+                            .map_err(|e| format!("Could not write {path}: {e}"))?;
+                        Box::new(BufWriter::new(file))
+                    }
+                    None => Box::new(io::stdout()),
+                };
+            }
+            MetaCommand::Import {
+                path,
+                table,
+                header,
+            } => {
+                let delimiter = if self.format.mode == Mode::Tsv {
+                    '\t'
+                } else {
+                    ','
+                };
+                let copy = CopyStatement {
+                    table,
+                    direction: CopyDirection::From,
+                    path,
+                    options: CopyOptions { header, delimiter },
+                };
+                let response = self
+                    .conn
+                    .execute_query(SqlQuery::Copy(copy))
+                    .map_err(|e| e.to_string())?;
+                ran = self.display_response(response).map_err(write_error)?;
+            }
+            MetaCommand::Help => writeln!(self.out, "{HELP}").map_err(write_error)?,
+            MetaCommand::Quit => {}
+        }
+        self.out.flush().map_err(write_error)?;
+        Ok(ran)
+    }
+
+    /// Run every statement of `script`, stopping at the first that fails
+    /// with `bail` and going on to the next otherwise, `first_line` is the
+    /// line the script starts on in the input it came from, for error
+    /// messages
+    ///
+    /// Returns whether every statement ran
+    pub fn run_script(&mut self, script: &str, first_line: u32, bail: bool) -> bool {
+        let statements = match parse_script(script) {
+            Ok(statements) => statements,
+            Err(mut e) => {
+                e.line += first_line - 1;
+                eprintln!("{e}");
+                return false;
+            }
+        };
+        let mut succeeded = true;
+        for statement in statements {
+            let line = statement.span.line + first_line - 1;
+            let ran = match self.conn.execute_query(statement.query) {
+                Ok(response) => {
+                    let written = self.display_response(response);
+                    match written.and_then(|complete| self.out.flush().map(|()| complete)) {
+                        Ok(complete) => complete,
+                        Err(e) => {
+                            // later results could not be written either
+                            eprintln!("Could not write the result: {e}");
+                            return false;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error in statement at line {line}: {e}");
+                    false
+                }
+            };
+            succeeded &= ran;
+            if !ran && bail {
+                return false;
+            }
+        }
+        succeeded
+    }
+
+    /// Write the changes made by statements to the database file, returns
+    /// whether that worked
+    pub fn save(&mut self) -> bool {
+        match self.conn.flush() {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Could not save the database: {e}");
+                false
+            }
+        }
+    }
+
+    /// Write what a statement produced, returns false if it was a copy that
+    /// skipped lines it could not read, which are reported on standard error
+    fn display_response(&mut self, response: ExecResponse) -> io::Result<bool> {
+        let complete = match &response {
+            ExecResponse::Copy(report) => {
+                report.errors.iter().for_each(|error| eprintln!("{error}"));
+                report.errors.is_empty()
+            }
+            _ => true,
+        };
+        match response {
+            ExecResponse::Select(result) => self.format.write(&mut self.out, &result)?,
+            ExecResponse::Explain(plan) => writeln!(self.out, "{plan}")?,
+            // other programs reading the output only want rows
+            _ if !self.format.mode.is_for_people() => {}
+            _ => writeln!(self.out, "{response}")?,
+        }
+        Ok(complete)
+    }
+}